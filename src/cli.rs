@@ -1,10 +1,20 @@
+use crate::models::{ListColumn, ListSort};
 use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "twig")]
 #[command(about = "A terminal task tracking application", long_about = None)]
 pub struct Cli {
+    /// Directory for all twig data and config (overrides TWIG_HOME and XDG directories)
+    #[arg(long, global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Work on a reportee's tasks instead of your own
+    #[arg(long = "for", global = true, value_name = "REPORTEE")]
+    pub for_reportee: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
@@ -24,11 +34,11 @@ pub enum Commands {
         #[arg(short, long)]
         tags: Option<String>,
 
-        /// Estimated effort (e.g., "1h", "2d", "3w", "2m")
+        /// Estimated effort (e.g., "4h", "1h30m", "2d", "3w", "2mo")
         #[arg(short, long)]
         estimate: Option<String>,
 
-        /// Estimated completion date (YYYY-MM-DD)
+        /// Estimated completion date (e.g. 2024-03-01, friday, "next monday 5pm", +3d, "end of month")
         #[arg(long)]
         eta: Option<String>,
 
@@ -37,17 +47,64 @@ pub enum Commands {
         description: Option<String>,
     },
 
-    /// Start working on a task (interactive selector)
-    Start,
+    /// Start working on a task
+    Start {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+    },
+
+    /// Complete a task
+    Complete {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+
+        /// Complete every open task matching this filter instead (e.g. "tag:sprint-12")
+        #[arg(long, value_name = "QUERY", conflicts_with = "task")]
+        filter: Option<String>,
+    },
+
+    /// Cancel a task
+    Cancel {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
 
-    /// Complete a task (interactive selector)
-    Complete,
+        /// Cancel every open task matching this filter instead
+        #[arg(long, value_name = "QUERY", conflicts_with = "task")]
+        filter: Option<String>,
+    },
+
+    /// Pause active time tracking on a task
+    Pause {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+    },
+
+    /// Show the running timer and how long it has been running
+    Status,
 
-    /// Cancel a task (interactive selector)
-    Cancel,
+    /// Log time worked on a task without running a timer
+    Log {
+        /// Task ID, ID prefix or title
+        task: String,
 
-    /// Pause active time tracking on a task (interactive selector)
-    Pause,
+        /// Time spent (e.g. "1h30m", "45min", "0.5d")
+        duration: String,
+
+        /// When the work started, e.g. "yesterday 14:00" or "2024-03-01 09:30"
+        /// (default: so that it ends now)
+        #[arg(long, value_name = "WHEN")]
+        at: Option<String>,
+
+        /// What the time was spent on
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// List, edit or delete the time entries of a task
+    Time {
+        #[command(subcommand)]
+        command: TimeCommands,
+    },
 
     /// List tasks
     List {
@@ -56,18 +113,75 @@ pub enum Commands {
         status: Option<StatusFilter>,
 
         /// Filter by tag
-        #[arg(short, long)]
+        #[arg(short, long, value_parser = tag_option)]
         tag: Option<String>,
+
+        /// Filter terms, e.g. `status:in-progress tag:backend eta.before:friday`
+        /// (put negated terms after `--`: `-- -tag:blocked`)
+        #[arg(value_name = "FILTER")]
+        terms: Vec<String>,
+
+        /// Filter query, added to the terms (e.g. "-tag:blocked")
+        #[arg(long, value_name = "QUERY", allow_hyphen_values = true)]
+        filter: Option<String>,
+
+        /// Sort by this field (default: `list_sort` in the config, or created)
+        #[arg(long, value_enum)]
+        sort: Option<ListSort>,
+
+        /// Reverse the sort order
+        #[arg(long)]
+        reverse: bool,
+
+        /// Columns to show, comma-separated, e.g. id,title,eta,remaining (default:
+        /// `list_columns` in the config)
+        #[arg(long, value_enum, value_delimiter = ',')]
+        columns: Vec<ListColumn>,
+
+        /// Include your tasks and every reportee's
+        #[arg(long, conflicts_with = "for_reportee")]
+        all: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
-    /// Show detailed information about a task (interactive selector)
-    Show,
+    /// Show detailed information about a task
+    Show {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
 
     /// Display task tree
-    Tree,
+    Tree {
+        /// Only show tasks matching these filter terms, with their parent tasks
+        /// (put negated terms after `--`: `-- -tag:blocked`)
+        #[arg(value_name = "FILTER")]
+        terms: Vec<String>,
+
+        /// Filter query, added to the terms (e.g. "-tag:blocked")
+        #[arg(long, value_name = "QUERY", allow_hyphen_values = true)]
+        filter: Option<String>,
+
+        /// Include your tasks and every reportee's
+        #[arg(long, conflicts_with = "for_reportee")]
+        all: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
 
-    /// Update task fields (interactive selector)
+    /// Update task fields
     Update {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+
         /// New title
         #[arg(long)]
         title: Option<String>,
@@ -76,22 +190,77 @@ pub enum Commands {
         #[arg(long)]
         description: Option<String>,
 
-        /// New estimated effort (e.g., "1h", "2d", "3w", "2m")
+        /// New estimated effort (e.g., "4h", "1h30m", "2d", "3w", "2mo")
         #[arg(long)]
         estimate: Option<String>,
 
-        /// New ETA (YYYY-MM-DD)
+        /// New ETA (e.g. 2024-03-01, friday, "next monday 5pm", +3d, "end of month")
         #[arg(long)]
         eta: Option<String>,
     },
 
-    /// Delete a task (interactive selector)
-    Delete,
+    /// Delete a task
+    Delete {
+        /// Task ID, ID prefix or title (picked interactively if omitted)
+        task: Option<String>,
+
+        /// Delete every task matching this filter instead
+        #[arg(long, value_name = "QUERY", conflicts_with = "task")]
+        filter: Option<String>,
+
+        /// What to do with the task's subtasks (asks if omitted)
+        #[arg(long, value_enum)]
+        subtasks: Option<SubtaskMode>,
+    },
 
-    /// Add tags to a task (interactive selector)
+    /// Add tags to a task: `twig tag <TASK> <TAGS>...`, or `twig tag <TAG>` to pick
+    /// the task interactively
+    #[command(override_usage = "twig tag [OPTIONS] [TASK] <TAGS>...")]
     Tag {
-        /// Tags to add
-        tags: Vec<String>,
+        /// The task (ID, ID prefix or title) followed by the tags to add, or a single tag
+        #[arg(required = true, value_name = "TAGS")]
+        args: Vec<String>,
+
+        /// Add the tags to every task matching this filter; all arguments are tags
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<String>,
+    },
+
+    /// Move a task and its subtasks to a reportee's tasks (or back with "me")
+    Delegate {
+        /// Task ID, ID prefix or title
+        task: String,
+
+        /// Reportee to hand the task to, or "me"
+        to: String,
+
+        /// Leave a stub in place of the task that follows its status
+        #[arg(long)]
+        stub: bool,
+    },
+
+    /// Undo the last change to your tasks
+    Undo,
+
+    /// Redo the last undone change
+    Redo,
+
+    /// Move finished task trees out of the active task list into monthly archive files
+    Archive {
+        /// Archive top-level tasks whose whole subtree was finished before this date
+        /// (e.g. 2024-03-01, today, "last friday", "30 days ago", "start of month")
+        #[arg(long)]
+        before: String,
+
+        /// Only archive task trees whose top-level task matches this filter
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<String>,
+    },
+
+    /// List, restore or permanently remove deleted tasks
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
     },
 
     /// Manage reportees
@@ -100,15 +269,52 @@ pub enum Commands {
         command: ReporteeCommands,
     },
 
+    /// Show or migrate the data directory
+    Data {
+        #[command(subcommand)]
+        command: DataCommands,
+    },
+
+    /// Check task data for inconsistencies (orphans, cycles, time tracking errors)
+    Doctor {
+        /// Repair the problems found
+        #[arg(long)]
+        fix: bool,
+    },
+
     /// Generate reports
     Report {
         /// Report period
-        #[arg(value_enum)]
-        period: ReportPeriod,
+        #[arg(value_enum, required_unless_present_any = ["from", "last_days"])]
+        period: Option<ReportPeriod>,
 
-        /// Specific date (YYYY-MM-DD, or "today", "yesterday", "this week", etc.)
+        /// Specific date (e.g. 2024-03-01, yesterday, "last friday", "3 days ago", "this week")
         #[arg(short, long)]
         date: Option<String>,
+
+        /// Report on the days from this date instead of a period
+        #[arg(long, conflicts_with_all = ["period", "date", "last_days"])]
+        from: Option<String>,
+
+        /// Last day of a --from range (default: today)
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+
+        /// Report on the last N days, today included, instead of a period
+        #[arg(long, value_name = "N", conflicts_with_all = ["period", "date"])]
+        last_days: Option<u32>,
+
+        /// Only count tasks matching this filter (e.g. "tag:backend -status:cancelled")
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<String>,
+
+        /// Include your tasks and every reportee's
+        #[arg(long, conflicts_with = "for_reportee")]
+        all: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
     /// Show statistics
@@ -117,9 +323,33 @@ pub enum Commands {
         #[arg(value_enum)]
         period: Option<StatsPeriod>,
 
-        /// Specific date (YYYY-MM-DD, or "today", "yesterday", "this week", etc.)
+        /// Specific date (e.g. 2024-03-01, yesterday, "last friday", "3 days ago", "this week")
         #[arg(short, long)]
         date: Option<String>,
+
+        /// Count the days from this date instead of a period
+        #[arg(long, conflicts_with_all = ["period", "date", "last_days"])]
+        from: Option<String>,
+
+        /// Last day of a --from range (default: today)
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+
+        /// Count the last N days, today included, instead of a period
+        #[arg(long, value_name = "N", conflicts_with_all = ["period", "date"])]
+        last_days: Option<u32>,
+
+        /// Only count tasks matching this filter (e.g. "tag:backend -status:cancelled")
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<String>,
+
+        /// Include your tasks and every reportee's
+        #[arg(long, conflicts_with = "for_reportee")]
+        all: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
     /// Launch interactive TUI
@@ -151,6 +381,81 @@ pub enum ReporteeCommands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum TimeCommands {
+    /// List a task's time entries
+    List {
+        /// Task ID, ID prefix or title
+        task: String,
+    },
+
+    /// Change a time entry
+    Edit {
+        /// Task ID, ID prefix or title
+        task: String,
+
+        /// Entry number, as shown by 'twig time list'
+        entry: usize,
+
+        /// New start, e.g. "yesterday 14:00" or "09:30"
+        #[arg(long, value_name = "WHEN")]
+        start: Option<String>,
+
+        /// New end (also stops a running entry)
+        #[arg(long, value_name = "WHEN", conflicts_with = "duration")]
+        end: Option<String>,
+
+        /// New length, counted from the start (e.g. "1h30m")
+        #[arg(long)]
+        duration: Option<String>,
+
+        /// New note
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Delete a time entry
+    Delete {
+        /// Task ID, ID prefix or title
+        task: String,
+
+        /// Entry number, as shown by 'twig time list'
+        entry: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    /// List tasks in the trash
+    List,
+
+    /// Restore a task (and any subtasks deleted with it) from the trash
+    Restore {
+        /// Task ID, ID prefix or title of a task in the trash
+        id: String,
+    },
+
+    /// Permanently remove tasks from the trash
+    Empty {
+        /// Only remove tasks deleted longer ago than this (e.g. "30d", "2w")
+        #[arg(long, value_name = "AGE")]
+        older_than: Option<String>,
+
+        /// Only remove trashed tasks matching this filter
+        #[arg(long, value_name = "QUERY")]
+        filter: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DataCommands {
+    /// Show where tasks and configuration are stored
+    Path,
+
+    /// Move a legacy ~/.twig directory to the XDG directories (or --data-dir/TWIG_HOME)
+    Migrate,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum StatusFilter {
     NotStarted,
@@ -159,11 +464,35 @@ pub enum StatusFilter {
     Cancelled,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable tables and text
+    Table,
+    /// A JSON document
+    Json,
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SubtaskMode {
+    /// Delete the subtasks too
+    Cascade,
+    /// Move the subtasks up to the deleted task's parent
+    Promote,
+    /// Don't delete a task that has subtasks
+    Abort,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ReportPeriod {
     Daily,
     Weekly,
     Monthly,
+    Quarterly,
+    Yearly,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -171,4 +500,97 @@ pub enum StatsPeriod {
     Daily,
     Weekly,
     Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl From<StatsPeriod> for ReportPeriod {
+    fn from(period: StatsPeriod) -> Self {
+        match period {
+            StatsPeriod::Daily => ReportPeriod::Daily,
+            StatsPeriod::Weekly => ReportPeriod::Weekly,
+            StatsPeriod::Monthly => ReportPeriod::Monthly,
+            StatsPeriod::Quarterly => ReportPeriod::Quarterly,
+            StatsPeriod::Yearly => ReportPeriod::Yearly,
+        }
+    }
+}
+
+/// Parse `list --tag`, refusing what is really a negated filter term that clap
+/// split into `-t` and a value (`-tag:blocked` reads as `-t ag:blocked`).
+fn tag_option(value: &str) -> Result<String, String> {
+    if ["ag:", "itle:", "itle~"]
+        .iter()
+        .any(|p| value.starts_with(p))
+    {
+        return Err(format!(
+            "'-t{}' looks like a negated filter term; put it after `--` or in --filter",
+            value
+        ));
+    }
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_after_filter_terms() {
+        let cli = Cli::try_parse_from([
+            "twig",
+            "list",
+            "tag:backend",
+            "--format",
+            "json",
+            "--sort",
+            "title",
+            "--reverse",
+            "--",
+            "-tag:blocked",
+        ])
+        .unwrap();
+        let Some(Commands::List {
+            terms,
+            filter,
+            sort,
+            reverse,
+            format,
+            ..
+        }) = cli.command
+        else {
+            panic!("expected list");
+        };
+        assert_eq!(terms, ["tag:backend", "-tag:blocked"]);
+        assert_eq!(filter, None);
+        assert_eq!(sort, Some(ListSort::Title));
+        assert!(reverse);
+        assert!(matches!(format, OutputFormat::Json));
+
+        let cli = Cli::try_parse_from([
+            "twig",
+            "tree",
+            "tag:backend",
+            "--filter",
+            "-tag:blocked",
+            "--format",
+            "json",
+        ])
+        .unwrap();
+        let Some(Commands::Tree {
+            terms,
+            filter,
+            format,
+            ..
+        }) = cli.command
+        else {
+            panic!("expected tree");
+        };
+        assert_eq!(terms, ["tag:backend"]);
+        assert_eq!(filter.as_deref(), Some("-tag:blocked"));
+        assert!(matches!(format, OutputFormat::Json));
+
+        assert!(Cli::try_parse_from(["twig", "list", "-tag:blocked"]).is_err());
+        assert!(Cli::try_parse_from(["twig", "list", "-t", "backend"]).is_ok());
+    }
 }
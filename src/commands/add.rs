@@ -1,10 +1,12 @@
+use crate::commands::Scope;
 use crate::models::Task;
-use crate::storage::{DataPaths, Storage};
+use crate::storage::{json_store, new_task_store, DataPaths};
 use crate::utils::parse_date;
+use crate::utils::task_ref::resolve_task;
 use anyhow::{Context, Result};
-use uuid::Uuid;
 
 pub fn add_task(
+    scope: &Scope,
     title: String,
     parent: Option<String>,
     tags: Option<String>,
@@ -13,7 +15,9 @@ pub fn add_task(
     description: Option<String>,
 ) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
     let mut task = Task::new(title);
@@ -24,17 +28,10 @@ pub fn add_task(
     }
 
     // Set parent
-    if let Some(parent_id) = parent {
-        let parent_uuid = if parent_id.len() == 8 {
-            // Short ID
-            storage
-                .find_task_by_short_id(&parent_id)
-                .map(|t| t.id)
-                .context("Parent task not found")?
-        } else {
-            Uuid::parse_str(&parent_id).context("Invalid parent UUID")?
-        };
-        task.parent_id = Some(parent_uuid);
+    if let Some(parent) = parent {
+        let parent = resolve_task(&storage.get_tasks(), &parent, config.id_prefix_length)
+            .context("Parent task not found")?;
+        task.parent_id = Some(parent.id);
     }
 
     // Set tags
@@ -44,15 +41,24 @@ pub fn add_task(
 
     // Set estimate
     if let Some(est) = estimate {
-        task.set_estimate(&est)?;
+        task.set_estimate(&est, &config.work_calendar.for_owner(&owner))?;
     }
 
     // Set ETA
     if let Some(eta_str) = eta {
-        task.eta = Some(parse_date(&eta_str)?);
+        task.eta = Some(parse_date(&eta_str, &config)?);
     }
 
-    println!("✓ Task created: {} [{}]", task.title, task.short_id());
+    if owner == "me" {
+        println!("✓ Task created: {} [{}]", task.title, task.short_id());
+    } else {
+        println!(
+            "✓ Task created for {}: {} [{}]",
+            owner,
+            task.title,
+            task.short_id()
+        );
+    }
     storage.add_task(task)?;
 
     Ok(())
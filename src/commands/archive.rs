@@ -0,0 +1,40 @@
+use crate::commands::scope::owner_label;
+use crate::commands::Scope;
+use crate::storage::archive::{archive_finished, Archive};
+use crate::storage::{json_store, new_task_store, DataPaths};
+use crate::utils::filter::Filter;
+use crate::utils::{format_datetime, parse_date};
+use anyhow::Result;
+
+/// Archive finished task trees from the task lists in `scope`, only those whose
+/// top-level task matches `filter` if given.
+pub fn archive_tasks(scope: &Scope, before: String, filter: Option<&Filter>) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let before = parse_date(&before, &config)?;
+    let mut total = 0;
+
+    for owner in scope.owners(&config)? {
+        let mut storage = new_task_store(&paths, &config, &owner);
+        storage.load()?;
+        let archive = Archive::for_owner(&paths, &config, &owner);
+
+        let calendar = config.work_calendar.for_owner(&owner);
+        let archived = archive_finished(storage.as_mut(), &archive, before, |task| {
+            filter.is_none_or(|filter| filter.matches(task, &calendar))
+        })?;
+        if archived > 0 {
+            println!("✓ {}: archived {} task(s)", owner_label(&owner), archived);
+        }
+        total += archived;
+    }
+
+    if total == 0 {
+        println!(
+            "Nothing to archive: no task trees were finished before {}.",
+            format_datetime(&before, &config.timezone)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::storage::{paths, DataPaths};
+use anyhow::Result;
+
+pub fn show_data_paths() -> Result<()> {
+    let paths = DataPaths::new()?;
+
+    println!("Data directory: {}", paths.data_dir().display());
+    println!("Config file:    {}", paths.config_file().display());
+
+    Ok(())
+}
+
+pub fn migrate_data_dir() -> Result<()> {
+    match paths::migrate_legacy_dir()? {
+        Some(paths) => {
+            println!("✓ Moved ~/.twig");
+            println!("  Data directory: {}", paths.data_dir().display());
+            println!("  Config file:    {}", paths.config_file().display());
+        }
+        None => println!("Nothing to migrate."),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::commands::Scope;
+use crate::storage::delegation::move_task_tree;
+use crate::storage::{json_store, new_task_store, DataPaths};
+use crate::utils::task_ref::resolve_task;
+use anyhow::Result;
+
+/// Move a task and its subtasks to another store: a reportee's, or back to mine
+/// with `to` = "me".
+pub fn delegate_task(scope: &Scope, task: String, to: String, stub: bool) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let from_owner = scope.owner(&config)?;
+    let to_owner = if to == "me" {
+        to
+    } else {
+        Scope {
+            reportee: Some(to),
+            all: false,
+        }
+        .owner(&config)?
+    };
+    if from_owner == to_owner {
+        anyhow::bail!("The task already belongs to {}", to_owner);
+    }
+
+    let mut from = new_task_store(&paths, &config, &from_owner);
+    from.load()?;
+    let mut to = new_task_store(&paths, &config, &to_owner);
+    to.load()?;
+
+    let task = resolve_task(&from.get_tasks(), &task, config.id_prefix_length)?;
+    let (task_id, title) = (task.id, task.title.clone());
+
+    let moved = move_task_tree(
+        from.as_mut(),
+        &from_owner,
+        to.as_mut(),
+        &to_owner,
+        task_id,
+        stub,
+    )?;
+    let subtasks = match moved - 1 {
+        0 => String::new(),
+        n => format!(" with {} subtask(s)", n),
+    };
+    if to_owner == "me" {
+        println!("✓ Took back '{}'{}", title, subtasks);
+    } else {
+        println!("✓ Delegated '{}'{} to {}", title, subtasks, to_owner);
+    }
+    if stub {
+        println!("  A stub following its status was left in its place");
+    }
+
+    Ok(())
+}
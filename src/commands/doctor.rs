@@ -0,0 +1,50 @@
+use crate::commands::scope::{owner_label, Scope};
+use crate::storage::{json_store, new_task_store, DataPaths};
+use crate::utils::integrity;
+use anyhow::Result;
+
+/// Check the stores in `scope` for inconsistent data, optionally repairing
+/// what's found.
+pub fn doctor(scope: &Scope, fix: bool) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let mut found = 0;
+
+    for owner in scope.owners(&config)? {
+        let label = owner_label(&owner);
+
+        let mut storage = new_task_store(&paths, &config, &owner);
+        if let Err(err) = storage.load() {
+            println!("✗ {}: could not be loaded: {:#}", label, err);
+            found += 1;
+            continue;
+        }
+
+        let issues = if fix {
+            integrity::repair(storage.tasks_mut())
+        } else {
+            integrity::check(storage.get_all_tasks())
+        };
+
+        if issues.is_empty() {
+            println!("✓ {}: no problems found", label);
+            continue;
+        }
+
+        println!("✗ {}: {} problem(s)", label, issues.len());
+        for issue in &issues {
+            println!("  - {}", issue);
+        }
+        if fix {
+            storage.save()?;
+            println!("  ✓ Repaired (run 'twig undo' to revert)");
+        }
+        found += issues.len();
+    }
+
+    if found > 0 && !fix {
+        println!("\nRun 'twig doctor --fix' to repair them.");
+    }
+
+    Ok(())
+}
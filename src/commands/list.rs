@@ -1,18 +1,147 @@
-use crate::cli::StatusFilter;
-use crate::models::{Task, TaskStatus};
-use crate::storage::{DataPaths, Storage};
+use crate::cli::{OutputFormat, StatusFilter};
+use crate::commands::output::{self, TaskRecord};
+use crate::commands::scope::{owner_label, Scope};
+use crate::models::task::EffortEstimate;
+use crate::models::{Config, ListColumn, ListSort, Task, TaskStatus, Timezone, WorkCalendar};
+use crate::storage::delegation::sync_stubs_from_disk;
+use crate::storage::{json_store, new_task_store, DataPaths, TaskStore};
+use crate::utils::filter::Filter;
 use crate::utils::format_datetime;
+use crate::utils::task_ref::IdPrefixes;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Table};
+use std::cmp::Ordering;
 
-pub fn list_tasks(status: Option<StatusFilter>, tag: Option<String>) -> Result<()> {
+/// How `twig list` orders and lays out tasks; unset options come from the config.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    pub sort: Option<ListSort>,
+    pub reverse: bool,
+    pub columns: Vec<ListColumn>,
+}
+
+pub fn list_tasks(
+    scope: &Scope,
+    status: Option<StatusFilter>,
+    tag: Option<String>,
+    filter: &Filter,
+    options: &ListOptions,
+    format: OutputFormat,
+) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
-    storage.load()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owners = scope.owners(&config)?;
+
+    let (sort, reverse) = match options.sort {
+        Some(sort) => (sort, options.reverse),
+        None => (config.list_sort, config.list_reverse || options.reverse),
+    };
+    let columns = if options.columns.is_empty() {
+        &config.list_columns
+    } else {
+        &options.columns
+    };
+
+    let mut stores = Vec::new();
+    for owner in &owners {
+        let mut storage = new_task_store(&paths, &config, owner);
+        storage.load()?;
+        sync_stubs_from_disk(&paths, &config, storage.as_mut())?;
+        let calendar = config.work_calendar.for_owner(owner);
+        stores.push((owner.as_str(), storage, calendar));
+    }
+
+    // Structured formats print every owner's tasks as one sorted list
+    if format != OutputFormat::Table {
+        let mut tasks: Vec<(&str, &Task)> = stores
+            .iter()
+            .flat_map(|(owner, storage, calendar)| {
+                filter_tasks(storage.as_ref(), &status, &tag, filter, calendar)
+                    .into_iter()
+                    .map(move |task| (*owner, task))
+            })
+            .collect();
+        tasks.sort_by(|(_, a), (_, b)| compare_tasks(a, b, sort, reverse));
+        let records: Vec<TaskRecord> = tasks
+            .into_iter()
+            .map(|(owner, task)| TaskRecord::new(task, owner))
+            .collect();
+        return output::print_tasks(format, &records);
+    }
+
+    for (owner, storage, calendar) in &stores {
+        if owners.len() > 1 {
+            println!("\n{}", owner_label(owner));
+        }
+        let mut tasks = filter_tasks(storage.as_ref(), &status, &tag, filter, calendar);
+        tasks.sort_by(|a, b| compare_tasks(a, b, sort, reverse));
+        print_tasks(storage.as_ref(), owner, &tasks, columns, &config, calendar);
+    }
+    Ok(())
+}
 
-    let tasks = storage.get_all_tasks();
-    let filtered: Vec<&Task> = tasks
-        .iter()
+/// Order two tasks by `sort`, oldest first between equals. Tasks without the
+/// field sorted on (no ETA or estimate) go last either way.
+fn compare_tasks(a: &Task, b: &Task, sort: ListSort, reverse: bool) -> Ordering {
+    fn present<T: PartialOrd>(a: Option<T>, b: Option<T>, reverse: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => {
+                let order = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                if reverse {
+                    order.reverse()
+                } else {
+                    order
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    let status_rank = |task: &Task| match task.status {
+        TaskStatus::InProgress => 0,
+        TaskStatus::NotStarted => 1,
+        TaskStatus::Completed => 2,
+        TaskStatus::Cancelled => 3,
+    };
+
+    let order = match sort {
+        ListSort::Created => Ordering::Equal,
+        ListSort::Eta => present(a.eta, b.eta, reverse),
+        ListSort::Estimate => present(a.estimated_effort_hours, b.estimated_effort_hours, reverse),
+        ListSort::Time => present(
+            Some(a.total_time_seconds),
+            Some(b.total_time_seconds),
+            reverse,
+        ),
+        ListSort::Status => present(Some(status_rank(a)), Some(status_rank(b)), reverse),
+        ListSort::Title => present(
+            Some(a.title.to_lowercase()),
+            Some(b.title.to_lowercase()),
+            reverse,
+        ),
+    };
+    order.then_with(|| {
+        let created = a.created_at.cmp(&b.created_at);
+        if reverse && sort == ListSort::Created {
+            created.reverse()
+        } else {
+            created
+        }
+    })
+}
+
+fn filter_tasks<'a>(
+    storage: &'a dyn TaskStore,
+    status: &Option<StatusFilter>,
+    tag: &Option<String>,
+    filter: &Filter,
+    calendar: &WorkCalendar,
+) -> Vec<&'a Task> {
+    storage
+        .get_tasks()
+        .into_iter()
         .filter(|task| {
             if let Some(ref s) = status {
                 let task_status = match s {
@@ -30,57 +159,170 @@ pub fn list_tasks(status: Option<StatusFilter>, tag: Option<String>) -> Result<(
                     return false;
                 }
             }
-            true
+            filter.matches(task, calendar)
         })
-        .collect();
+        .collect()
+}
 
-    if filtered.is_empty() {
+fn print_tasks(
+    storage: &dyn TaskStore,
+    owner: &str,
+    tasks: &[&Task],
+    columns: &[ListColumn],
+    config: &Config,
+    calendar: &WorkCalendar,
+) {
+    if tasks.is_empty() {
         println!("No tasks found.");
-        return Ok(());
+        return;
     }
 
+    // Prefixes are unique across the whole store, so they still work when filtered
+    let ids = IdPrefixes::new(&storage.get_tasks(), config.id_prefix_length);
+
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec!["ID", "Status", "Title", "Tags", "Time", "Created"]);
-
-    for task in &filtered {
-        let status_str = match task.status {
-            TaskStatus::NotStarted => Cell::new("○ Not Started").fg(Color::Grey),
-            TaskStatus::InProgress => Cell::new("◐ In Progress").fg(Color::Yellow),
-            TaskStatus::Completed => Cell::new("● Completed").fg(Color::Green),
-            TaskStatus::Cancelled => Cell::new("✗ Cancelled").fg(Color::Red),
-        };
+        .set_header(columns.iter().map(|c| column_header(*c)));
 
-        let tags_str = if task.tags.is_empty() {
-            String::new()
-        } else {
-            task.tags
+    for task in tasks {
+        table.add_row(columns.iter().map(|column| {
+            cell(
+                *column,
+                task,
+                storage,
+                owner,
+                &ids,
+                calendar,
+                &config.timezone,
+            )
+        }));
+    }
+
+    println!("{}", table);
+    println!("\nTotal: {} task(s)", tasks.len());
+}
+
+fn column_header(column: ListColumn) -> &'static str {
+    match column {
+        ListColumn::Id => "ID",
+        ListColumn::Status => "Status",
+        ListColumn::Title => "Title",
+        ListColumn::Description => "Description",
+        ListColumn::Tags => "Tags",
+        ListColumn::Path => "Path",
+        ListColumn::Owner => "Owner",
+        ListColumn::Estimate => "Estimate",
+        ListColumn::Remaining => "Remaining",
+        ListColumn::Time => "Time",
+        ListColumn::Eta => "ETA",
+        ListColumn::Created => "Created",
+        ListColumn::Started => "Started",
+        ListColumn::Completed => "Completed",
+    }
+}
+
+fn cell(
+    column: ListColumn,
+    task: &Task,
+    storage: &dyn TaskStore,
+    owner: &str,
+    ids: &IdPrefixes,
+    calendar: &WorkCalendar,
+    timezone: &Timezone,
+) -> Cell {
+    let datetime = |dt: Option<DateTime<Utc>>| {
+        dt.map(|dt| format_datetime(&dt, timezone))
+            .unwrap_or_default()
+    };
+
+    let text = match column {
+        ListColumn::Id => ids.get(task),
+        ListColumn::Status => {
+            return match task.status {
+                TaskStatus::NotStarted => Cell::new("○ Not Started").fg(Color::Grey),
+                TaskStatus::InProgress => Cell::new("◐ In Progress").fg(Color::Yellow),
+                TaskStatus::Completed => Cell::new("● Completed").fg(Color::Green),
+                TaskStatus::Cancelled => Cell::new("✗ Cancelled").fg(Color::Red),
+            }
+        }
+        ListColumn::Title => display_title(task),
+        ListColumn::Description => task.description.clone(),
+        ListColumn::Tags => task
+            .tags
+            .iter()
+            .map(|t| format!("#{}", t))
+            .collect::<Vec<_>>()
+            .join(" "),
+        ListColumn::Path => {
+            let hierarchy = storage.get_task_hierarchy(task);
+            hierarchy[..hierarchy.len() - 1]
                 .iter()
-                .map(|t| format!("#{}", t))
+                .filter_map(|id| storage.get_task(*id))
+                .map(|t| t.title.as_str())
                 .collect::<Vec<_>>()
-                .join(" ")
-        };
-
-        let time_str = if task.total_time_seconds > 0 {
-            task.get_formatted_total_time()
-        } else {
-            String::new()
-        };
+                .join(" / ")
+        }
+        ListColumn::Owner => owner.to_string(),
+        ListColumn::Estimate => task.get_formatted_estimate(calendar).unwrap_or_default(),
+        ListColumn::Remaining => match task.estimated_effort_hours {
+            Some(hours) => {
+                let remaining = hours - task.total_time_seconds as f64 / 3600.0;
+                // Negative once the estimate is overrun
+                let cell = Cell::new(EffortEstimate::from_hours(remaining, calendar));
+                return if remaining < 0.0 {
+                    cell.fg(Color::Red)
+                } else {
+                    cell
+                };
+            }
+            None => String::new(),
+        },
+        ListColumn::Time => {
+            if task.total_time_seconds > 0 {
+                task.get_formatted_total_time(calendar)
+            } else {
+                String::new()
+            }
+        }
+        ListColumn::Eta => datetime(task.eta),
+        ListColumn::Created => format_datetime(&task.created_at, timezone),
+        ListColumn::Started => datetime(task.started_at),
+        ListColumn::Completed => datetime(task.completed_at),
+    };
+    Cell::new(text)
+}
 
-        table.add_row(vec![
-            Cell::new(task.short_id()),
-            status_str,
-            Cell::new(&task.title),
-            Cell::new(tags_str),
-            Cell::new(time_str),
-            Cell::new(format_datetime(&task.created_at)),
-        ]);
+/// The title, marked with the reportee a delegated stub stands in for.
+fn display_title(task: &Task) -> String {
+    match &task.delegated_to {
+        Some(link) => format!("{} → {}", task.title, link.owner),
+        None => task.title.clone(),
     }
+}
 
-    println!("{}", table);
-    println!("\nTotal: {} task(s)", filtered.len());
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    #[test]
+    fn test_sorts_missing_values_last() {
+        let mut tasks: Vec<Task> = ["b", "A", "c"]
+            .iter()
+            .map(|title| Task::new(title.to_string()))
+            .collect();
+        tasks[0].estimated_effort_hours = Some(2.0);
+        tasks[2].estimated_effort_hours = Some(1.0);
+        let titles = |sort, reverse| {
+            let mut refs: Vec<&Task> = tasks.iter().collect();
+            refs.sort_by(|a, b| compare_tasks(a, b, sort, reverse));
+            refs.iter().map(|t| t.title.as_str()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(titles(ListSort::Title, false), ["A", "b", "c"]);
+        assert_eq!(titles(ListSort::Title, true), ["c", "b", "A"]);
+        assert_eq!(titles(ListSort::Estimate, false), ["c", "b", "A"]);
+        assert_eq!(titles(ListSort::Estimate, true), ["b", "c", "A"]);
+    }
 }
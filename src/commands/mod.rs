@@ -1,15 +1,32 @@
 pub mod add;
+pub mod archive;
+pub mod data;
+pub mod delegate;
+pub mod doctor;
 pub mod list;
+pub mod output;
 pub mod report;
 pub mod reportee;
+pub mod scope;
+pub mod time;
+pub mod trash;
 pub mod tree;
+pub mod undo;
 pub mod update;
 
 pub use add::add_task;
+pub use archive::archive_tasks;
+pub use data::{migrate_data_dir, show_data_paths};
+pub use delegate::delegate_task;
+pub use doctor::doctor;
 pub use list::list_tasks;
 pub use report::{generate_report, show_stats};
 pub use reportee::{add_reportee, list_reportees, remove_reportee};
+pub use scope::Scope;
+pub use time::{delete_time_entry, edit_time_entry, list_time_entries, log_time, show_status};
+pub use trash::{empty_trash, list_trash, restore_from_trash};
 pub use tree::show_tree;
+pub use undo::{redo, undo};
 pub use update::{
     cancel_task, complete_task, delete_task, pause_task, show_task, start_task, tag_task,
     update_task,
@@ -0,0 +1,291 @@
+use crate::cli::OutputFormat;
+use crate::models::{Delegation, Task, TaskStatus, TimeEntry};
+use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::io::{self, Write};
+use uuid::Uuid;
+
+/// A task as printed by `--format json|csv|ndjson`. Unlike the storage format,
+/// its fields only ever gain additions, so scripts can rely on them.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskRecord {
+    pub id: Uuid,
+    pub short_id: String,
+    /// "me" or the reportee whose store holds the task
+    pub owner: String,
+    pub title: String,
+    pub description: String,
+    pub status: TaskStatus,
+    pub tags: Vec<String>,
+    pub parent_id: Option<Uuid>,
+    pub estimate_hours: Option<f64>,
+    pub eta: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub time_seconds: i64,
+    /// Whether a timer is running on the task
+    pub tracking: bool,
+    pub delegated_to: Option<Delegation>,
+}
+
+impl TaskRecord {
+    pub fn new(task: &Task, owner: &str) -> Self {
+        Self {
+            id: task.id,
+            short_id: task.short_id(),
+            owner: owner.to_string(),
+            title: task.title.clone(),
+            description: task.description.clone(),
+            status: task.status.clone(),
+            tags: task.tags.clone(),
+            parent_id: task.parent_id,
+            estimate_hours: task.estimated_effort_hours,
+            eta: task.eta,
+            created_at: task.created_at,
+            started_at: task.started_at,
+            completed_at: task.completed_at,
+            cancelled_at: task.cancelled_at,
+            time_seconds: task.total_time_seconds,
+            tracking: task.has_active_time_entry(),
+            delegated_to: task.delegated_to.clone(),
+        }
+    }
+
+    /// CSV columns: the JSON fields, with tags joined by spaces and the
+    /// delegation split in two.
+    pub const CSV_HEADER: [&'static str; 18] = [
+        "id",
+        "short_id",
+        "owner",
+        "title",
+        "description",
+        "status",
+        "tags",
+        "parent_id",
+        "estimate_hours",
+        "eta",
+        "created_at",
+        "started_at",
+        "completed_at",
+        "cancelled_at",
+        "time_seconds",
+        "tracking",
+        "delegated_to_owner",
+        "delegated_to_task_id",
+    ];
+
+    pub fn csv_fields(&self) -> Vec<String> {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+        }
+        // Same timestamp form as the JSON output
+        let rfc3339 = |t: &DateTime<Utc>| t.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        let time = |value: &Option<DateTime<Utc>>| opt(&value.as_ref().map(rfc3339));
+
+        vec![
+            self.id.to_string(),
+            self.short_id.clone(),
+            self.owner.clone(),
+            self.title.clone(),
+            self.description.clone(),
+            status_name(&self.status).to_string(),
+            self.tags.join(" "),
+            opt(&self.parent_id),
+            opt(&self.estimate_hours),
+            time(&self.eta),
+            rfc3339(&self.created_at),
+            time(&self.started_at),
+            time(&self.completed_at),
+            time(&self.cancelled_at),
+            self.time_seconds.to_string(),
+            self.tracking.to_string(),
+            opt(&self.delegated_to.as_ref().map(|d| d.owner.clone())),
+            opt(&self.delegated_to.as_ref().map(|d| d.task_id)),
+        ]
+    }
+}
+
+/// The status as it appears in JSON.
+fn status_name(status: &TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::NotStarted => "not_started",
+        TaskStatus::InProgress => "in_progress",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Cancelled => "cancelled",
+    }
+}
+
+/// A task with its subtasks nested, as printed by `twig tree --format json`.
+#[derive(Debug, Serialize)]
+pub struct TreeRecord {
+    #[serde(flatten)]
+    pub task: TaskRecord,
+    pub subtasks: Vec<TreeRecord>,
+}
+
+impl TreeRecord {
+    /// Depth-first list of every task in `forest` with its depth (0 for a root),
+    /// for the flat formats.
+    fn flatten(forest: &[TreeRecord]) -> Vec<(usize, &TaskRecord)> {
+        fn walk<'a>(node: &'a TreeRecord, depth: usize, out: &mut Vec<(usize, &'a TaskRecord)>) {
+            out.push((depth, &node.task));
+            for child in &node.subtasks {
+                walk(child, depth + 1, out);
+            }
+        }
+        let mut out = Vec::new();
+        for node in forest {
+            walk(node, 0, &mut out);
+        }
+        out
+    }
+}
+
+/// Everything `twig show` prints about a task.
+#[derive(Debug, Serialize)]
+pub struct TaskDetails {
+    #[serde(flatten)]
+    pub task: TaskRecord,
+    pub notes: String,
+    /// Titles from the top-level ancestor down to the task itself
+    pub path: Vec<String>,
+    pub subtasks: Vec<TaskRecord>,
+    pub time_entries: Vec<TimeEntry>,
+}
+
+/// Print `tasks` as a JSON array, one JSON object per line, or CSV rows.
+pub fn print_tasks(format: OutputFormat, tasks: &[TaskRecord]) -> Result<()> {
+    let rows: Vec<Vec<String>> = tasks.iter().map(|t| t.csv_fields()).collect();
+    print_structured(format, tasks, &TaskRecord::CSV_HEADER, rows)
+}
+
+/// Print a task forest: nested under `subtasks` in JSON, and flattened
+/// depth-first with a `depth` field for NDJSON and CSV.
+pub fn print_tree(format: OutputFormat, forest: &[TreeRecord]) -> Result<()> {
+    let flat = TreeRecord::flatten(forest);
+    match format {
+        OutputFormat::Json => print_json(&forest),
+        OutputFormat::Ndjson => {
+            #[derive(Serialize)]
+            struct Line<'a> {
+                #[serde(flatten)]
+                task: &'a TaskRecord,
+                depth: usize,
+            }
+            let lines: Vec<Line> = flat
+                .into_iter()
+                .map(|(depth, task)| Line { task, depth })
+                .collect();
+            print_ndjson(&lines)
+        }
+        _ => {
+            let mut header = TaskRecord::CSV_HEADER.to_vec();
+            header.push("depth");
+            let rows = flat
+                .into_iter()
+                .map(|(depth, task)| {
+                    let mut fields = task.csv_fields();
+                    fields.push(depth.to_string());
+                    fields
+                })
+                .collect();
+            print_csv(&header, rows)
+        }
+    }
+}
+
+/// Print a single object: pretty JSON, one NDJSON line, or `header` and `rows`
+/// as CSV.
+pub fn print_object<T: Serialize>(
+    format: OutputFormat,
+    value: &T,
+    header: &[&str],
+    rows: Vec<Vec<String>>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => print_json(value),
+        OutputFormat::Ndjson => print_ndjson(std::slice::from_ref(value)),
+        _ => print_csv(header, rows),
+    }
+}
+
+/// Print a list of items as a JSON array, NDJSON or CSV (`header` and `rows`).
+pub fn print_structured<T: Serialize>(
+    format: OutputFormat,
+    items: &[T],
+    header: &[&str],
+    rows: Vec<Vec<String>>,
+) -> Result<()> {
+    match format {
+        OutputFormat::Json => print_json(&items),
+        OutputFormat::Ndjson => print_ndjson(items),
+        _ => print_csv(header, rows),
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let mut out = io::stdout().lock();
+    serde_json::to_writer_pretty(&mut out, value).context("Failed to write JSON")?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn print_ndjson<T: Serialize>(items: &[T]) -> Result<()> {
+    let mut out = io::stdout().lock();
+    for item in items {
+        serde_json::to_writer(&mut out, item).context("Failed to write JSON")?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn print_csv(header: &[&str], rows: Vec<Vec<String>>) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(io::stdout().lock());
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_records_flatten_in_order() {
+        let root = Task::new("Root".to_string());
+        let mut child = Task::new("Child".to_string());
+        child.parent_id = Some(root.id);
+        child.tags = vec!["a".to_string(), "b".to_string()];
+        let sibling = Task::new("Sibling".to_string());
+
+        let node = |task: &Task, subtasks| TreeRecord {
+            task: TaskRecord::new(task, "me"),
+            subtasks,
+        };
+        let forest = vec![
+            node(&root, vec![node(&child, Vec::new())]),
+            node(&sibling, Vec::new()),
+        ];
+
+        let flat: Vec<(usize, &str)> = TreeRecord::flatten(&forest)
+            .into_iter()
+            .map(|(depth, task)| (depth, task.title.as_str()))
+            .collect();
+        assert_eq!(flat, vec![(0, "Root"), (1, "Child"), (0, "Sibling")]);
+
+        let fields = forest[0].subtasks[0].task.csv_fields();
+        assert_eq!(fields.len(), TaskRecord::CSV_HEADER.len());
+        assert_eq!(fields[6], "a b");
+        assert_eq!(fields[7], root.id.to_string());
+
+        let json = serde_json::to_value(&forest[0]).unwrap();
+        assert_eq!(json["subtasks"][0]["title"], "Child");
+        assert_eq!(json["status"], "not_started");
+    }
+}
@@ -1,37 +1,198 @@
-use crate::cli::{ReportPeriod, StatsPeriod};
-use crate::models::{Task, TaskStatus};
-use crate::storage::{DataPaths, Storage};
-use crate::utils::date::{format_date, format_datetime, format_duration_human, DateRange};
-use anyhow::Result;
-use comfy_table::{presets::UTF8_FULL, Cell, ContentArrangement, Table};
-
-pub fn generate_report(period: ReportPeriod, date: Option<String>) -> Result<()> {
-    let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
-    storage.load()?;
-
-    let date_str = date.unwrap_or_else(|| "today".to_string());
-    let range = match period {
-        ReportPeriod::Daily => DateRange::parse_day(&date_str)?,
-        ReportPeriod::Weekly => DateRange::parse_week(&date_str)?,
-        ReportPeriod::Monthly => DateRange::parse_month(&date_str)?,
-    };
+use crate::cli::{OutputFormat, ReportPeriod, StatsPeriod};
+use crate::commands::output::{self, TaskRecord};
+use crate::commands::Scope;
+use crate::models::{Config, Task, TaskStatus};
+use crate::storage::delegation::sync_stubs_from_disk;
+use crate::storage::{archive, json_store, new_task_store, DataPaths};
+use crate::utils::date::{format_datetime, format_duration_human, DateRange};
+use crate::utils::filter::Filter;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Table};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Live tasks of every owner in `owners` that match `filter`, plus archived ones
+/// that may have activity since `since` (tasks finished in or after a period may
+/// have been archived since), with the owner of each. Stubs for tasks delegated
+/// within `owners` are left out so the work isn't counted twice.
+fn load_tasks(
+    paths: &DataPaths,
+    config: &Config,
+    owners: &[String],
+    since: Option<DateTime<Utc>>,
+    filter: &Filter,
+) -> Result<(Vec<Task>, HashMap<Uuid, String>)> {
+    let mut tasks = Vec::new();
+    let mut owner_of = HashMap::new();
+    for owner in owners {
+        let mut storage = new_task_store(paths, config, owner);
+        storage.load()?;
+        sync_stubs_from_disk(paths, config, storage.as_mut())?;
+        let archived = archive::load_archived(paths, config, owner, storage.as_ref(), since)?;
+        let calendar = config.work_calendar.for_owner(owner);
+        let owned = storage
+            .get_tasks()
+            .into_iter()
+            .filter(|t| {
+                t.delegated_to
+                    .as_ref()
+                    .is_none_or(|link| !owners.contains(&link.owner))
+            })
+            .cloned()
+            .chain(archived)
+            .filter(|t| filter.matches(t, &calendar));
+        for task in owned {
+            owner_of.insert(task.id, owner.clone());
+            tasks.push(task);
+        }
+    }
+    Ok((tasks, owner_of))
+}
+
+fn print_scope(owners: &[String], filter: &Filter) {
+    if owners != ["me"] {
+        println!("For: {}", owners.join(", "));
+    }
+    if !filter.is_empty() {
+        println!("Filter: {}", filter.as_str());
+    }
+}
 
-    let start = range.start();
-    let end = range.end();
+/// The dates given to `twig report` and `twig stats` along with the period.
+pub struct PeriodDates {
+    pub date: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub last_days: Option<u32>,
+}
 
-    println!(
-        "\n{} Report",
-        match period {
-            ReportPeriod::Daily => "Daily",
-            ReportPeriod::Weekly => "Weekly",
-            ReportPeriod::Monthly => "Monthly",
+impl PeriodDates {
+    /// The days covered: the `period` around `--date` (default today), the
+    /// `--from`/`--to` span or the last N days. `None` without any of them.
+    fn range(&self, period: Option<ReportPeriod>, config: &Config) -> Result<Option<DateRange>> {
+        let now = Utc::now().with_timezone(&config.timezone);
+        if let Some(days) = self.last_days {
+            return DateRange::last_days(days, &now).map(Some);
         }
-    );
-    println!("Period: {} to {}", format_date(&start), format_date(&end));
-    println!("{}", "=".repeat(60));
+        if let Some(from) = &self.from {
+            return DateRange::parse_span(from, self.to.as_deref(), &now, config.week_start)
+                .map(Some);
+        }
+
+        let Some(period) = period else {
+            return Ok(None);
+        };
+        let date = self.date.as_deref().unwrap_or("today");
+        let range = match period {
+            ReportPeriod::Daily => DateRange::parse_day(date, &now, config.week_start)?,
+            ReportPeriod::Weekly => DateRange::parse_week(date, &now, config.week_start)?,
+            ReportPeriod::Monthly => DateRange::parse_month(date, &now, config.week_start)?,
+            ReportPeriod::Quarterly => DateRange::parse_quarter(date, &now, config.week_start)?,
+            ReportPeriod::Yearly => DateRange::parse_year(date, &now, config.week_start)?,
+        };
+        Ok(Some(range))
+    }
+}
+
+/// `twig report --format json`: the summary counts and the tasks in each bucket.
+#[derive(Serialize)]
+struct ReportRecord {
+    period: &'static str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    owners: Vec<String>,
+    summary: ReportSummary,
+    created: Vec<ReportTask>,
+    started: Vec<ReportTask>,
+    completed: Vec<ReportTask>,
+    cancelled: Vec<ReportTask>,
+    in_progress: Vec<ReportTask>,
+    /// Tasks with time worked in the period, most first
+    worked_on: Vec<ReportTask>,
+}
+
+#[derive(Serialize)]
+struct ReportSummary {
+    created: usize,
+    started: usize,
+    completed: usize,
+    cancelled: usize,
+    in_progress: usize,
+    worked_on: usize,
+    time_worked_seconds: i64,
+}
+
+/// A task in a report, with the time worked on it in the period.
+#[derive(Serialize)]
+struct ReportTask {
+    #[serde(flatten)]
+    task: TaskRecord,
+    time_worked_seconds: i64,
+}
+
+/// A task in one of a report's buckets, for the flat formats (NDJSON and CSV).
+#[derive(Serialize)]
+struct BucketLine<'a> {
+    bucket: &'static str,
+    #[serde(flatten)]
+    task: &'a ReportTask,
+}
+
+fn print_report(format: OutputFormat, report: &ReportRecord) -> Result<()> {
+    if format == OutputFormat::Json {
+        return output::print_object(format, report, &[], Vec::new());
+    }
+
+    let buckets = [
+        ("created", &report.created),
+        ("started", &report.started),
+        ("completed", &report.completed),
+        ("cancelled", &report.cancelled),
+        ("in_progress", &report.in_progress),
+        ("worked_on", &report.worked_on),
+    ];
+    let lines: Vec<BucketLine> = buckets
+        .iter()
+        .flat_map(|(bucket, tasks)| tasks.iter().map(|task| BucketLine { bucket, task }))
+        .collect();
+
+    let mut header = vec!["bucket"];
+    header.extend(TaskRecord::CSV_HEADER);
+    header.push("time_worked_seconds");
+    let rows = lines
+        .iter()
+        .map(|line| {
+            let mut fields = vec![line.bucket.to_string()];
+            fields.extend(line.task.task.csv_fields());
+            fields.push(line.task.time_worked_seconds.to_string());
+            fields
+        })
+        .collect();
+    output::print_structured(format, &lines, &header, rows)
+}
+
+pub fn generate_report(
+    scope: &Scope,
+    period: Option<ReportPeriod>,
+    dates: PeriodDates,
+    filter: &Filter,
+    format: OutputFormat,
+) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owners = scope.owners(&config)?;
 
-    let tasks: Vec<&Task> = storage.get_all_tasks().iter().collect();
+    let range = dates
+        .range(period, &config)?
+        .context("Give a period, --from or --last-days")?;
+
+    let start = range.start(&config.timezone);
+    let end = range.end(&config.timezone);
+
+    let (all_tasks, owner_of) = load_tasks(&paths, &config, &owners, Some(start), filter)?;
+    let tasks: Vec<&Task> = all_tasks.iter().collect();
 
     // Tasks created in period
     let created: Vec<&Task> = tasks
@@ -86,12 +247,100 @@ pub fn generate_report(period: ReportPeriod, date: Option<String>) -> Result<()>
         .copied()
         .collect();
 
+    // Time worked in the period, from the time entries that overlap it
+    let now = Utc::now();
+    let worked: HashMap<Uuid, i64> = tasks
+        .iter()
+        .map(|t| (t.id, t.time_worked_between(start, end, now)))
+        .collect();
+    let mut worked_on: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| worked[&t.id] > 0)
+        .copied()
+        .collect();
+    worked_on.sort_by_key(|t| std::cmp::Reverse(worked[&t.id]));
+    let time_worked: i64 = worked.values().sum();
+
+    if format != OutputFormat::Table {
+        let records = |tasks: &[&Task]| -> Vec<ReportTask> {
+            tasks
+                .iter()
+                .map(|t| ReportTask {
+                    task: TaskRecord::new(t, &owner_of[&t.id]),
+                    time_worked_seconds: worked[&t.id],
+                })
+                .collect()
+        };
+        let report = ReportRecord {
+            period: range.name(),
+            start,
+            end,
+            owners,
+            summary: ReportSummary {
+                created: created.len(),
+                started: started.len(),
+                completed: completed.len(),
+                cancelled: cancelled.len(),
+                in_progress: in_progress.len(),
+                worked_on: worked_on.len(),
+                time_worked_seconds: time_worked,
+            },
+            created: records(&created),
+            started: records(&started),
+            completed: records(&completed),
+            cancelled: records(&cancelled),
+            in_progress: records(&in_progress),
+            worked_on: records(&worked_on),
+        };
+        return print_report(format, &report);
+    }
+
+    println!(
+        "\n{} Report",
+        match range {
+            DateRange::Day(_) => "Daily",
+            DateRange::Week(_) => "Weekly",
+            DateRange::Month(_) => "Monthly",
+            DateRange::Quarter(_) => "Quarterly",
+            DateRange::Year(_) => "Yearly",
+            DateRange::Span(..) => "Custom",
+        }
+    );
+    println!("Period: {} to {}", range.first_day(), range.last_day());
+    print_scope(&owners, filter);
+    println!("{}", "=".repeat(60));
+
+    let time_spent = |task: &Task| {
+        if task.total_time_seconds > 0 {
+            task.get_formatted_total_time(&config.work_calendar.for_owner(&owner_of[&task.id]))
+        } else {
+            String::from("-")
+        }
+    };
+    let time_worked_on = |task: &Task| match worked[&task.id] {
+        0 => String::from("-"),
+        seconds => format_duration_human(
+            seconds,
+            &config.work_calendar.for_owner(&owner_of[&task.id]),
+        ),
+    };
+    // Totals across owners are counted in the shared calendar
+    let calendar = match owners.as_slice() {
+        [owner] => config.work_calendar.for_owner(owner),
+        _ => config.work_calendar.for_owner("me"),
+    };
+
     println!("\nSummary:");
     println!("  Created:     {} task(s)", created.len());
     println!("  Started:     {} task(s)", started.len());
     println!("  Completed:   {} task(s)", completed.len());
     println!("  Cancelled:   {} task(s)", cancelled.len());
     println!("  In Progress: {} task(s)", in_progress.len());
+    println!(
+        "  Worked On:   {} task(s), {}",
+        worked_on.len(),
+        format_duration_human(time_worked, &calendar)
+    );
 
     if !completed.is_empty() {
         println!("\nCompleted Tasks:");
@@ -99,18 +348,24 @@ pub fn generate_report(period: ReportPeriod, date: Option<String>) -> Result<()>
         table
             .load_preset(UTF8_FULL)
             .set_content_arrangement(ContentArrangement::Dynamic)
-            .set_header(vec!["Title", "ID", "Time Spent", "Completed At"]);
+            .set_header(vec![
+                "Title",
+                "ID",
+                "This Period",
+                "Time Spent",
+                "Completed At",
+            ]);
 
         for task in &completed {
             table.add_row(vec![
                 Cell::new(&task.title),
                 Cell::new(task.short_id()),
-                Cell::new(if task.total_time_seconds > 0 {
-                    task.get_formatted_total_time()
-                } else {
-                    String::from("-")
-                }),
-                Cell::new(format_datetime(&task.completed_at.unwrap())),
+                Cell::new(time_worked_on(task)),
+                Cell::new(time_spent(task)),
+                Cell::new(format_datetime(
+                    &task.completed_at.unwrap(),
+                    &config.timezone,
+                )),
             ]);
         }
         println!("{}", table);
@@ -122,19 +377,22 @@ pub fn generate_report(period: ReportPeriod, date: Option<String>) -> Result<()>
         table
             .load_preset(UTF8_FULL)
             .set_content_arrangement(ContentArrangement::Dynamic)
-            .set_header(vec!["Title", "ID", "Time Spent", "Started At"]);
+            .set_header(vec![
+                "Title",
+                "ID",
+                "This Period",
+                "Time Spent",
+                "Started At",
+            ]);
 
         for task in &in_progress {
             table.add_row(vec![
                 Cell::new(&task.title),
                 Cell::new(task.short_id()),
-                Cell::new(if task.total_time_seconds > 0 {
-                    task.get_formatted_total_time()
-                } else {
-                    String::from("-")
-                }),
+                Cell::new(time_worked_on(task)),
+                Cell::new(time_spent(task)),
                 Cell::new(if let Some(started) = task.started_at {
-                    format_datetime(&started)
+                    format_datetime(&started, &config.timezone)
                 } else {
                     String::from("-")
                 }),
@@ -143,72 +401,219 @@ pub fn generate_report(period: ReportPeriod, date: Option<String>) -> Result<()>
         println!("{}", table);
     }
 
+    if !worked_on.is_empty() {
+        println!("\nWorked On:");
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec!["Title", "ID", "This Period", "Status"]);
+
+        for task in &worked_on {
+            table.add_row(vec![
+                Cell::new(&task.title),
+                Cell::new(task.short_id()),
+                Cell::new(time_worked_on(task)),
+                match task.status {
+                    TaskStatus::NotStarted => Cell::new("○ Not Started").fg(Color::Grey),
+                    TaskStatus::InProgress => Cell::new("◐ In Progress").fg(Color::Yellow),
+                    TaskStatus::Completed => Cell::new("● Completed").fg(Color::Green),
+                    TaskStatus::Cancelled => Cell::new("✗ Cancelled").fg(Color::Red),
+                },
+            ]);
+        }
+
+        println!("{}", table);
+        println!("Total: {}", format_duration_human(time_worked, &calendar));
+    }
+
     println!("{}", "=".repeat(60));
 
     Ok(())
 }
 
-pub fn show_stats(period: Option<StatsPeriod>, date: Option<String>) -> Result<()> {
-    let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
-    storage.load()?;
+/// `twig stats --format json`.
+#[derive(Serialize)]
+struct StatsRecord {
+    period: Option<&'static str>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    owners: Vec<String>,
+    total: usize,
+    not_started: usize,
+    in_progress: usize,
+    completed: usize,
+    cancelled: usize,
+    total_time_seconds: i64,
+    average_time_seconds: i64,
+    /// Completed tasks with an estimate; absent if there are none
+    estimates: Option<EstimateAccuracy>,
+    /// Tag counts, most used first
+    tags: Vec<TagCount>,
+}
 
-    let tasks: Vec<&Task> = storage.get_all_tasks().iter().collect();
+#[derive(Serialize)]
+struct EstimateAccuracy {
+    tasks: usize,
+    estimated_hours: f64,
+    actual_hours: f64,
+    variance_percent: f64,
+}
 
-    // If period and date specified, filter by date range
-    let (tasks, period_info): (Vec<&Task>, Option<String>) = if let Some(p) = period {
-        let date_str = date.unwrap_or_else(|| "today".to_string());
-        let range = match p {
-            StatsPeriod::Daily => DateRange::parse_day(&date_str)?,
-            StatsPeriod::Weekly => DateRange::parse_week(&date_str)?,
-            StatsPeriod::Monthly => DateRange::parse_month(&date_str)?,
-        };
+#[derive(Serialize)]
+struct TagCount {
+    tag: String,
+    count: usize,
+}
+
+impl StatsRecord {
+    /// `metric,value` rows for CSV, tags as `tag:<name>`.
+    fn csv_rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![
+            ("total", self.total.to_string()),
+            ("not_started", self.not_started.to_string()),
+            ("in_progress", self.in_progress.to_string()),
+            ("completed", self.completed.to_string()),
+            ("cancelled", self.cancelled.to_string()),
+            ("total_time_seconds", self.total_time_seconds.to_string()),
+            (
+                "average_time_seconds",
+                self.average_time_seconds.to_string(),
+            ),
+        ];
+        if let Some(estimates) = &self.estimates {
+            rows.extend([
+                ("estimated_tasks", estimates.tasks.to_string()),
+                ("estimated_hours", estimates.estimated_hours.to_string()),
+                ("actual_hours", estimates.actual_hours.to_string()),
+                ("variance_percent", estimates.variance_percent.to_string()),
+            ]);
+        }
+        let mut rows: Vec<Vec<String>> = rows
+            .into_iter()
+            .map(|(metric, value)| vec![metric.to_string(), value])
+            .collect();
+        rows.extend(
+            self.tags
+                .iter()
+                .map(|t| vec![format!("tag:{}", t.tag), t.count.to_string()]),
+        );
+        rows
+    }
+}
+
+pub fn show_stats(
+    scope: &Scope,
+    period: Option<StatsPeriod>,
+    dates: PeriodDates,
+    filter: &Filter,
+    format: OutputFormat,
+) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owners = scope.owners(&config)?;
 
-        let start = range.start();
-        let end = range.end();
+    let range = dates.range(period.map(ReportPeriod::from), &config)?;
+    let bounds = range
+        .as_ref()
+        .map(|r| (r.start(&config.timezone), r.end(&config.timezone)));
 
-        let filtered: Vec<&Task> = tasks
+    // All-time stats read the whole archive
+    let since = bounds.map(|(start, _)| start);
+    let (all_tasks, _) = load_tasks(&paths, &config, &owners, since, filter)?;
+    let tasks: Vec<&Task> = all_tasks.iter().collect();
+
+    // If period and date specified, filter by date range
+    let tasks: Vec<&Task> = if let Some((start, end)) = bounds {
+        tasks
             .into_iter()
             .filter(|t| {
                 // Include task if it was created, started, or completed in the range
                 (t.created_at >= start && t.created_at < end)
-                    || (t.started_at.map_or(false, |s| s >= start && s < end))
-                    || (t.completed_at.map_or(false, |c| c >= start && c < end))
+                    || (t.started_at.is_some_and(|s| s >= start && s < end))
+                    || (t.completed_at.is_some_and(|c| c >= start && c < end))
             })
-            .collect();
+            .collect()
+    } else {
+        tasks
+    };
 
-        (
-            filtered,
-            Some(format!("{} to {}", format_date(&start), format_date(&end))),
-        )
+    let count = |status: TaskStatus| tasks.iter().filter(|t| t.status == status).count();
+    let total = tasks.len();
+    let not_started = count(TaskStatus::NotStarted);
+    let in_progress = count(TaskStatus::InProgress);
+    let completed = count(TaskStatus::Completed);
+    let cancelled = count(TaskStatus::Cancelled);
+
+    // Time statistics
+    let total_time: i64 = tasks.iter().map(|t| t.total_time_seconds).sum();
+    let avg_time = if !tasks.is_empty() {
+        total_time / tasks.len() as i64
     } else {
-        (tasks, None)
+        0
     };
 
+    // Estimate vs actual for completed tasks
+    let with_estimates: Vec<&&Task> = tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Completed && t.estimated_effort_hours.is_some())
+        .collect();
+    let estimates = (!with_estimates.is_empty()).then(|| {
+        let estimated_hours: f64 = with_estimates
+            .iter()
+            .map(|t| t.estimated_effort_hours.unwrap_or(0.0))
+            .sum();
+        let actual_hours: f64 = with_estimates
+            .iter()
+            .map(|t| t.total_time_seconds as f64 / 3600.0)
+            .sum();
+        EstimateAccuracy {
+            tasks: with_estimates.len(),
+            estimated_hours,
+            actual_hours,
+            variance_percent: ((actual_hours - estimated_hours) / estimated_hours) * 100.0,
+        }
+    });
+
+    // Tags analysis
+    let mut tag_counts: HashMap<String, usize> = HashMap::new();
+    for task in &tasks {
+        for tag in &task.tags {
+            *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+    let mut tags: Vec<TagCount> = tag_counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    if format != OutputFormat::Table {
+        let stats = StatsRecord {
+            period: range.map(|r| r.name()),
+            start: bounds.map(|(start, _)| start),
+            end: bounds.map(|(_, end)| end),
+            owners,
+            total,
+            not_started,
+            in_progress,
+            completed,
+            cancelled,
+            total_time_seconds: total_time,
+            average_time_seconds: avg_time,
+            estimates,
+            tags,
+        };
+        return output::print_object(format, &stats, &["metric", "value"], stats.csv_rows());
+    }
+
     println!("\nStatistics");
-    if let Some(ref info) = period_info {
-        println!("Period: {}", info);
+    if let Some(range) = &range {
+        println!("Period: {} to {}", range.first_day(), range.last_day());
     }
+    print_scope(&owners, filter);
     println!("{}", "=".repeat(60));
 
-    let total = tasks.len();
-    let not_started = tasks
-        .iter()
-        .filter(|t| t.status == TaskStatus::NotStarted)
-        .count();
-    let in_progress = tasks
-        .iter()
-        .filter(|t| t.status == TaskStatus::InProgress)
-        .count();
-    let completed = tasks
-        .iter()
-        .filter(|t| t.status == TaskStatus::Completed)
-        .count();
-    let cancelled = tasks
-        .iter()
-        .filter(|t| t.status == TaskStatus::Cancelled)
-        .count();
-
     println!("\nTask Status:");
     println!("  Total:        {}", total);
     println!(
@@ -232,64 +637,32 @@ pub fn show_stats(period: Option<StatsPeriod>, date: Option<String>) -> Result<(
         (cancelled as f64 / total as f64) * 100.0
     );
 
-    // Time statistics
-    let total_time: i64 = tasks.iter().map(|t| t.total_time_seconds).sum();
-    let avg_time = if !tasks.is_empty() {
-        total_time / tasks.len() as i64
-    } else {
-        0
+    // Totals across owners are counted in the shared calendar
+    let calendar = match owners.as_slice() {
+        [owner] => config.work_calendar.for_owner(owner),
+        _ => config.work_calendar.for_owner("me"),
     };
-
     println!("\nTime Tracking:");
-    println!("  Total Time:   {}", format_duration_human(total_time));
-    println!("  Average Time: {}", format_duration_human(avg_time));
-
-    // Estimate vs actual for completed tasks
-    let completed_tasks: Vec<&&Task> = tasks
-        .iter()
-        .filter(|t| t.status == TaskStatus::Completed)
-        .collect();
-    if !completed_tasks.is_empty() {
-        let with_estimates: Vec<&&Task> = completed_tasks
-            .iter()
-            .filter(|t| t.estimated_effort_hours.is_some())
-            .copied()
-            .collect();
-
-        if !with_estimates.is_empty() {
-            let total_estimated: f64 = with_estimates
-                .iter()
-                .map(|t| t.estimated_effort_hours.unwrap_or(0.0))
-                .sum();
-            let total_actual: f64 = with_estimates
-                .iter()
-                .map(|t| t.total_time_seconds as f64 / 3600.0)
-                .sum();
-
-            println!("\nEstimate Accuracy (Completed Tasks with Estimates):");
-            println!("  Estimated: {:.1}h", total_estimated);
-            println!("  Actual:    {:.1}h", total_actual);
-            println!(
-                "  Variance:  {:.1}%",
-                ((total_actual - total_estimated) / total_estimated) * 100.0
-            );
-        }
-    }
+    println!(
+        "  Total Time:   {}",
+        format_duration_human(total_time, &calendar)
+    );
+    println!(
+        "  Average Time: {}",
+        format_duration_human(avg_time, &calendar)
+    );
 
-    // Tags analysis
-    let mut tag_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
-    for task in &tasks {
-        for tag in &task.tags {
-            *tag_counts.entry(tag.clone()).or_insert(0) += 1;
-        }
+    if let Some(estimates) = estimates {
+        println!("\nEstimate Accuracy (Completed Tasks with Estimates):");
+        println!("  Estimated: {:.1}h", estimates.estimated_hours);
+        println!("  Actual:    {:.1}h", estimates.actual_hours);
+        println!("  Variance:  {:.1}%", estimates.variance_percent);
     }
 
-    if !tag_counts.is_empty() {
+    if !tags.is_empty() {
         println!("\nTop Tags:");
-        let mut tags: Vec<_> = tag_counts.iter().collect();
-        tags.sort_by(|a, b| b.1.cmp(a.1));
-        for (tag, count) in tags.iter().take(10) {
-            println!("  #{}: {}", tag, count);
+        for tag in tags.iter().take(10) {
+            println!("  #{}: {}", tag.tag, tag.count);
         }
     }
 
@@ -1,4 +1,5 @@
-use crate::storage::{json_store, DataPaths};
+use crate::models::config::StorageBackend;
+use crate::storage::{json_store, new_task_store, DataPaths};
 use anyhow::Result;
 use comfy_table::{presets::UTF8_FULL, ContentArrangement, Table};
 
@@ -9,11 +10,10 @@ pub fn add_reportee(name: String) -> Result<()> {
     if config.add_reportee(name.clone()) {
         json_store::save_config(&paths.config_file(), &config)?;
 
-        // Create empty tasks file for reportee
-        let reportee_path = paths.reportee_tasks_file(&name);
-        if !reportee_path.exists() {
-            std::fs::write(&reportee_path, "[]")?;
-        }
+        // Create empty task store for reportee
+        let mut storage = new_task_store(&paths, &config, &name);
+        storage.load()?;
+        storage.save()?;
 
         println!("✓ Added reportee: {}", name);
     } else {
@@ -39,7 +39,10 @@ pub fn list_reportees() -> Result<()> {
         .set_header(vec!["Name", "Tasks File"]);
 
     for reportee in &config.reportees {
-        let file_path = paths.reportee_tasks_file(reportee);
+        let file_path = match config.storage_backend {
+            StorageBackend::Json => paths.reportee_tasks_file(reportee),
+            StorageBackend::Sqlite => paths.reportee_tasks_db_file(reportee),
+        };
         let exists = if file_path.exists() { "✓" } else { "✗" };
         table.add_row(vec![
             reportee,
@@ -0,0 +1,89 @@
+use crate::models::Config;
+use anyhow::{bail, Result};
+
+/// Which task stores a command works on, chosen with the global `--for <reportee>`
+/// option, or `--all` on read-only commands.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    pub reportee: Option<String>,
+    pub all: bool,
+}
+
+impl Scope {
+    /// Me and every reportee, for commands that always look at all stores.
+    pub fn everyone() -> Self {
+        Self {
+            reportee: None,
+            all: true,
+        }
+    }
+
+    /// This scope widened to every store if `all` (`--all`), which can't be
+    /// combined with `--for`.
+    pub fn with_all(&self, all: bool) -> Result<Self> {
+        if all && self.reportee.is_some() {
+            bail!("--all covers every reportee and can't be combined with --for");
+        }
+        Ok(Self {
+            all,
+            ..self.clone()
+        })
+    }
+
+    /// Refuse `--for` on `command`, which doesn't work on task stores (and so
+    /// would otherwise silently ignore it).
+    pub fn reject_for(&self, command: &str) -> Result<()> {
+        if let Some(name) = &self.reportee {
+            bail!(
+                "'{}' doesn't work on a reportee's tasks; run it without --for {}",
+                command,
+                name
+            );
+        }
+        Ok(())
+    }
+
+    /// Owner of the single store a command reads or changes: "me", or the
+    /// `--for` reportee once checked against the configured reportees.
+    pub fn owner(&self, config: &Config) -> Result<String> {
+        let Some(name) = &self.reportee else {
+            return Ok("me".to_string());
+        };
+
+        if config.reportees.contains(name) {
+            return Ok(name.clone());
+        }
+        if config.reportees.is_empty() {
+            bail!(
+                "Unknown reportee '{}': no reportees are set up (add one with 'twig reportee add {}')",
+                name,
+                name
+            );
+        }
+        bail!(
+            "Unknown reportee '{}'. Reportees: {}",
+            name,
+            config.reportees.join(", ")
+        )
+    }
+
+    /// Every owner selected: me and all reportees with `--all`, otherwise just
+    /// `owner()`.
+    pub fn owners(&self, config: &Config) -> Result<Vec<String>> {
+        if self.all && self.reportee.is_none() {
+            return Ok(std::iter::once("me".to_string())
+                .chain(config.reportees.iter().cloned())
+                .collect());
+        }
+        Ok(vec![self.owner(config)?])
+    }
+}
+
+/// Heading for an owner's tasks in output covering several stores.
+pub fn owner_label(owner: &str) -> String {
+    if owner == "me" {
+        "Your tasks".to_string()
+    } else {
+        format!("{}'s tasks", owner)
+    }
+}
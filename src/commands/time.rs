@@ -0,0 +1,287 @@
+use crate::commands::update::choose_task;
+use crate::commands::Scope;
+use crate::models::{Config, Task, WorkCalendar};
+use crate::storage::timers::running_timers;
+use crate::storage::{json_store, new_task_store, DataPaths, TaskStore};
+use crate::utils::date::{format_duration_human, parse_date, parse_duration};
+use crate::utils::format_datetime;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use comfy_table::{presets::UTF8_FULL, Cell, Color, ContentArrangement, Table};
+use uuid::Uuid;
+
+/// The longest time entry accepted; anything longer is surely a typo.
+const MAX_ENTRY_DAYS: i64 = 366;
+
+/// Parse the length of a time entry, which must be more than zero and at most
+/// a year.
+fn parse_entry_length(input: &str, calendar: &WorkCalendar) -> Result<Duration> {
+    let duration = parse_duration(input, calendar)?;
+    if duration <= Duration::zero() {
+        bail!("The duration must be longer than zero");
+    }
+    if duration > Duration::days(MAX_ENTRY_DAYS) {
+        bail!(
+            "A time entry can't be longer than a year ({} is)",
+            input.trim()
+        );
+    }
+    Ok(duration)
+}
+
+/// `start` moved by `duration`, or an error if that's out of range.
+fn add_duration(start: DateTime<Utc>, duration: Duration) -> Result<DateTime<Utc>> {
+    start
+        .checked_add_signed(duration)
+        .context("The time entry would end out of range")
+}
+
+/// Changes to a time entry given to `twig time edit`.
+pub struct EntryEdit {
+    pub start: Option<String>,
+    pub end: Option<String>,
+    pub duration: Option<String>,
+    pub note: Option<String>,
+}
+
+/// The store of the owner in `scope`, with the config and the owner's work
+/// calendar.
+fn load_store(scope: &Scope) -> Result<(Box<dyn TaskStore>, Config, WorkCalendar)> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
+    storage.load()?;
+    let calendar = config.work_calendar.for_owner(&owner);
+    Ok((storage, config, calendar))
+}
+
+/// Resolve `reference` to a task whose time entries can be changed.
+fn task_for_time(storage: &dyn TaskStore, reference: &str, min_prefix: usize) -> Result<Uuid> {
+    let id = choose_task(storage, Some(reference), min_prefix, "", |_| true)?
+        .context("Task not found")?;
+    let task = storage.get_task(id).context("Task not found")?;
+    if let Some(link) = &task.delegated_to {
+        bail!(
+            "'{}' is a stub for a task delegated to {}; log time on that task instead",
+            task.title,
+            link.owner
+        );
+    }
+    Ok(id)
+}
+
+/// The index of the entry numbered `number` (1-based, as `twig time list` shows).
+fn entry_index(task: &Task, number: usize) -> Result<usize> {
+    if number == 0 || number > task.time_entries.len() {
+        bail!(
+            "'{}' has no time entry #{} (it has {})",
+            task.title,
+            number,
+            task.time_entries.len()
+        );
+    }
+    Ok(number - 1)
+}
+
+/// Show the tasks being timed, in any store, and for how long.
+pub fn show_status() -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let mut stores = Vec::new();
+    for owner in Scope::everyone().owners(&config)? {
+        let mut storage = new_task_store(&paths, &config, &owner);
+        storage.load()?;
+        stores.push((owner, storage));
+    }
+
+    let timers = running_timers(
+        stores
+            .iter()
+            .map(|(owner, storage)| (owner.as_str(), storage.as_ref())),
+    );
+    if timers.is_empty() {
+        println!("No timer running.");
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    for timer in &timers {
+        let task = &timer.task;
+        let Some(entry) = task.time_entries.iter().find(|e| e.is_active()) else {
+            continue;
+        };
+        let elapsed = (now - entry.start).num_seconds();
+        let calendar = config.work_calendar.for_owner(&timer.owner);
+        println!(
+            "▶ {} [{}]{}",
+            task.title,
+            task.short_id(),
+            timer.owner_suffix()
+        );
+        println!(
+            "  Running for {} (since {}), {} in total",
+            format_duration_human(elapsed, &calendar),
+            format_datetime(&entry.start, &config.timezone),
+            format_duration_human(task.total_time_seconds + elapsed, &calendar)
+        );
+    }
+
+    Ok(())
+}
+
+pub fn log_time(
+    scope: &Scope,
+    task: String,
+    duration: String,
+    at: Option<String>,
+    note: Option<String>,
+) -> Result<()> {
+    let (mut storage, config, calendar) = load_store(scope)?;
+    let task_id = task_for_time(storage.as_ref(), &task, config.id_prefix_length)?;
+
+    let duration = parse_entry_length(&duration, &calendar)?;
+    // Without --at the work is taken to have just finished
+    let start = match at {
+        Some(at) => parse_date(&at, &config)?,
+        None => Utc::now()
+            .checked_sub_signed(duration)
+            .context("The time entry would start out of range")?,
+    };
+    if add_duration(start, duration)? > Utc::now() {
+        bail!("Logged time can't end in the future");
+    }
+
+    let task = storage.get_task_mut(task_id).context("Task not found")?;
+    task.log_time(start, duration, note.unwrap_or_default());
+    println!(
+        "✓ Logged {} on {} [{}] from {}",
+        format_duration_human(duration.num_seconds(), &calendar),
+        task.title,
+        task.short_id(),
+        format_datetime(&start, &config.timezone)
+    );
+    println!("  Total time: {}", task.get_formatted_total_time(&calendar));
+    storage.save()?;
+
+    Ok(())
+}
+
+pub fn list_time_entries(scope: &Scope, task: String) -> Result<()> {
+    let (storage, config, calendar) = load_store(scope)?;
+    let task_id = task_for_time(storage.as_ref(), &task, config.id_prefix_length)?;
+    let task = storage.get_task(task_id).context("Task not found")?;
+
+    if task.time_entries.is_empty() {
+        println!("No time recorded on {} [{}].", task.title, task.short_id());
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["#", "Start", "End", "Duration", "Note"]);
+
+    for (i, entry) in task.time_entries.iter().enumerate() {
+        let (end, duration) = match (entry.end, entry.duration_seconds) {
+            (Some(end), Some(seconds)) => (
+                Cell::new(format_datetime(&end, &config.timezone)),
+                Cell::new(format_duration_human(seconds, &calendar)),
+            ),
+            _ => (
+                Cell::new("running").fg(Color::Green),
+                Cell::new(format_duration_human(
+                    (Utc::now() - entry.start).num_seconds(),
+                    &calendar,
+                )),
+            ),
+        };
+        table.add_row(vec![
+            Cell::new(i + 1),
+            Cell::new(format_datetime(&entry.start, &config.timezone)),
+            end,
+            duration,
+            Cell::new(&entry.note),
+        ]);
+    }
+
+    println!("{} [{}]", task.title, task.short_id());
+    println!("{}", table);
+    println!("\nTotal time: {}", task.get_formatted_total_time(&calendar));
+
+    Ok(())
+}
+
+pub fn edit_time_entry(scope: &Scope, task: String, number: usize, edit: EntryEdit) -> Result<()> {
+    let (mut storage, config, calendar) = load_store(scope)?;
+    let task_id = task_for_time(storage.as_ref(), &task, config.id_prefix_length)?;
+    let task = storage.get_task_mut(task_id).context("Task not found")?;
+    let index = entry_index(task, number)?;
+
+    let entry = &mut task.time_entries[index];
+    if let Some(start) = &edit.start {
+        entry.start = parse_date(start, &config)?;
+    }
+    let end: Option<DateTime<Utc>> = match (&edit.end, &edit.duration) {
+        (Some(end), _) => Some(parse_date(end, &config)?),
+        (None, Some(duration)) => Some(add_duration(
+            entry.start,
+            parse_entry_length(duration, &calendar)?,
+        )?),
+        // Keep the length of a finished entry whose start moved
+        (None, None) => match entry.duration_seconds.filter(|_| edit.start.is_some()) {
+            Some(seconds) => Some(add_duration(
+                entry.start,
+                Duration::try_seconds(seconds).context("Invalid time entry length")?,
+            )?),
+            None => None,
+        },
+    };
+    if let Some(end) = end {
+        if end <= entry.start {
+            bail!("A time entry must end after it starts");
+        }
+        if end > Utc::now() {
+            bail!("A time entry can't end in the future");
+        }
+        entry.end_entry(end);
+    } else if entry.start > Utc::now() {
+        bail!("A time entry can't start in the future");
+    }
+    if let Some(note) = edit.note {
+        entry.note = note;
+    }
+
+    task.time_entries_changed();
+    println!(
+        "✓ Updated time entry #{} on {} [{}]",
+        number,
+        task.title,
+        task.short_id()
+    );
+    println!("  Total time: {}", task.get_formatted_total_time(&calendar));
+    storage.save()?;
+
+    Ok(())
+}
+
+pub fn delete_time_entry(scope: &Scope, task: String, number: usize) -> Result<()> {
+    let (mut storage, config, calendar) = load_store(scope)?;
+    let task_id = task_for_time(storage.as_ref(), &task, config.id_prefix_length)?;
+    let task = storage.get_task_mut(task_id).context("Task not found")?;
+    let index = entry_index(task, number)?;
+
+    task.time_entries.remove(index);
+    task.time_entries_changed();
+    println!(
+        "✓ Deleted time entry #{} from {} [{}]",
+        number,
+        task.title,
+        task.short_id()
+    );
+    println!("  Total time: {}", task.get_formatted_total_time(&calendar));
+    storage.save()?;
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::commands::Scope;
+use crate::models::Task;
+use crate::storage::{json_store, new_task_store, DataPaths};
+use crate::utils::date::parse_age;
+use crate::utils::filter::Filter;
+use crate::utils::format_datetime;
+use crate::utils::task_ref::{resolve_task, IdPrefixes};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use comfy_table::{presets::UTF8_FULL, Cell, ContentArrangement, Table};
+
+pub fn list_trash(scope: &Scope) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
+    storage.load()?;
+
+    let trashed = storage.get_trashed_tasks();
+    if trashed.is_empty() {
+        println!("The trash is empty.");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["ID", "Title", "Parent", "Deleted"]);
+
+    let ids = IdPrefixes::new(&trashed, config.id_prefix_length);
+    for task in &trashed {
+        let parent = task
+            .parent_id
+            .and_then(|id| storage.get_task(id))
+            .map(|p| p.title.clone())
+            .unwrap_or_default();
+        table.add_row(vec![
+            Cell::new(ids.get(task)),
+            Cell::new(&task.title),
+            Cell::new(parent),
+            Cell::new(
+                task.deleted_at
+                    .map(|d| format_datetime(&d, &config.timezone))
+                    .unwrap_or_default(),
+            ),
+        ]);
+    }
+
+    println!("{}", table);
+    println!("\nTotal: {} task(s) in the trash", trashed.len());
+
+    Ok(())
+}
+
+pub fn restore_from_trash(scope: &Scope, id: String) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
+    storage.load()?;
+
+    // Only tasks in the trash can be restored, so only they are matched
+    let trashed = storage.get_trashed_tasks();
+    let task = resolve_task(&trashed, &id, config.id_prefix_length)?;
+    let (task_id, title) = (task.id, task.title.clone());
+
+    let restored = storage.restore_task(task_id)?;
+    if restored > 1 {
+        println!("✓ Restored: {} (with {} subtask(s))", title, restored - 1);
+    } else {
+        println!("✓ Restored: {}", title);
+    }
+
+    Ok(())
+}
+
+pub fn empty_trash(
+    scope: &Scope,
+    older_than: Option<String>,
+    filter: Option<&Filter>,
+) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
+    storage.load()?;
+
+    let cutoff = match older_than {
+        Some(age) => Some(
+            Utc::now()
+                .checked_sub_signed(parse_age(&age)?)
+                .with_context(|| format!("Age too long: {}", age))?,
+        ),
+        None => None,
+    };
+
+    let calendar = config.work_calendar.for_owner(&owner);
+    let matching = |task: &Task| filter.is_none_or(|filter| filter.matches(task, &calendar));
+    match storage.empty_trash(cutoff, &matching)? {
+        0 => println!("Nothing to remove."),
+        removed => println!(
+            "✓ Permanently removed {} task(s) (run 'twig undo' to bring them back)",
+            removed
+        ),
+    }
+
+    Ok(())
+}
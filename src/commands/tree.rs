@@ -1,25 +1,64 @@
-use crate::storage::{DataPaths, Storage};
+use crate::cli::OutputFormat;
+use crate::commands::output::{self, TaskRecord, TreeRecord};
+use crate::commands::scope::{owner_label, Scope};
+use crate::storage::delegation::sync_stubs_from_disk;
+use crate::storage::{json_store, new_task_store, DataPaths};
+use crate::utils::filter::Filter;
+use crate::utils::task_ref::IdPrefixes;
 use crate::utils::tree::{format_tree, TreeNode};
 use anyhow::Result;
 
-pub fn show_tree() -> Result<()> {
+pub fn show_tree(scope: &Scope, filter: &Filter, format: OutputFormat) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
-    storage.load()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owners = scope.owners(&config)?;
 
-    let forest = TreeNode::build_forest(&storage);
-    let lines = format_tree(&forest);
+    let mut records = Vec::new();
+    for owner in &owners {
+        let mut storage = new_task_store(&paths, &config, owner);
+        storage.load()?;
+        sync_stubs_from_disk(&paths, &config, storage.as_mut())?;
+        let calendar = config.work_calendar.for_owner(owner);
 
-    if lines.is_empty() {
-        println!("No tasks found.");
-    } else {
-        println!("\nTask Tree:");
-        println!("{}", "=".repeat(60));
-        for line in lines {
-            println!("{}", line);
+        let mut forest = TreeNode::build_forest(storage.as_ref());
+        if !filter.is_empty() {
+            forest = TreeNode::retain(forest, &|task| filter.matches(task, &calendar));
+        }
+        if format != OutputFormat::Table {
+            records.extend(forest.iter().map(|node| tree_record(node, owner)));
+            continue;
+        }
+        let ids = IdPrefixes::new(&storage.get_tasks(), config.id_prefix_length);
+        let lines = format_tree(&forest, &ids, &calendar);
+
+        if owners.len() > 1 {
+            println!("\n{}", owner_label(owner));
+        }
+        if lines.is_empty() {
+            println!("No tasks found.");
+        } else {
+            println!("\nTask Tree:");
+            println!("{}", "=".repeat(60));
+            for line in lines {
+                println!("{}", line);
+            }
+            println!("{}", "=".repeat(60));
         }
-        println!("{}", "=".repeat(60));
     }
 
+    if format != OutputFormat::Table {
+        output::print_tree(format, &records)?;
+    }
     Ok(())
 }
+
+fn tree_record(node: &TreeNode, owner: &str) -> TreeRecord {
+    TreeRecord {
+        task: TaskRecord::new(&node.task, owner),
+        subtasks: node
+            .children
+            .iter()
+            .map(|child| tree_record(child, owner))
+            .collect(),
+    }
+}
@@ -0,0 +1,55 @@
+use crate::commands::Scope;
+use crate::storage::task_store::{redo_linked, undo_linked};
+use crate::storage::{json_store, new_task_store, DataPaths, TaskStore};
+use anyhow::Result;
+
+/// Every store, the one of the owner in `scope` first, since changes like a
+/// delegation are undone in all the stores they touched.
+fn load_stores(scope: &Scope) -> Result<Vec<(String, Box<dyn TaskStore>)>> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+
+    let mut stores = Vec::new();
+    for other in std::iter::once(owner.clone()).chain(
+        Scope::everyone()
+            .owners(&config)?
+            .into_iter()
+            .filter(|other| *other != owner),
+    ) {
+        let mut storage = new_task_store(&paths, &config, &other);
+        storage.load()?;
+        stores.push((other, storage));
+    }
+    Ok(stores)
+}
+
+pub fn undo(scope: &Scope) -> Result<()> {
+    let mut loaded = load_stores(scope)?;
+    let mut stores: Vec<(&str, &mut dyn TaskStore)> = Vec::new();
+    for (owner, storage) in &mut loaded {
+        stores.push((owner.as_str(), storage.as_mut()));
+    }
+
+    match undo_linked(stores)? {
+        Some(summary) => println!("✓ Undid: {}", summary),
+        None => println!("Nothing to undo."),
+    }
+
+    Ok(())
+}
+
+pub fn redo(scope: &Scope) -> Result<()> {
+    let mut loaded = load_stores(scope)?;
+    let mut stores: Vec<(&str, &mut dyn TaskStore)> = Vec::new();
+    for (owner, storage) in &mut loaded {
+        stores.push((owner.as_str(), storage.as_mut()));
+    }
+
+    match redo_linked(stores)? {
+        Some(summary) => println!("✓ Redid: {}", summary),
+        None => println!("Nothing to redo."),
+    }
+
+    Ok(())
+}
@@ -1,8 +1,17 @@
-use crate::models::{Task, TaskStatus};
-use crate::storage::{DataPaths, Storage};
+use crate::cli::{OutputFormat, SubtaskMode};
+use crate::commands::output::{self, TaskDetails, TaskRecord};
+use crate::commands::Scope;
+use crate::models::{Task, TaskStatus, TimerPolicy, WorkCalendar};
+use crate::storage::delegation::sync_stubs_from_disk;
+use crate::storage::timers::save_pausing_other_timers;
+use crate::storage::{json_store, new_task_store, DataPaths, DeleteMode, TaskStore};
+use crate::utils::filter::Filter;
+use crate::utils::task_ref::{resolve_task, IdPrefixes};
 use crate::utils::{format_datetime, parse_date};
 use anyhow::{Context, Result};
 use dialoguer::{theme::ColorfulTheme, Confirm, Select};
+use std::io::IsTerminal;
+use uuid::Uuid;
 
 fn select_task_mut<'a>(tasks: &'a mut [Task], prompt: &str) -> Result<Option<&'a mut Task>> {
     if tasks.is_empty() {
@@ -32,58 +41,198 @@ fn select_task_mut<'a>(tasks: &'a mut [Task], prompt: &str) -> Result<Option<&'a
     Ok(selection.map(|i| &mut tasks[i]))
 }
 
-pub fn start_task() -> Result<()> {
-    let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
-    storage.load()?;
+/// The task a command acts on: the one `reference` names if given, otherwise one
+/// picked interactively from the tasks matching `pickable`.
+pub(crate) fn choose_task(
+    storage: &dyn TaskStore,
+    reference: Option<&str>,
+    min_prefix: usize,
+    prompt: &str,
+    pickable: impl Fn(&Task) -> bool,
+) -> Result<Option<Uuid>> {
+    if let Some(reference) = reference {
+        return Ok(Some(
+            resolve_task(&storage.get_tasks(), reference, min_prefix)?.id,
+        ));
+    }
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "No task given. Pass a task ID, ID prefix or title (the task picker needs a terminal)"
+        );
+    }
 
     let mut tasks: Vec<Task> = storage
-        .get_all_tasks()
-        .iter()
-        .filter(|t| t.status != TaskStatus::Completed && t.status != TaskStatus::Cancelled)
+        .get_tasks()
+        .into_iter()
+        .filter(|t| pickable(t))
         .cloned()
         .collect();
+    Ok(select_task_mut(&mut tasks, prompt)?.map(|t| t.id))
+}
 
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to start")? {
-        task.id
-    } else {
+/// The live tasks matching `filter` and `pickable`, for commands given `--filter`
+/// to act on all of them at once.
+fn matching_tasks(
+    storage: &dyn TaskStore,
+    filter: &Filter,
+    calendar: &WorkCalendar,
+    pickable: impl Fn(&Task) -> bool,
+) -> Vec<Uuid> {
+    storage
+        .get_tasks()
+        .into_iter()
+        .filter(|t| pickable(t) && filter.matches(t, calendar))
+        .map(|t| t.id)
+        .collect()
+}
+
+/// Complete or cancel (`finish`) every open task matching `filter`, as one
+/// change. `done` is what's printed for each, e.g. "Completed".
+fn finish_matching(
+    storage: &mut dyn TaskStore,
+    filter: &Filter,
+    calendar: &WorkCalendar,
+    done: &str,
+    finish: fn(&mut Task),
+) -> Result<()> {
+    let ids = matching_tasks(storage, filter, calendar, is_open);
+    if ids.is_empty() {
+        println!("No open tasks match '{}'.", filter.as_str());
+        return Ok(());
+    }
+    for id in &ids {
+        let task = storage.get_task_mut(*id).context("Task not found")?;
+        finish(task);
+        println!("✓ {} task: {} [{}]", done, task.title, task.short_id());
+    }
+    storage.save()?;
+    if ids.len() > 1 {
+        println!("  {} {} task(s)", done, ids.len());
+    }
+    Ok(())
+}
+
+fn is_open(task: &Task) -> bool {
+    task.status != TaskStatus::Completed && task.status != TaskStatus::Cancelled
+}
+
+/// Refuse to change the status of a task that is already finished.
+fn ensure_open(task: &Task) -> Result<()> {
+    match task.status {
+        TaskStatus::Completed => anyhow::bail!("'{}' is already completed", task.title),
+        TaskStatus::Cancelled => anyhow::bail!("'{}' is already cancelled", task.title),
+        _ => Ok(()),
+    }
+}
+
+pub fn start_task(scope: &Scope, task: Option<String>) -> Result<()> {
+    let paths = DataPaths::new()?;
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
+    storage.load()?;
+
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to start",
+        is_open,
+    )?
+    else {
         return Ok(());
     };
 
-    if let Some(task) = storage.get_task_mut(task_id) {
-        task.start();
-        println!("✓ Started task: {} [{}]", task.title, task.short_id());
-        storage.save()?;
-    } else {
+    let Some(task) = storage.get_task_mut(task_id) else {
         anyhow::bail!("Task not found");
+    };
+    ensure_open(task)?;
+    if let Some(link) = &task.delegated_to {
+        anyhow::bail!(
+            "'{}' is a stub for a task delegated to {}; start that task instead",
+            task.title,
+            link.owner
+        );
+    }
+    if task.has_active_time_entry() {
+        println!(
+            "Already tracking time on: {} [{}]",
+            task.title,
+            task.short_id()
+        );
+        return Ok(());
+    }
+    task.start();
+    println!("✓ Started task: {} [{}]", task.title, task.short_id());
+    let summary = format!("Start '{}'", task.title);
+
+    if config.timer_policy == TimerPolicy::Single {
+        let mut others = Vec::new();
+        for other in Scope::everyone().owners(&config)? {
+            if other != owner {
+                let mut store = new_task_store(&paths, &config, &other);
+                store.load()?;
+                others.push((other, store));
+            }
+        }
+        let mut stores: Vec<(&str, &mut dyn TaskStore)> = vec![(&owner, storage.as_mut())];
+        for (other, store) in &mut others {
+            stores.push((other.as_str(), store.as_mut()));
+        }
+        for timer in save_pausing_other_timers(stores, task_id, &summary)? {
+            println!(
+                "  Paused: {} [{}]{}",
+                timer.task.title,
+                timer.task.short_id(),
+                timer.owner_suffix()
+            );
+        }
+    } else {
+        storage.save()?;
     }
 
     Ok(())
 }
 
-pub fn complete_task() -> Result<()> {
+pub fn complete_task(scope: &Scope, task: Option<String>, filter: Option<&Filter>) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage
-        .get_all_tasks()
-        .iter()
-        .filter(|t| t.status != TaskStatus::Completed && t.status != TaskStatus::Cancelled)
-        .cloned()
-        .collect();
+    if let Some(filter) = filter {
+        let calendar = config.work_calendar.for_owner(&owner);
+        return finish_matching(
+            storage.as_mut(),
+            filter,
+            &calendar,
+            "Completed",
+            Task::complete,
+        );
+    }
 
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to complete")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to complete",
+        is_open,
+    )?
+    else {
         return Ok(());
     };
 
     if let Some(task) = storage.get_task_mut(task_id) {
+        ensure_open(task)?;
         task.complete();
         println!("✓ Completed task: {} [{}]", task.title, task.short_id());
         if task.total_time_seconds > 0 {
-            println!("  Total time: {}", task.get_formatted_total_time());
+            println!(
+                "  Total time: {}",
+                task.get_formatted_total_time(&config.work_calendar.for_owner(&owner))
+            );
         }
         storage.save()?;
     } else {
@@ -93,25 +242,37 @@ pub fn complete_task() -> Result<()> {
     Ok(())
 }
 
-pub fn cancel_task() -> Result<()> {
+pub fn cancel_task(scope: &Scope, task: Option<String>, filter: Option<&Filter>) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage
-        .get_all_tasks()
-        .iter()
-        .filter(|t| t.status != TaskStatus::Completed && t.status != TaskStatus::Cancelled)
-        .cloned()
-        .collect();
+    if let Some(filter) = filter {
+        let calendar = config.work_calendar.for_owner(&owner);
+        return finish_matching(
+            storage.as_mut(),
+            filter,
+            &calendar,
+            "Cancelled",
+            Task::cancel,
+        );
+    }
 
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to cancel")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to cancel",
+        is_open,
+    )?
+    else {
         return Ok(());
     };
 
     if let Some(task) = storage.get_task_mut(task_id) {
+        ensure_open(task)?;
         task.cancel();
         println!("✓ Cancelled task: {} [{}]", task.title, task.short_id());
         storage.save()?;
@@ -122,26 +283,31 @@ pub fn cancel_task() -> Result<()> {
     Ok(())
 }
 
-pub fn pause_task() -> Result<()> {
+pub fn pause_task(scope: &Scope, task: Option<String>) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage
-        .get_all_tasks()
-        .iter()
-        .filter(|t| t.has_active_time_entry())
-        .cloned()
-        .collect();
-
-    if tasks.is_empty() {
+    if task.is_none()
+        && !storage
+            .get_tasks()
+            .iter()
+            .any(|t| t.has_active_time_entry())
+    {
         println!("No tasks with active time tracking.");
         return Ok(());
     }
 
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to pause")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to pause",
+        Task::has_active_time_entry,
+    )?
+    else {
         return Ok(());
     };
 
@@ -152,7 +318,10 @@ pub fn pause_task() -> Result<()> {
         }
         task.pause();
         println!("✓ Paused task: {} [{}]", task.title, task.short_id());
-        println!("  Total time: {}", task.get_formatted_total_time());
+        println!(
+            "  Total time: {}",
+            task.get_formatted_total_time(&config.work_calendar.for_owner(&owner))
+        );
         storage.save()?;
     } else {
         anyhow::bail!("Task not found");
@@ -161,21 +330,49 @@ pub fn pause_task() -> Result<()> {
     Ok(())
 }
 
-pub fn show_task() -> Result<()> {
+pub fn show_task(scope: &Scope, task: Option<String>, format: OutputFormat) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage.get_all_tasks().to_vec();
-
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to view")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to view",
+        |_| true,
+    )?
+    else {
         return Ok(());
     };
 
+    sync_stubs_from_disk(&paths, &config, storage.as_mut())?;
     let task = storage.get_task(task_id).context("Task not found")?;
 
+    if format != OutputFormat::Table {
+        let details = TaskDetails {
+            task: TaskRecord::new(task, &owner),
+            notes: task.notes.clone(),
+            path: storage
+                .get_task_hierarchy(task)
+                .into_iter()
+                .filter_map(|id| storage.get_task(id))
+                .map(|t| t.title.clone())
+                .collect(),
+            subtasks: storage
+                .get_children(task.id)
+                .into_iter()
+                .map(|child| TaskRecord::new(child, &owner))
+                .collect(),
+            time_entries: task.time_entries.clone(),
+        };
+        // CSV has no room for nested data: just the task's own row
+        let row = details.task.csv_fields();
+        return output::print_object(format, &details, &TaskRecord::CSV_HEADER, vec![row]);
+    }
+
     println!("\n{}", "=".repeat(60));
     println!("Task: {}", task.title);
     println!("{}", "=".repeat(60));
@@ -191,6 +388,21 @@ pub fn show_task() -> Result<()> {
         }
     );
 
+    if let Some(link) = &task.delegated_to {
+        // Show the delegated task's ID as `twig --for <owner> list` does
+        let mut delegate_store = new_task_store(&paths, &config, &link.owner);
+        delegate_store.load()?;
+        let delegate_tasks = delegate_store.get_tasks();
+        match delegate_tasks.iter().find(|t| t.id == link.task_id) {
+            Some(delegated) => println!(
+                "Delegated:   to {} [{}]",
+                link.owner,
+                IdPrefixes::new(&delegate_tasks, config.id_prefix_length).get(delegated)
+            ),
+            None => println!("Delegated:   to {} (task not found)", link.owner),
+        }
+    }
+
     if !task.description.is_empty() {
         println!("Description: {}", task.description);
     }
@@ -206,30 +418,45 @@ pub fn show_task() -> Result<()> {
         );
     }
 
-    if let Some(estimate) = task.get_formatted_estimate() {
+    if let Some(estimate) = task.get_formatted_estimate(&config.work_calendar.for_owner(&owner)) {
         println!("Estimate:    {}", estimate);
     }
 
     if let Some(eta) = task.eta {
-        println!("ETA:         {}", format_datetime(&eta));
+        println!("ETA:         {}", format_datetime(&eta, &config.timezone));
     }
 
-    println!("Created:     {}", format_datetime(&task.created_at));
+    println!(
+        "Created:     {}",
+        format_datetime(&task.created_at, &config.timezone)
+    );
 
     if let Some(started) = task.started_at {
-        println!("Started:     {}", format_datetime(&started));
+        println!(
+            "Started:     {}",
+            format_datetime(&started, &config.timezone)
+        );
     }
 
     if let Some(completed) = task.completed_at {
-        println!("Completed:   {}", format_datetime(&completed));
+        println!(
+            "Completed:   {}",
+            format_datetime(&completed, &config.timezone)
+        );
     }
 
     if let Some(cancelled) = task.cancelled_at {
-        println!("Cancelled:   {}", format_datetime(&cancelled));
+        println!(
+            "Cancelled:   {}",
+            format_datetime(&cancelled, &config.timezone)
+        );
     }
 
     if task.total_time_seconds > 0 {
-        println!("Total Time:  {}", task.get_formatted_total_time());
+        println!(
+            "Total Time:  {}",
+            task.get_formatted_total_time(&config.work_calendar.for_owner(&owner))
+        );
     }
 
     // Show hierarchy
@@ -264,20 +491,27 @@ pub fn show_task() -> Result<()> {
 }
 
 pub fn update_task(
+    scope: &Scope,
+    task: Option<String>,
     title: Option<String>,
     description: Option<String>,
     estimate: Option<String>,
     eta: Option<String>,
 ) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage.get_all_tasks().to_vec();
-
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to update")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to update",
+        |_| true,
+    )?
+    else {
         return Ok(());
     };
 
@@ -295,12 +529,12 @@ pub fn update_task(
         }
 
         if let Some(est) = estimate {
-            task.set_estimate(&est)?;
+            task.set_estimate(&est, &config.work_calendar.for_owner(&owner))?;
             updated = true;
         }
 
         if let Some(eta_str) = eta {
-            task.eta = Some(parse_date(&eta_str)?);
+            task.eta = Some(parse_date(&eta_str, &config)?);
             updated = true;
         }
 
@@ -317,39 +551,107 @@ pub fn update_task(
     Ok(())
 }
 
-pub fn delete_task() -> Result<()> {
+pub fn delete_task(
+    scope: &Scope,
+    task: Option<String>,
+    filter: Option<&Filter>,
+    subtasks: Option<SubtaskMode>,
+) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut tasks: Vec<Task> = storage.get_all_tasks().to_vec();
+    if let Some(filter) = filter {
+        let calendar = config.work_calendar.for_owner(&owner);
+        return delete_matching(storage.as_mut(), filter, &calendar, subtasks);
+    }
 
-    let task_id = if let Some(task) = select_task_mut(&mut tasks, "Select task to delete")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to delete",
+        |_| true,
+    )?
+    else {
         return Ok(());
     };
 
     let task = storage.get_task(task_id).context("Task not found")?;
+    let subtask_count = storage.get_descendants(task_id).len();
+
+    // Choosing from the subtask menu counts as confirmation. Without a terminal
+    // there's no one to ask; the task can still be restored from the trash.
+    let interactive = std::io::stdin().is_terminal();
+    let mut needs_confirmation = interactive;
+    let mode = if subtask_count == 0 {
+        DeleteMode::Abort
+    } else {
+        let mode = match subtasks {
+            Some(SubtaskMode::Cascade) => DeleteMode::Cascade,
+            Some(SubtaskMode::Promote) => DeleteMode::Promote,
+            Some(SubtaskMode::Abort) => DeleteMode::Abort,
+            None if !interactive => DeleteMode::Abort,
+            None => {
+                let promote_target = match task.parent_id.and_then(|id| storage.get_task(id)) {
+                    Some(parent) => format!("under '{}'", parent.title),
+                    None => "to the top level".to_string(),
+                };
+                let choices = [
+                    format!("Delete it and its {} subtask(s)", subtask_count),
+                    format!("Delete it and move its subtasks {}", promote_target),
+                    "Cancel".to_string(),
+                ];
+                let selection = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!(
+                        "'{}' [{}] has {} subtask(s)",
+                        task.title,
+                        task.short_id(),
+                        subtask_count
+                    ))
+                    .items(&choices)
+                    .default(2)
+                    .interact_opt()?;
+                needs_confirmation = false;
+                match selection {
+                    Some(0) => DeleteMode::Cascade,
+                    Some(1) => DeleteMode::Promote,
+                    _ => {
+                        println!("Cancelled");
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        if mode == DeleteMode::Abort {
+            anyhow::bail!(
+                "'{}' has {} subtask(s); not deleted. Use --subtasks cascade or --subtasks promote",
+                task.title,
+                subtask_count
+            );
+        }
+        mode
+    };
 
-    // Check for children
-    let children = storage.get_children(task_id);
-    if !children.is_empty() {
-        println!("Warning: This task has {} subtask(s).", children.len());
-    }
-
-    let confirmation = Confirm::with_theme(&ColorfulTheme::default())
-        .with_prompt(format!(
-            "Delete task '{}' [{}]?",
-            task.title,
-            task.short_id()
-        ))
-        .default(false)
-        .interact()?;
+    let confirmation = !needs_confirmation
+        || Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Delete task '{}' [{}]?",
+                task.title,
+                task.short_id()
+            ))
+            .default(false)
+            .interact()?;
 
     if confirmation {
-        storage.delete_task(task_id)?;
-        println!("✓ Task deleted");
+        let removed = storage.delete_task(task_id, mode)?;
+        if removed > 1 {
+            println!("✓ Moved {} tasks to the trash", removed);
+        } else {
+            println!("✓ Task moved to the trash");
+        }
     } else {
         println!("Cancelled");
     }
@@ -357,16 +659,96 @@ pub fn delete_task() -> Result<()> {
     Ok(())
 }
 
-pub fn tag_task(tags: Vec<String>) -> Result<()> {
+/// Move every task matching `filter` to the trash as one change, after asking
+/// when there's a terminal. Subtasks that don't match are handled as `subtasks`
+/// says, refusing by default.
+fn delete_matching(
+    storage: &mut dyn TaskStore,
+    filter: &Filter,
+    calendar: &WorkCalendar,
+    subtasks: Option<SubtaskMode>,
+) -> Result<()> {
+    let ids = matching_tasks(storage, filter, calendar, |_| true);
+    if ids.is_empty() {
+        println!("No tasks match '{}'.", filter.as_str());
+        return Ok(());
+    }
+    let mode = match subtasks {
+        Some(SubtaskMode::Cascade) => DeleteMode::Cascade,
+        Some(SubtaskMode::Promote) => DeleteMode::Promote,
+        Some(SubtaskMode::Abort) | None => DeleteMode::Abort,
+    };
+
+    for id in &ids {
+        let task = storage.get_task(*id).context("Task not found")?;
+        println!("  {} [{}]", task.title, task.short_id());
+    }
+    let confirmation = !std::io::stdin().is_terminal()
+        || Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Delete these {} task(s)?", ids.len()))
+            .default(false)
+            .interact()?;
+    if !confirmation {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let removed = storage.delete_tasks(&ids, mode).map_err(|e| {
+        e.context("Not deleted. Use --subtasks cascade or --subtasks promote for their subtasks")
+    })?;
+    if removed > 1 {
+        println!("✓ Moved {} tasks to the trash", removed);
+    } else {
+        println!("✓ Task moved to the trash");
+    }
+    Ok(())
+}
+
+pub fn tag_task(
+    scope: &Scope,
+    task: Option<String>,
+    filter: Option<&Filter>,
+    tags: Vec<String>,
+) -> Result<()> {
     let paths = DataPaths::new()?;
-    let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+    let config = json_store::load_config(&paths.config_file())?;
+    let owner = scope.owner(&config)?;
+    let mut storage = new_task_store(&paths, &config, &owner);
     storage.load()?;
 
-    let mut all_tasks: Vec<Task> = storage.get_all_tasks().to_vec();
+    if let Some(filter) = filter {
+        let calendar = config.work_calendar.for_owner(&owner);
+        let ids = matching_tasks(storage.as_ref(), filter, &calendar, |_| true);
+        let mut tagged = 0;
+        for id in &ids {
+            let task = storage.get_task_mut(*id).context("Task not found")?;
+            let missing: Vec<&String> = tags.iter().filter(|t| !task.tags.contains(t)).collect();
+            if !missing.is_empty() {
+                task.tags.extend(missing.into_iter().cloned());
+                tagged += 1;
+            }
+        }
+        if tagged > 0 {
+            storage.save()?;
+        }
+        let tags: Vec<String> = tags.iter().map(|t| format!("#{}", t)).collect();
+        println!(
+            "✓ Tagged {} of {} matching task(s) with {}",
+            tagged,
+            ids.len(),
+            tags.join(" ")
+        );
+        return Ok(());
+    }
 
-    let task_id = if let Some(task) = select_task_mut(&mut all_tasks, "Select task to tag")? {
-        task.id
-    } else {
+    let Some(task_id) = choose_task(
+        storage.as_ref(),
+        task.as_deref(),
+        config.id_prefix_length,
+        "Select task to tag",
+        |_| true,
+    )?
+    else {
         return Ok(());
     };
 
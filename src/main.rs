@@ -8,15 +8,51 @@ mod utils;
 use anyhow::Result;
 use clap::{CommandFactory, Parser};
 use clap_complete::{generate, Shell};
-use cli::{Cli, Commands, ReporteeCommands};
+use cli::{Cli, Commands, DataCommands, ReporteeCommands, TimeCommands, TrashCommands};
 use std::io;
+use utils::filter::Filter;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Some(dir) = cli.data_dir {
+        storage::paths::set_data_dir_override(dir);
+    }
+
+    let scope = commands::Scope {
+        reportee: cli.for_reportee,
+        all: false,
+    };
+    // Read-only commands can cover every store with --all
+    let scope_all = |all: bool| scope.with_all(all);
+    // Doctor and archive always do unless --for picks one
+    let scope_every = commands::Scope {
+        all: true,
+        ..scope.clone()
+    };
+    // Filters read their dates with the configured week start
+    let config = || storage::json_store::load_config(&storage::DataPaths::new()?.config_file());
+    // The filter terms of list and tree, with their --filter query
+    let with_query = |mut terms: Vec<String>, query: Option<String>| {
+        terms.extend(query);
+        terms
+    };
+    // The --filter of commands acting on every matching task
+    let bulk_filter = |query: Option<&str>| -> Result<Option<Filter>> {
+        let Some(query) = query else {
+            return Ok(None);
+        };
+        let filter = Filter::parse(query, &config()?)?;
+        if filter.is_empty() {
+            anyhow::bail!("An empty --filter would match every task");
+        }
+        Ok(Some(filter))
+    };
+
     match cli.command {
         None => {
             // No command provided - launch TUI
+            scope.reject_for("twig")?;
             tui::run_tui()?;
         }
         Some(Commands::Add {
@@ -27,64 +63,212 @@ fn main() -> Result<()> {
             eta,
             description,
         }) => {
-            commands::add_task(title, parent, tags, estimate, eta, description)?;
+            commands::add_task(&scope, title, parent, tags, estimate, eta, description)?;
         }
-        Some(Commands::Start) => {
-            commands::start_task()?;
+        Some(Commands::Start { task }) => {
+            commands::start_task(&scope, task)?;
         }
-        Some(Commands::Complete) => {
-            commands::complete_task()?;
+        Some(Commands::Complete { task, filter }) => {
+            let filter = bulk_filter(filter.as_deref())?;
+            commands::complete_task(&scope, task, filter.as_ref())?;
         }
-        Some(Commands::Cancel) => {
-            commands::cancel_task()?;
+        Some(Commands::Cancel { task, filter }) => {
+            let filter = bulk_filter(filter.as_deref())?;
+            commands::cancel_task(&scope, task, filter.as_ref())?;
         }
-        Some(Commands::Pause) => {
-            commands::pause_task()?;
+        Some(Commands::Pause { task }) => {
+            commands::pause_task(&scope, task)?;
         }
-        Some(Commands::List { status, tag }) => {
-            commands::list_tasks(status, tag)?;
+        Some(Commands::Status) => {
+            commands::show_status()?;
         }
-        Some(Commands::Show) => {
-            commands::show_task()?;
+        Some(Commands::Log {
+            task,
+            duration,
+            at,
+            note,
+        }) => {
+            commands::log_time(&scope, task, duration, at, note)?;
         }
-        Some(Commands::Tree) => {
-            commands::show_tree()?;
+        Some(Commands::Time { command }) => match command {
+            TimeCommands::List { task } => {
+                commands::list_time_entries(&scope, task)?;
+            }
+            TimeCommands::Edit {
+                task,
+                entry,
+                start,
+                end,
+                duration,
+                note,
+            } => {
+                let edit = commands::time::EntryEdit {
+                    start,
+                    end,
+                    duration,
+                    note,
+                };
+                commands::edit_time_entry(&scope, task, entry, edit)?;
+            }
+            TimeCommands::Delete { task, entry } => {
+                commands::delete_time_entry(&scope, task, entry)?;
+            }
+        },
+        Some(Commands::List {
+            status,
+            tag,
+            terms,
+            filter,
+            sort,
+            reverse,
+            columns,
+            all,
+            format,
+        }) => {
+            let filter = Filter::from_args(&with_query(terms, filter), &config()?)?;
+            let options = commands::list::ListOptions {
+                sort,
+                reverse,
+                columns,
+            };
+            commands::list_tasks(&scope_all(all)?, status, tag, &filter, &options, format)?;
+        }
+        Some(Commands::Show { task, format }) => {
+            commands::show_task(&scope, task, format)?;
+        }
+        Some(Commands::Tree {
+            terms,
+            filter,
+            all,
+            format,
+        }) => {
+            let filter = Filter::from_args(&with_query(terms, filter), &config()?)?;
+            commands::show_tree(&scope_all(all)?, &filter, format)?;
         }
         Some(Commands::Update {
+            task,
             title,
             description,
             estimate,
             eta,
         }) => {
-            commands::update_task(title, description, estimate, eta)?;
+            commands::update_task(&scope, task, title, description, estimate, eta)?;
         }
-        Some(Commands::Delete) => {
-            commands::delete_task()?;
+        Some(Commands::Delete {
+            task,
+            filter,
+            subtasks,
+        }) => {
+            let filter = bulk_filter(filter.as_deref())?;
+            commands::delete_task(&scope, task, filter.as_ref(), subtasks)?;
+        }
+        Some(Commands::Tag { mut args, filter }) => {
+            let filter = bulk_filter(filter.as_deref())?;
+            // A lone argument is a tag for an interactively picked task
+            let task = if args.len() > 1 && filter.is_none() {
+                Some(args.remove(0))
+            } else {
+                None
+            };
+            commands::tag_task(&scope, task, filter.as_ref(), args)?;
+        }
+        Some(Commands::Delegate { task, to, stub }) => {
+            commands::delegate_task(&scope, task, to, stub)?;
         }
-        Some(Commands::Tag { tags }) => {
-            commands::tag_task(tags)?;
+        Some(Commands::Undo) => {
+            commands::undo(&scope)?;
         }
-        Some(Commands::Reportee { command }) => match command {
-            ReporteeCommands::Add { name } => {
-                commands::add_reportee(name)?;
+        Some(Commands::Redo) => {
+            commands::redo(&scope)?;
+        }
+        Some(Commands::Archive { before, filter }) => {
+            let filter = bulk_filter(filter.as_deref())?;
+            commands::archive_tasks(&scope_every, before, filter.as_ref())?;
+        }
+        Some(Commands::Trash { command }) => match command {
+            TrashCommands::List => {
+                commands::list_trash(&scope)?;
             }
-            ReporteeCommands::List => {
-                commands::list_reportees()?;
+            TrashCommands::Restore { id } => {
+                commands::restore_from_trash(&scope, id)?;
             }
-            ReporteeCommands::Remove { name } => {
-                commands::remove_reportee(name)?;
+            TrashCommands::Empty { older_than, filter } => {
+                let filter = bulk_filter(filter.as_deref())?;
+                commands::empty_trash(&scope, older_than, filter.as_ref())?;
             }
         },
-        Some(Commands::Report { period, date }) => {
-            commands::generate_report(period, date)?;
+        Some(Commands::Reportee { command }) => {
+            scope.reject_for("twig reportee")?;
+            match command {
+                ReporteeCommands::Add { name } => {
+                    commands::add_reportee(name)?;
+                }
+                ReporteeCommands::List => {
+                    commands::list_reportees()?;
+                }
+                ReporteeCommands::Remove { name } => {
+                    commands::remove_reportee(name)?;
+                }
+            }
         }
-        Some(Commands::Stats { period, date }) => {
-            commands::show_stats(period, date)?;
+        Some(Commands::Data { command }) => {
+            scope.reject_for("twig data")?;
+            match command {
+                DataCommands::Path => {
+                    commands::show_data_paths()?;
+                }
+                DataCommands::Migrate => {
+                    commands::migrate_data_dir()?;
+                }
+            }
+        }
+        Some(Commands::Doctor { fix }) => {
+            commands::doctor(&scope_every, fix)?;
+        }
+        Some(Commands::Report {
+            period,
+            date,
+            from,
+            to,
+            last_days,
+            filter,
+            all,
+            format,
+        }) => {
+            let filter = Filter::parse(filter.as_deref().unwrap_or(""), &config()?)?;
+            let dates = commands::report::PeriodDates {
+                date,
+                from,
+                to,
+                last_days,
+            };
+            commands::generate_report(&scope_all(all)?, period, dates, &filter, format)?;
+        }
+        Some(Commands::Stats {
+            period,
+            date,
+            from,
+            to,
+            last_days,
+            filter,
+            all,
+            format,
+        }) => {
+            let filter = Filter::parse(filter.as_deref().unwrap_or(""), &config()?)?;
+            let dates = commands::report::PeriodDates {
+                date,
+                from,
+                to,
+                last_days,
+            };
+            commands::show_stats(&scope_all(all)?, period, dates, &filter, format)?;
         }
         Some(Commands::Tui) => {
+            scope.reject_for("twig tui")?;
             tui::run_tui()?;
         }
         Some(Commands::Completions { shell }) => {
+            scope.reject_for("twig completions")?;
             generate_completions(shell);
         }
     }
@@ -1,9 +1,271 @@
+use chrono::{FixedOffset, Weekday};
+use chrono_tz::Tz;
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub reportees: Vec<String>,
     pub default_view: ViewMode,
+    #[serde(default)]
+    pub storage_backend: StorageBackend,
+    /// Shortest task ID prefix accepted (and displayed) as a task reference
+    #[serde(default = "default_id_prefix_length")]
+    pub id_prefix_length: usize,
+    /// `twig list` order when `--sort` isn't given
+    #[serde(default)]
+    pub list_sort: ListSort,
+    #[serde(default)]
+    pub list_reverse: bool,
+    /// `twig list` columns when `--columns` isn't given
+    #[serde(default = "default_list_columns")]
+    pub list_columns: Vec<ListColumn>,
+    /// Whether starting a task pauses timers running on other tasks
+    #[serde(default)]
+    pub timer_policy: TimerPolicy,
+    /// Working hours and days behind effort conversions (1d, 1w, 1mo)
+    #[serde(default)]
+    pub work_calendar: WorkCalendar,
+    /// Timezone that days, weeks and months start in for reports, stats and
+    /// the TUI's History view
+    #[serde(default)]
+    pub timezone: Timezone,
+    /// First day of the week for weekly reports, stats and the History view
+    #[serde(default = "default_week_start")]
+    pub week_start: Weekday,
+}
+
+fn default_id_prefix_length() -> usize {
+    4
+}
+
+fn default_week_start() -> Weekday {
+    Weekday::Mon
+}
+
+fn default_list_columns() -> Vec<ListColumn> {
+    vec![
+        ListColumn::Id,
+        ListColumn::Status,
+        ListColumn::Title,
+        ListColumn::Tags,
+        ListColumn::Time,
+        ListColumn::Created,
+    ]
+}
+
+/// What `twig list` sorts tasks by.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ListSort {
+    #[default]
+    Created,
+    Eta,
+    Estimate,
+    Time,
+    Status,
+    Title,
+}
+
+/// A column `twig list` can show.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ListColumn {
+    Id,
+    Status,
+    Title,
+    Description,
+    Tags,
+    /// Titles of the parent tasks
+    Path,
+    Owner,
+    Estimate,
+    /// Estimate minus the time tracked so far
+    Remaining,
+    Time,
+    Eta,
+    Created,
+    Started,
+    Completed,
+}
+
+/// The working schedule that effort units are counted in: a day is
+/// `hours_per_day`, a week one day per entry of `work_days` and a month 4 weeks.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkCalendar {
+    pub hours_per_day: f64,
+    pub work_days: Vec<Weekday>,
+    /// Reportees working a different schedule
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub reportees: BTreeMap<String, CalendarOverride>,
+}
+
+/// The parts of a reportee's schedule that differ from the work calendar.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CalendarOverride {
+    pub hours_per_day: Option<f64>,
+    pub work_days: Option<Vec<Weekday>>,
+}
+
+impl Default for WorkCalendar {
+    fn default() -> Self {
+        Self {
+            hours_per_day: 8.0,
+            work_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            reportees: BTreeMap::new(),
+        }
+    }
+}
+
+impl WorkCalendar {
+    /// The calendar `owner` ("me" or a reportee name) works to.
+    pub fn for_owner(&self, owner: &str) -> WorkCalendar {
+        let mut calendar = WorkCalendar {
+            reportees: BTreeMap::new(),
+            ..self.clone()
+        };
+        if let Some(schedule) = self.reportees.get(owner) {
+            if let Some(hours) = schedule.hours_per_day {
+                calendar.hours_per_day = hours;
+            }
+            if let Some(days) = &schedule.work_days {
+                calendar.work_days = days.clone();
+            }
+        }
+        calendar
+    }
+
+    /// Check that every day has some hours and every week some days, for
+    /// everyone, so efforts never divide by zero or count a day twice.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        check_schedule(self.hours_per_day, &self.work_days)?;
+        for name in self.reportees.keys() {
+            let calendar = self.for_owner(name);
+            check_schedule(calendar.hours_per_day, &calendar.work_days)
+                .map_err(|e| anyhow::anyhow!("{} (in {}'s schedule)", e, name))?;
+        }
+        Ok(())
+    }
+
+    pub fn hours_per_week(&self) -> f64 {
+        self.hours_per_day * self.work_days.len() as f64
+    }
+
+    pub fn hours_per_month(&self) -> f64 {
+        self.hours_per_week() * 4.0
+    }
+}
+
+fn check_schedule(hours_per_day: f64, work_days: &[Weekday]) -> anyhow::Result<()> {
+    if !(hours_per_day > 0.0 && hours_per_day <= 24.0) {
+        anyhow::bail!(
+            "hours_per_day must be more than 0 and at most 24, not {}",
+            hours_per_day
+        );
+    }
+    if work_days.is_empty() {
+        anyhow::bail!("work_days must list at least one day");
+    }
+    for (i, day) in work_days.iter().enumerate() {
+        if work_days[..i].contains(day) {
+            anyhow::bail!("work_days lists {} more than once", day);
+        }
+    }
+    Ok(())
+}
+
+/// How many tasks can have a running timer at once.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimerPolicy {
+    /// Starting a task pauses whatever else is running, in every store
+    #[default]
+    Single,
+    /// Timers run side by side until paused
+    Multiple,
+}
+
+/// The timezone dates are read and periods counted in: the system's, a named
+/// zone that follows its daylight saving changes, or a fixed offset from UTC.
+/// Written "local", like "Europe/Paris", "utc" or like "+05:30" and "-0800" in
+/// the config.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub enum Timezone {
+    #[default]
+    Local,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid timezone: '{}'. Use local, a zone like Europe/Paris, utc or an \
+                 offset like +05:30",
+                input
+            )
+        };
+        let input = input.trim();
+        match input.to_lowercase().as_str() {
+            "local" => return Ok(Timezone::Local),
+            "utc" | "z" => return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+            _ => {}
+        }
+        if let Ok(zone) = input.parse::<Tz>() {
+            return Ok(Timezone::Named(zone));
+        }
+
+        let (sign, offset) = match input.split_at_checked(1) {
+            Some(("+", offset)) => (1, offset),
+            Some(("-", offset)) => (-1, offset),
+            _ => return Err(invalid()),
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some(parts) => parts,
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        if minutes >= 60 {
+            return Err(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(Timezone::Fixed)
+            .ok_or_else(invalid)
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Timezone> for String {
+    fn from(timezone: Timezone) -> Self {
+        match timezone {
+            Timezone::Local => "local".to_string(),
+            Timezone::Named(zone) => zone.name().to_string(),
+            Timezone::Fixed(offset) => offset.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -13,11 +275,28 @@ pub enum ViewMode {
     List,
 }
 
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Sqlite,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             reportees: Vec::new(),
             default_view: ViewMode::Tree,
+            storage_backend: StorageBackend::default(),
+            id_prefix_length: default_id_prefix_length(),
+            list_sort: ListSort::default(),
+            list_reverse: false,
+            list_columns: default_list_columns(),
+            timer_policy: TimerPolicy::default(),
+            work_calendar: WorkCalendar::default(),
+            timezone: Timezone::default(),
+            week_start: default_week_start(),
         }
     }
 }
@@ -1,5 +1,5 @@
 pub mod config;
 pub mod task;
 
-pub use config::Config;
-pub use task::{Task, TaskStatus};
+pub use config::{Config, ListColumn, ListSort, TimerPolicy, Timezone, WorkCalendar};
+pub use task::{Delegation, Task, TaskStatus, TimeEntry};
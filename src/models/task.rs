@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use crate::models::WorkCalendar;
+use crate::utils::date::format_duration_human;
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -16,6 +18,9 @@ pub struct TimeEntry {
     pub start: DateTime<Utc>,
     pub end: Option<DateTime<Utc>>,
     pub duration_seconds: Option<i64>,
+    /// What the time was spent on, for entries logged or edited by hand
+    #[serde(default)]
+    pub note: String,
 }
 
 impl TimeEntry {
@@ -24,6 +29,7 @@ impl TimeEntry {
             start,
             end: None,
             duration_seconds: None,
+            note: String::new(),
         }
     }
 
@@ -37,67 +43,145 @@ impl TimeEntry {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Link from a stub task to the task it stands in for after being delegated to
+/// another store.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Delegation {
+    /// Owner of the store the task was moved to ("me" or a reportee name)
+    pub owner: String,
+    pub task_id: Uuid,
+}
+
+/// An amount of work time, as given for estimates and logged time. How long a
+/// day, week or month is depends on the `WorkCalendar` it's counted in.
+#[derive(Debug, Clone, PartialEq)]
 pub struct EffortEstimate {
-    pub value: f64,
-    pub unit: EffortUnit,
+    parts: Vec<(f64, EffortUnit)>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EffortUnit {
+    Seconds,
+    Minutes,
     Hours,
     Days,
     Weeks,
     Months,
 }
 
+impl EffortUnit {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "s" | "sec" | "secs" | "second" | "seconds" => Some(EffortUnit::Seconds),
+            "m" | "min" | "mins" | "minute" | "minutes" => Some(EffortUnit::Minutes),
+            "h" | "hr" | "hrs" | "hour" | "hours" => Some(EffortUnit::Hours),
+            "d" | "day" | "days" => Some(EffortUnit::Days),
+            "w" | "wk" | "wks" | "week" | "weeks" => Some(EffortUnit::Weeks),
+            "mo" | "mon" | "month" | "months" => Some(EffortUnit::Months),
+            _ => None,
+        }
+    }
+
+    pub fn hours(self, calendar: &WorkCalendar) -> f64 {
+        match self {
+            EffortUnit::Seconds => 1.0 / 3600.0,
+            EffortUnit::Minutes => 1.0 / 60.0,
+            EffortUnit::Hours => 1.0,
+            EffortUnit::Days => calendar.hours_per_day,
+            EffortUnit::Weeks => calendar.hours_per_week(),
+            EffortUnit::Months => calendar.hours_per_month(),
+        }
+    }
+}
+
 impl EffortEstimate {
-    /// Parse effort string like "1h", "2d", "3w", "2m"
+    /// Parse an effort like "4h", "1h30m", "90min", "1.5 days", "2d4h" or "2mo".
+    /// Parts may be separated by spaces; "m" is minutes and "mo" months.
     pub fn parse(s: &str) -> anyhow::Result<Self> {
-        let s = s.trim().to_lowercase();
-        let (num_str, unit_str) = s.split_at(s.len() - 1);
-
-        let value: f64 = num_str
-            .parse()
-            .map_err(|_| anyhow::anyhow!("Invalid effort value: {}", num_str))?;
-
-        let unit = match unit_str {
-            "h" => EffortUnit::Hours,
-            "d" => EffortUnit::Days,
-            "w" => EffortUnit::Weeks,
-            "m" => EffortUnit::Months,
-            _ => {
-                return Err(anyhow::anyhow!(
-                    "Invalid effort unit: {}. Use h/d/w/m",
-                    unit_str
-                ))
-            }
+        let input = s.trim().to_lowercase();
+        let invalid = || {
+            anyhow::anyhow!(
+                "Invalid effort: {}. Use e.g. 4h, 1h30m, 90min, 1.5d or 2mo",
+                input
+            )
         };
 
-        Ok(Self { value, unit })
+        let mut parts = Vec::new();
+        let mut rest = input.as_str();
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        while !rest.is_empty() {
+            let split = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let value: f64 = rest[..split].parse().map_err(|_| invalid())?;
+            rest = rest[split..].trim_start();
+
+            let split = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let unit = match &rest[..split] {
+                "" => return Err(anyhow::anyhow!("Missing unit in effort: {}", input)),
+                name => EffortUnit::from_name(name).ok_or_else(|| {
+                    anyhow::anyhow!("Invalid effort unit: {}. Use s, m(in), h, d, w or mo", name)
+                })?,
+            };
+            parts.push((value, unit));
+            rest = rest[split..].trim_start_matches([' ', ',']);
+        }
+
+        Ok(Self { parts })
     }
 
     /// Convert to hours for storage
-    pub fn to_hours(&self) -> f64 {
-        match self.unit {
-            EffortUnit::Hours => self.value,
-            EffortUnit::Days => self.value * 8.0, // 8 hour workday
-            EffortUnit::Weeks => self.value * 40.0, // 5 day work week
-            EffortUnit::Months => self.value * 160.0, // ~4 weeks per month
+    pub fn to_hours(&self, calendar: &WorkCalendar) -> f64 {
+        self.parts
+            .iter()
+            .map(|(value, unit)| value * unit.hours(calendar))
+            .sum()
+    }
+
+    /// The effort as a duration, to the nearest second, or `None` if it's too
+    /// long to represent
+    pub fn to_duration(&self, calendar: &WorkCalendar) -> Option<Duration> {
+        let seconds = (self.to_hours(calendar) * 3600.0).round();
+        if !seconds.is_finite() || seconds.abs() >= i64::MAX as f64 {
+            return None;
         }
+        Duration::try_seconds(seconds as i64)
     }
 
-    /// Format hours back to human readable format
-    pub fn from_hours(hours: f64) -> String {
-        if hours < 8.0 {
-            format!("{:.1}h", hours)
-        } else if hours < 40.0 {
-            format!("{:.1}d", hours / 8.0)
-        } else if hours < 160.0 {
-            format!("{:.1}w", hours / 40.0)
-        } else {
-            format!("{:.1}m", hours / 160.0)
+    /// Format hours back to human readable format, like "1d 4h" or "2h 30m"
+    /// (to the nearest minute), which `parse` reads back
+    pub fn from_hours(hours: f64, calendar: &WorkCalendar) -> String {
+        let total_minutes = (hours.abs() * 60.0).round() as i64;
+        let mut minutes = total_minutes;
+        let units = [
+            (EffortUnit::Months, "mo"),
+            (EffortUnit::Weeks, "w"),
+            (EffortUnit::Days, "d"),
+            (EffortUnit::Hours, "h"),
+        ];
+
+        let mut parts = Vec::new();
+        for (unit, suffix) in units {
+            let unit_minutes = (unit.hours(calendar) * 60.0).round() as i64;
+            if unit_minutes > 0 && minutes >= unit_minutes {
+                parts.push(format!("{}{}", minutes / unit_minutes, suffix));
+                minutes %= unit_minutes;
+            }
+        }
+        if minutes > 0 || parts.is_empty() {
+            parts.push(format!("{}m", minutes));
         }
+
+        let sign = if hours < 0.0 && total_minutes > 0 {
+            "-"
+        } else {
+            ""
+        };
+        format!("{}{}", sign, parts.join(" "))
     }
 }
 
@@ -119,6 +203,14 @@ pub struct Task {
     pub total_time_seconds: i64,
     #[serde(default)]
     pub notes: String,
+    /// When the task was moved to the trash; trashed tasks are hidden everywhere
+    /// except the trash itself
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Set on a stub left behind by `twig delegate --stub`; its status mirrors
+    /// the delegated task's
+    #[serde(default)]
+    pub delegated_to: Option<Delegation>,
 }
 
 impl Task {
@@ -139,10 +231,17 @@ impl Task {
             time_entries: Vec::new(),
             total_time_seconds: 0,
             notes: String::new(),
+            deleted_at: None,
+            delegated_to: None,
         }
     }
 
+    /// Start (or resume) the task and its timer. A task whose timer is already
+    /// running keeps its current time entry.
     pub fn start(&mut self) {
+        if self.has_active_time_entry() {
+            return;
+        }
         if self.status == TaskStatus::NotStarted {
             self.started_at = Some(Utc::now());
         }
@@ -170,52 +269,83 @@ impl Task {
 
     fn end_active_time_entry(&mut self) {
         if let Some(entry) = self.time_entries.iter_mut().find(|e| e.is_active()) {
-            let now = Utc::now();
-            entry.end_entry(now);
-            self.total_time_seconds += entry.duration_seconds.unwrap_or(0);
+            entry.end_entry(Utc::now());
+            self.recompute_total_time();
         }
     }
 
-    pub fn has_active_time_entry(&self) -> bool {
-        self.time_entries.iter().any(|e| e.is_active())
+    /// Add a finished time entry for work done from `start` for `duration`,
+    /// keeping the entries in start order. Returns the new entry's index.
+    pub fn log_time(&mut self, start: DateTime<Utc>, duration: Duration, note: String) -> usize {
+        let mut entry = TimeEntry::new(start);
+        entry.end_entry(start + duration);
+        entry.note = note;
+
+        let index = self.time_entries.partition_point(|e| e.start <= start);
+        self.time_entries.insert(index, entry);
+        self.recompute_total_time();
+        index
     }
 
-    pub fn set_estimate(&mut self, estimate: &str) -> anyhow::Result<()> {
-        let effort = EffortEstimate::parse(estimate)?;
-        self.estimated_effort_hours = Some(effort.to_hours());
-        Ok(())
+    /// Put the time entries back in start order and recompute the total time
+    /// after they were edited.
+    pub fn time_entries_changed(&mut self) {
+        self.time_entries.sort_by_key(|e| e.start);
+        self.recompute_total_time();
     }
 
-    pub fn get_formatted_estimate(&self) -> Option<String> {
-        self.estimated_effort_hours.map(EffortEstimate::from_hours)
+    /// Set `total_time_seconds` to the sum of the finished time entries.
+    pub fn recompute_total_time(&mut self) {
+        self.total_time_seconds = self
+            .time_entries
+            .iter()
+            .filter_map(|e| e.duration_seconds)
+            .sum();
     }
 
-    pub fn get_formatted_total_time(&self) -> String {
-        let seconds = self.total_time_seconds;
-        if seconds < 60 {
-            return format!("{}s", seconds);
-        }
+    /// Seconds worked between `start` and `end`: the parts of the time entries
+    /// that fall in between, with a running entry counted up to `now`.
+    pub fn time_worked_between(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> i64 {
+        self.time_entries
+            .iter()
+            .map(|entry| {
+                let from = entry.start.max(start);
+                let to = entry.end.unwrap_or(now).min(end);
+                (to - from).num_seconds().max(0)
+            })
+            .sum()
+    }
 
-        let minutes = seconds / 60;
-        let hours = minutes / 60;
-        let days = hours / 8; // 8-hour work days
+    pub fn is_delegated(&self) -> bool {
+        self.delegated_to.is_some()
+    }
 
-        let remaining_hours = hours % 8;
-        let remaining_minutes = minutes % 60;
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
 
-        let mut parts = Vec::new();
+    pub fn has_active_time_entry(&self) -> bool {
+        self.time_entries.iter().any(|e| e.is_active())
+    }
 
-        if days > 0 {
-            parts.push(format!("{}d", days));
-        }
-        if remaining_hours > 0 || days > 0 {
-            parts.push(format!("{}h", remaining_hours));
-        }
-        if remaining_minutes > 0 || parts.is_empty() {
-            parts.push(format!("{}m", remaining_minutes));
-        }
+    pub fn set_estimate(&mut self, estimate: &str, calendar: &WorkCalendar) -> anyhow::Result<()> {
+        let effort = EffortEstimate::parse(estimate)?;
+        self.estimated_effort_hours = Some(effort.to_hours(calendar));
+        Ok(())
+    }
+
+    pub fn get_formatted_estimate(&self, calendar: &WorkCalendar) -> Option<String> {
+        self.estimated_effort_hours
+            .map(|hours| EffortEstimate::from_hours(hours, calendar))
+    }
 
-        parts.join(" ")
+    pub fn get_formatted_total_time(&self, calendar: &WorkCalendar) -> String {
+        format_duration_human(self.total_time_seconds, calendar)
     }
 
     pub fn short_id(&self) -> String {
@@ -226,6 +356,8 @@ impl Task {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::config::CalendarOverride;
+    use chrono::Weekday;
 
     #[test]
     fn test_effort_parsing() {
@@ -233,14 +365,65 @@ mod tests {
             ("1h", 1.0),
             ("2d", 16.0),
             ("1w", 40.0),
-            ("2m", 320.0),
+            ("2mo", 320.0),
             ("0.5h", 0.5),
+            ("1h30m", 1.5),
+            ("90min", 1.5),
+            ("1.5 days", 12.0),
+            ("2d4h", 20.0),
+            ("1w 2d", 56.0),
+            ("30m", 0.5),
         ];
 
+        let calendar = WorkCalendar::default();
         for (input, expected_hours) in cases {
             let effort = EffortEstimate::parse(input).unwrap();
-            assert_eq!(effort.to_hours(), expected_hours);
+            assert_eq!(effort.to_hours(&calendar), expected_hours);
         }
+
+        for invalid in ["", "2", "h", "3x", "1.2.3h"] {
+            assert!(EffortEstimate::parse(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_effort_round_trips() {
+        let calendar = WorkCalendar::default();
+        for hours in [0.0, 0.25, 1.5, 7.0, 12.0, 41.75, 320.0, 500.5] {
+            let formatted = EffortEstimate::from_hours(hours, &calendar);
+            let parsed = EffortEstimate::parse(&formatted)
+                .unwrap()
+                .to_hours(&calendar);
+            assert!((parsed - hours).abs() < 1e-9, "{} -> {}", hours, formatted);
+        }
+        assert_eq!(EffortEstimate::from_hours(12.0, &calendar), "1d 4h");
+        assert_eq!(EffortEstimate::from_hours(-1.5, &calendar), "-1h 30m");
+    }
+
+    #[test]
+    fn test_efforts_follow_the_work_calendar() {
+        let mut calendar = WorkCalendar::default();
+        calendar.reportees.insert(
+            "alice".to_string(),
+            CalendarOverride {
+                hours_per_day: Some(4.0),
+                work_days: Some(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed]),
+            },
+        );
+        let alice = calendar.for_owner("alice");
+        assert_eq!(calendar.for_owner("me"), WorkCalendar::default());
+
+        let effort = EffortEstimate::parse("1w 1d").unwrap();
+        assert_eq!(effort.to_hours(&calendar), 48.0);
+        assert_eq!(effort.to_hours(&alice), 16.0);
+        assert_eq!(EffortEstimate::from_hours(16.0, &alice), "1w 1d");
+        assert_eq!(format_duration_human(6 * 3600, &alice), "1d 2h");
+
+        let duration = |input: &str| EffortEstimate::parse(input).unwrap().to_duration(&calendar);
+        assert_eq!(duration("1h30m"), Some(Duration::minutes(90)));
+        assert!(duration("10000000mo").is_some());
+        assert_eq!(duration("100000000000000mo"), None);
+        assert_eq!(duration(&format!("{}h", "9".repeat(400))), None);
     }
 
     #[test]
@@ -258,4 +441,64 @@ mod tests {
         assert!(task.completed_at.is_some());
         assert!(!task.has_active_time_entry());
     }
+
+    #[test]
+    fn test_logged_time_is_sorted_and_totalled() {
+        let mut task = Task::new("Test task".to_string());
+        let now = Utc::now();
+        task.log_time(
+            now - Duration::hours(2),
+            Duration::minutes(30),
+            String::new(),
+        );
+        let index = task.log_time(
+            now - Duration::hours(5),
+            Duration::minutes(90),
+            "Offline".to_string(),
+        );
+        assert_eq!(index, 0);
+        assert_eq!(task.time_entries[0].note, "Offline");
+        assert_eq!(task.total_time_seconds, 2 * 3600);
+
+        task.time_entries.remove(0);
+        task.time_entries_changed();
+        assert_eq!(task.total_time_seconds, 30 * 60);
+    }
+
+    #[test]
+    fn test_time_worked_is_clipped_to_the_period() {
+        let mut task = Task::new("Test task".to_string());
+        let start = Utc::now() - Duration::days(10);
+        let end = start + Duration::days(1);
+        // Straddles the start, inside, straddles the end, and after
+        task.log_time(
+            start - Duration::hours(2),
+            Duration::hours(3),
+            String::new(),
+        );
+        task.log_time(
+            start + Duration::hours(5),
+            Duration::minutes(45),
+            String::new(),
+        );
+        task.log_time(
+            end - Duration::minutes(30),
+            Duration::hours(2),
+            String::new(),
+        );
+        task.log_time(end + Duration::hours(1), Duration::hours(1), String::new());
+
+        let now = Utc::now();
+        let worked = task.time_worked_between(start, end, now);
+        assert_eq!(worked, 3600 + 45 * 60 + 30 * 60);
+        assert_eq!(task.time_worked_between(end, now, now), 90 * 60 + 3600);
+
+        // A running timer counts up to now
+        task.time_entries
+            .push(TimeEntry::new(now - Duration::minutes(20)));
+        assert_eq!(
+            task.time_worked_between(now - Duration::hours(1), now, now),
+            20 * 60
+        );
+    }
 }
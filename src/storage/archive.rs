@@ -0,0 +1,230 @@
+use crate::models::{Config, Task, TaskStatus, Timezone};
+use crate::storage::atomic::write_atomic;
+use crate::storage::json_store::read_tasks_file;
+use crate::storage::lock::FileLock;
+use crate::storage::migrations::TaskDocument;
+use crate::storage::{DataPaths, TaskStore};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Cold storage for finished tasks moved out of a task store by `twig archive`.
+///
+/// Each task goes in a file for the month it was finished in the configured
+/// timezone, e.g. `archive/2024-03.json`. Since nothing happens to a task after
+/// it's finished, only the files from a range's first month onwards can hold
+/// activity in it.
+pub struct Archive {
+    dir: PathBuf,
+    timezone: Timezone,
+}
+
+impl Archive {
+    /// The archive for `owner` ("me" or a reportee name).
+    pub fn for_owner(paths: &DataPaths, config: &Config, owner: &str) -> Self {
+        let dir = if owner == "me" {
+            paths.archive_dir()
+        } else {
+            paths.reportee_archive_dir(owner)
+        };
+        Self {
+            dir,
+            timezone: config.timezone,
+        }
+    }
+
+    /// Add `tasks` to the archive, replacing any earlier copies.
+    pub fn add(&self, tasks: Vec<Task>) -> Result<()> {
+        let mut by_month: BTreeMap<(i32, u32), Vec<Task>> = BTreeMap::new();
+        for task in tasks {
+            let month = month_of(&task, &self.timezone);
+            by_month.entry(month).or_default().push(task);
+        }
+
+        fs::create_dir_all(&self.dir).context("Failed to create archive directory")?;
+        for ((year, month), tasks) in by_month {
+            let path = self.dir.join(format!("{:04}-{:02}.json", year, month));
+            let mut lock = FileLock::exclusive(&path)?;
+
+            let ids: HashSet<Uuid> = tasks.iter().map(|t| t.id).collect();
+            let mut archived = if path.exists() {
+                read_tasks_file(&path, &mut lock)?
+            } else {
+                Vec::new()
+            };
+            archived.retain(|t| !ids.contains(&t.id));
+            archived.extend(tasks);
+
+            let json = serde_json::to_string_pretty(&TaskDocument::current(&archived))
+                .context("Failed to serialize archive")?;
+            write_atomic(&path, json.as_bytes())
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Archived tasks that may have activity on or after `since`, or every
+    /// archived task if `since` is `None`.
+    pub fn load_since(&self, since: Option<DateTime<Utc>>) -> Result<Vec<Task>> {
+        // Tasks were filed in the timezone configured back then, which can be
+        // up to 12 hours behind UTC: start from the month that covers any of them
+        let first_month = since.map(|since| {
+            let earliest = (since - Duration::hours(12)).naive_utc();
+            (earliest.year(), earliest.month())
+        });
+
+        let mut tasks = Vec::new();
+        for (month, path) in self.month_files()? {
+            if first_month.is_none_or(|first| month >= first) {
+                let mut lock = FileLock::shared(&path)?;
+                tasks.extend(read_tasks_file(&path, &mut lock)?);
+            }
+        }
+        Ok(tasks)
+    }
+
+    fn month_files(&self) -> Result<Vec<((i32, u32), PathBuf)>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir).context("Failed to read archive directory")? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let Some(stem) = name.strip_suffix(".json") else {
+                continue;
+            };
+            if let Some((year, month)) = stem.split_once('-') {
+                if let (Ok(year), Ok(month)) = (year.parse(), month.parse()) {
+                    files.push(((year, month), path));
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// When a task was finished, falling back to its creation for inconsistent data.
+fn finished_at(task: &Task) -> DateTime<Utc> {
+    task.completed_at
+        .or(task.cancelled_at)
+        .unwrap_or(task.created_at)
+}
+
+fn month_of(task: &Task, timezone: &Timezone) -> (i32, u32) {
+    let local = finished_at(task).with_timezone(timezone);
+    (local.year(), local.month())
+}
+
+fn is_finished(task: &Task) -> bool {
+    matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled)
+}
+
+/// Move every top-level task picked by `include` whose whole subtree was
+/// finished before `before` from `storage` into `archive`, as one (undoable)
+/// change. Tasks in the trash travel with their subtree. Returns the number of
+/// tasks archived.
+pub fn archive_finished(
+    storage: &mut dyn TaskStore,
+    archive: &Archive,
+    before: DateTime<Utc>,
+    include: impl Fn(&Task) -> bool,
+) -> Result<usize> {
+    let all = storage.get_all_tasks();
+    let mut archived_ids = HashSet::new();
+    for root in all
+        .iter()
+        .filter(|t| t.parent_id.is_none() && !t.is_deleted() && include(t))
+    {
+        let ids = storage.get_subtree(root.id);
+        let ready = all
+            .iter()
+            .filter(|t| ids.contains(&t.id))
+            .all(|t| t.is_deleted() || (is_finished(t) && finished_at(t) < before));
+        if ready {
+            archived_ids.extend(ids);
+        }
+    }
+
+    if archived_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let tasks: Vec<Task> = all
+        .iter()
+        .filter(|t| archived_ids.contains(&t.id))
+        .cloned()
+        .collect();
+    // Write the archive first: a crash in between leaves tasks in both places,
+    // which readers tolerate, rather than in neither
+    archive.add(tasks)?;
+    storage
+        .tasks_mut()
+        .retain(|t| !archived_ids.contains(&t.id));
+    storage.save()?;
+    Ok(archived_ids.len())
+}
+
+/// Live archived tasks for `owner` with possible activity since `since`, minus any
+/// that are (back) in `storage`, e.g. after undoing an archive.
+pub fn load_archived(
+    paths: &DataPaths,
+    config: &Config,
+    owner: &str,
+    storage: &dyn TaskStore,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<Task>> {
+    let mut tasks = Archive::for_owner(paths, config, owner).load_since(since)?;
+    tasks.retain(|t| !t.is_deleted() && storage.get_task(t.id).is_none());
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::JsonStore;
+
+    #[test]
+    fn test_archives_finished_subtrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonStore::new(dir.path().join("tasks.json").to_string_lossy().to_string());
+        store.load().unwrap();
+
+        let mut done = Task::new("Done".to_string());
+        done.complete();
+        let mut done_child = Task::new("Done child".to_string());
+        done_child.parent_id = Some(done.id);
+        done_child.cancel();
+        let mut open = Task::new("Open".to_string());
+        open.complete();
+        let mut open_child = Task::new("Open child".to_string());
+        open_child.parent_id = Some(open.id);
+        let done_id = done.id;
+        store
+            .tasks_mut()
+            .extend([done, done_child, open, open_child]);
+        store.save().unwrap();
+
+        let archive = Archive {
+            dir: dir.path().join("archive"),
+            timezone: Timezone::Local,
+        };
+        let before = Utc::now() + chrono::Duration::days(1);
+        assert_eq!(
+            archive_finished(&mut store, &archive, before, |_| true).unwrap(),
+            2
+        );
+        assert_eq!(store.get_all_tasks().len(), 2);
+
+        let archived = archive.load_since(Some(Utc::now())).unwrap();
+        assert_eq!(archived.len(), 2);
+        assert!(archived.iter().any(|t| t.id == done_id));
+        let future = Utc::now() + chrono::Duration::days(62);
+        assert!(archive.load_since(Some(future)).unwrap().is_empty());
+    }
+}
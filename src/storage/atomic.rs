@@ -0,0 +1,155 @@
+use crate::storage::lock::FileLock;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Path of the previous version kept alongside `path` (e.g. `tasks.json.bak`).
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, "bak")
+}
+
+/// Sibling of `path` with `.<suffix>` appended to the file name.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace the contents of `path` without ever leaving a partially written file behind.
+///
+/// The data is written to a temporary file in the same directory and fsynced, the
+/// current file is preserved as `<path>.bak`, and the temporary file is then renamed
+/// over the original.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp_path = with_suffix(path, "tmp");
+
+    {
+        let mut file = File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {}", tmp_path.display()))?;
+        file.write_all(contents)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        file.sync_all()
+            .with_context(|| format!("Failed to sync {}", tmp_path.display()))?;
+    }
+
+    if path.exists() {
+        let bak_path = backup_path(path);
+        let _ = fs::remove_file(&bak_path);
+        // A hard link keeps the old version without copying it; fall back to a copy
+        // on filesystems that don't support links.
+        if fs::hard_link(path, &bak_path).is_err() {
+            fs::copy(path, &bak_path)
+                .with_context(|| format!("Failed to back up {}", path.display()))?;
+        }
+    }
+
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+
+    // Make the rename itself durable
+    if let Some(dir) = path.parent() {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the JSON document at `path`, falling back to `<path>.bak` if the primary
+/// file is corrupt. On recovery the corrupt file is kept as `<path>.corrupt` and the
+/// backup is restored in its place.
+///
+/// `lock` must be held on `path`. A shared lock is upgraded to an exclusive one
+/// before recovering, and the file read again in case another process recovered
+/// it meanwhile, so that only one process restores it and none reads it half-way.
+///
+/// Empty files parse as `T::default()`.
+pub fn read_json_with_recovery<T>(path: &Path, what: &str, lock: &mut FileLock) -> Result<T>
+where
+    T: DeserializeOwned + Default,
+{
+    let read = || fs::read_to_string(path).with_context(|| format!("Failed to read {} file", what));
+    if let Ok(value) = parse_json(&read()?) {
+        return Ok(value);
+    }
+
+    lock.upgrade(path)?;
+    let primary_err = match parse_json(&read()?) {
+        Ok(value) => return Ok(value),
+        Err(err) => err,
+    };
+
+    let bak_path = backup_path(path);
+    let recovered = fs::read_to_string(&bak_path)
+        .ok()
+        .and_then(|backup| parse_json::<T>(&backup).ok().map(|value| (value, backup)));
+
+    match recovered {
+        Some((value, backup)) => {
+            let corrupt_path = with_suffix(path, "corrupt");
+            fs::rename(path, &corrupt_path)
+                .with_context(|| format!("Failed to move aside corrupt {} file", what))?;
+            write_atomic(path, backup.as_bytes())?;
+            eprintln!(
+                "Warning: {} was corrupt ({}); recovered from {}. The damaged file was kept as {}.",
+                path.display(),
+                primary_err,
+                bak_path.display(),
+                corrupt_path.display()
+            );
+            Ok(value)
+        }
+        None => Err(primary_err).with_context(|| format!("Failed to parse {} JSON", what)),
+    }
+}
+
+fn parse_json<T: DeserializeOwned + Default>(content: &str) -> serde_json::Result<T> {
+    if content.trim().is_empty() {
+        Ok(T::default())
+    } else {
+        serde_json::from_str(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovers_from_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json");
+
+        write_atomic(&path, b"[1, 2]").unwrap();
+        write_atomic(&path, b"[1, 2, 3]").unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), "[1, 2]");
+
+        // Simulate a truncated write
+        fs::write(&path, "[1, 2,").unwrap();
+        let mut lock = FileLock::shared(&path).unwrap();
+        let values: Vec<i32> = read_json_with_recovery(&path, "test", &mut lock).unwrap();
+        assert_eq!(values, vec![1, 2]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1, 2]");
+        assert!(with_suffix(&path, "corrupt").exists());
+        drop(lock);
+
+        // Recovery waits for other readers to let go of the file
+        fs::write(&path, "[1, 2,").unwrap();
+        let reader = FileLock::shared(&path).unwrap();
+        let recovering = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut lock = FileLock::shared(&path).unwrap();
+                read_json_with_recovery::<Vec<i32>>(&path, "test", &mut lock).unwrap()
+            }
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1, 2,");
+        drop(reader);
+        assert_eq!(recovering.join().unwrap(), vec![1, 2]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "[1, 2]");
+    }
+}
@@ -0,0 +1,223 @@
+use crate::models::{Config, Delegation, Task};
+use crate::storage::task_store::link_next_saves;
+use crate::storage::{new_task_store, DataPaths, TaskStore};
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Move task `id` and its whole subtree (trashed subtasks included) from `from`,
+/// the store of `from_owner`, into `to`, the store of `to_owner`, keeping IDs,
+/// time entries, notes and tags. Running timers are stopped, since they were
+/// someone else's time. Both stores journal the move as one change.
+///
+/// With `keep_stub`, a stub linked to the moved task takes its place in `from`.
+/// Moving a task back to a store with stubs for it replaces them. Returns the
+/// number of tasks moved.
+pub fn move_task_tree(
+    from: &mut dyn TaskStore,
+    from_owner: &str,
+    to: &mut dyn TaskStore,
+    to_owner: &str,
+    id: Uuid,
+    keep_stub: bool,
+) -> Result<usize> {
+    let Some(root) = from.get_tasks().into_iter().find(|t| t.id == id).cloned() else {
+        bail!("Task not found");
+    };
+    if let Some(link) = &root.delegated_to {
+        bail!(
+            "'{}' is a stub for a task delegated to {}; move that task instead",
+            root.title,
+            link.owner
+        );
+    }
+
+    let ids: HashSet<Uuid> = from.get_subtree(id).into_iter().collect();
+    let mut moved: Vec<Task> = from
+        .get_all_tasks()
+        .iter()
+        .filter(|t| ids.contains(&t.id))
+        .cloned()
+        .collect();
+    for task in &mut moved {
+        task.pause();
+        // Take the place of a stub left when the task was delegated from `to`
+        if task.id == id {
+            task.parent_id = to
+                .get_tasks()
+                .into_iter()
+                .find(|t| is_stub_for(t, id))
+                .and_then(|stub| stub.parent_id)
+                .filter(|parent_id| !ids.contains(parent_id));
+        }
+    }
+    let count = moved.len();
+    let summary = if to_owner == "me" {
+        format!("Take back '{}'", root.title)
+    } else {
+        format!("Delegate '{}' to {}", root.title, to_owner)
+    };
+    link_next_saves(
+        &mut [(from_owner, &mut *from), (to_owner, &mut *to)],
+        &summary,
+    );
+
+    // Add to the destination first: a failure in between leaves a copy in both
+    // stores rather than in neither
+    let tasks = to.tasks_mut();
+    tasks.retain(|t| !ids.contains(&t.id) && !is_stub_for(t, id));
+    tasks.extend(moved);
+    to.save()?;
+
+    from.tasks_mut().retain(|t| !ids.contains(&t.id));
+    if keep_stub {
+        let mut stub = Task::new(root.title.clone());
+        stub.description = root.description.clone();
+        stub.tags = root.tags.clone();
+        stub.parent_id = root.parent_id;
+        stub.eta = root.eta;
+        stub.delegated_to = Some(Delegation {
+            owner: to_owner.to_string(),
+            task_id: id,
+        });
+        copy_status(&mut stub, &root);
+        from.tasks_mut().push(stub);
+    }
+    from.save()?;
+
+    Ok(count)
+}
+
+fn is_stub_for(task: &Task, id: Uuid) -> bool {
+    task.delegated_to.as_ref().is_some_and(|d| d.task_id == id)
+}
+
+fn copy_status(stub: &mut Task, task: &Task) {
+    stub.status = task.status.clone();
+    stub.started_at = task.started_at;
+    stub.completed_at = task.completed_at;
+    stub.cancelled_at = task.cancelled_at;
+}
+
+/// Bring the status of every stub in `storage` in line with the task it links
+/// to, as found by `find(owner, id)`. Only changes the tasks in memory, and
+/// returns whether any stub changed.
+pub fn sync_stubs(storage: &mut dyn TaskStore, find: impl Fn(&str, Uuid) -> Option<Task>) -> bool {
+    let mut changed = false;
+    for stub in storage.tasks_mut() {
+        let Some(link) = &stub.delegated_to else {
+            continue;
+        };
+        if let Some(task) = find(&link.owner, link.task_id) {
+            let before = status_of(stub);
+            copy_status(stub, &task);
+            changed |= status_of(stub) != before;
+        }
+    }
+    changed
+}
+
+fn status_of(task: &Task) -> impl PartialEq {
+    (
+        task.status.clone(),
+        task.started_at,
+        task.completed_at,
+        task.cancelled_at,
+    )
+}
+
+/// `sync_stubs` for a store loaded on its own, reading the stores its stubs
+/// link to from disk.
+pub fn sync_stubs_from_disk(
+    paths: &DataPaths,
+    config: &Config,
+    storage: &mut dyn TaskStore,
+) -> Result<()> {
+    let owners: HashSet<String> = storage
+        .get_tasks()
+        .into_iter()
+        .filter_map(|t| t.delegated_to.as_ref().map(|d| d.owner.clone()))
+        .filter(|owner| owner == "me" || config.reportees.contains(owner))
+        .collect();
+
+    let mut linked: HashMap<(String, Uuid), Task> = HashMap::new();
+    for owner in owners {
+        let mut other = new_task_store(paths, config, &owner);
+        other.load()?;
+        for task in other.get_tasks() {
+            linked.insert((owner.clone(), task.id), task.clone());
+        }
+    }
+
+    sync_stubs(storage, |owner, id| {
+        linked.get(&(owner.to_string(), id)).cloned()
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskStatus;
+    use crate::storage::task_store::{redo_linked, undo_linked};
+    use crate::storage::JsonStore;
+
+    #[test]
+    fn test_delegate_and_take_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mine = JsonStore::new(dir.path().join("tasks.json").to_string_lossy().to_string());
+        let mut theirs =
+            JsonStore::new(dir.path().join("alice.json").to_string_lossy().to_string());
+        mine.load().unwrap();
+        theirs.load().unwrap();
+
+        let project = Task::new("Project".to_string());
+        let mut task = Task::new("Task".to_string());
+        task.parent_id = Some(project.id);
+        task.start();
+        task.notes = "Context".to_string();
+        let mut subtask = Task::new("Subtask".to_string());
+        subtask.parent_id = Some(task.id);
+        let (project_id, task_id) = (project.id, task.id);
+        mine.tasks_mut().extend([project, task, subtask]);
+        mine.save().unwrap();
+
+        assert_eq!(
+            move_task_tree(&mut mine, "me", &mut theirs, "alice", task_id, true).unwrap(),
+            2
+        );
+        let moved = theirs.get_task(task_id).unwrap();
+        assert_eq!(moved.parent_id, None);
+        assert_eq!(moved.notes, "Context");
+        assert_eq!(moved.time_entries.len(), 1);
+        assert!(!moved.has_active_time_entry());
+        assert_eq!(theirs.get_children(task_id).len(), 1);
+
+        let stub = mine.get_children(project_id)[0].clone();
+        assert!(stub.is_delegated());
+        assert_eq!(stub.status, TaskStatus::InProgress);
+
+        // Undoing the delegation from either side puts both stores back
+        let undone = undo_linked(vec![("alice", &mut theirs), ("me", &mut mine)]).unwrap();
+        assert_eq!(undone.as_deref(), Some("Delegate 'Task' to alice"));
+        assert!(theirs.get_all_tasks().is_empty());
+        assert_eq!(mine.get_children(project_id)[0].id, task_id);
+        redo_linked(vec![("me", &mut mine), ("alice", &mut theirs)]).unwrap();
+        assert_eq!(theirs.get_children(task_id).len(), 1);
+        assert_eq!(mine.get_children(project_id)[0].id, stub.id);
+
+        theirs.get_task_mut(task_id).unwrap().complete();
+        assert!(sync_stubs(&mut mine, |_, id| theirs.get_task(id).cloned()));
+        assert!(!sync_stubs(&mut mine, |_, id| theirs.get_task(id).cloned()));
+        assert_eq!(
+            mine.get_task(stub.id).unwrap().status,
+            TaskStatus::Completed
+        );
+
+        // Taking it back replaces the stub
+        move_task_tree(&mut theirs, "alice", &mut mine, "me", task_id, false).unwrap();
+        assert!(theirs.get_all_tasks().is_empty());
+        assert!(mine.get_task(stub.id).is_none());
+        assert_eq!(mine.get_task(task_id).unwrap().parent_id, Some(project_id));
+    }
+}
@@ -0,0 +1,477 @@
+use crate::models::{Task, TaskStatus};
+use crate::storage::atomic::{with_suffix, write_atomic};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// The state of one task before and after a mutation. `None` means the task
+/// didn't exist (added) or no longer exists (deleted).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskChange {
+    pub task_id: Uuid,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mutation {
+    pub id: Uuid,
+    pub at: DateTime<Utc>,
+    pub summary: String,
+    pub changes: Vec<TaskChange>,
+    /// Mutations of other stores made in the same change, undone and redone
+    /// together with this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked: Vec<LinkedMutation>,
+}
+
+/// A mutation of another owner's store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkedMutation {
+    pub owner: String,
+    pub id: Uuid,
+}
+
+/// A mutation paired with when it was last applied or undone.
+pub type TimedMutation = (Mutation, DateTime<Utc>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Mutation(Mutation),
+    Undo { target: Uuid, at: DateTime<Utc> },
+    Redo { target: Uuid, at: DateTime<Utc> },
+}
+
+/// How many changes to each task store are kept undoable (or redoable) when
+/// the journal is compacted.
+const MAX_UNDO: usize = 500;
+
+/// Journal size past which older changes are dropped, leaving at most half of it.
+const MAX_JOURNAL_BYTES: u64 = 8 * 1024 * 1024;
+
+/// Append-only log of every change made to a task store, used for undo/redo.
+///
+/// Stored next to the task file it belongs to: `tasks.journal.jsonl` for
+/// `tasks.json` and `tasks.db.journal.jsonl` for `tasks.db`.
+pub struct Journal {
+    path: PathBuf,
+    // Entry to write on the next save instead of the computed mutation
+    pending: Option<JournalEntry>,
+    skip_next: bool,
+    // ID, summary and linked mutations for the next mutation (see `link_next`)
+    next_link: Option<(Uuid, String, Vec<LinkedMutation>)>,
+    // Size past which `append` compacts the journal
+    max_bytes: u64,
+}
+
+impl Journal {
+    pub fn for_store(store_path: &Path) -> Self {
+        Self {
+            path: if store_path.extension().is_some_and(|ext| ext == "json") {
+                store_path.with_extension("journal.jsonl")
+            } else {
+                with_suffix(store_path, "journal.jsonl")
+            },
+            pending: None,
+            skip_next: false,
+            next_link: None,
+            max_bytes: MAX_JOURNAL_BYTES,
+        }
+    }
+
+    /// Don't journal the next save (used for bulk imports that shouldn't be undoable).
+    pub fn skip_next(&mut self) {
+        self.skip_next = true;
+    }
+
+    /// Record the next save as mutation `id`, described by `summary`, made
+    /// together with the `linked` mutations of other stores.
+    pub fn link_next(&mut self, id: Uuid, summary: String, linked: Vec<LinkedMutation>) {
+        self.next_link = Some((id, summary, linked));
+    }
+
+    /// Record the next save as an undo/redo of an earlier mutation rather than as a
+    /// new mutation.
+    pub fn set_pending(&mut self, entry: JournalEntry) {
+        self.pending = Some(entry);
+    }
+
+    /// Work out the entry describing a save, given the serialized tasks as they were
+    /// on disk (`base`) and the tasks about to be written.
+    pub fn prepare(
+        &mut self,
+        base: &HashMap<Uuid, String>,
+        tasks: &[Task],
+    ) -> Result<Option<JournalEntry>> {
+        let pending = self.pending.take();
+        let next_link = self.next_link.take();
+        if std::mem::take(&mut self.skip_next) {
+            return Ok(None);
+        }
+        if pending.is_some() {
+            return Ok(pending);
+        }
+
+        let mut changes = Vec::new();
+        let mut seen = HashSet::new();
+        for task in tasks {
+            seen.insert(task.id);
+            let current = serde_json::to_string(task).context("Failed to serialize task")?;
+            match base.get(&task.id) {
+                Some(previous) if *previous == current => {}
+                previous => changes.push(TaskChange {
+                    task_id: task.id,
+                    before: previous.map(|p| serde_json::from_str(p)).transpose()?,
+                    after: Some(task.clone()),
+                }),
+            }
+        }
+        for (id, previous) in base {
+            if !seen.contains(id) {
+                changes.push(TaskChange {
+                    task_id: *id,
+                    before: Some(serde_json::from_str(previous)?),
+                    after: None,
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            return Ok(None);
+        }
+
+        let (id, summary, linked) =
+            next_link.unwrap_or_else(|| (Uuid::new_v4(), summarize(&changes), Vec::new()));
+        Ok(Some(JournalEntry::Mutation(Mutation {
+            id,
+            at: Utc::now(),
+            summary,
+            changes,
+            linked,
+        })))
+    }
+
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry).context("Failed to serialize journal entry")?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open journal")?;
+        file.write_all(line.as_bytes())
+            .context("Failed to write journal")?;
+
+        let size = file.metadata().context("Failed to read journal")?.len();
+        if size > self.max_bytes {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the journal with only the most recent changes that can be undone
+    /// or redone, preferring the redoable ones: at most `MAX_UNDO` of them, in
+    /// half of `max_bytes` (but always the latest one).
+    fn compact(&self) -> Result<()> {
+        let (mut done, mut undone) = self.stacks()?;
+        let mut budget = self.max_bytes / 2;
+        let mut kept = 0;
+        let mut keep_top = |stack: &[TimedMutation]| -> Result<usize> {
+            let mut count = 0;
+            for (mutation, _) in stack.iter().rev() {
+                let size = serde_json::to_vec(mutation)
+                    .context("Failed to serialize journal entry")?
+                    .len() as u64;
+                if kept == MAX_UNDO || (kept > 0 && size > budget) {
+                    break;
+                }
+                budget = budget.saturating_sub(size);
+                kept += 1;
+                count += 1;
+            }
+            Ok(count)
+        };
+        let undone_kept = keep_top(&undone)?;
+        let done_kept = keep_top(&done)?;
+        undone.drain(..undone.len() - undone_kept);
+        done.drain(..done.len() - done_kept);
+
+        // Redoable changes are written as applied, then undone most recent first
+        let mut entries: Vec<JournalEntry> = done
+            .into_iter()
+            .chain(undone.iter().rev().cloned())
+            .map(|(mutation, _)| JournalEntry::Mutation(mutation))
+            .collect();
+        entries.extend(undone.into_iter().map(|(mutation, at)| JournalEntry::Undo {
+            target: mutation.id,
+            at,
+        }));
+
+        let mut content = String::new();
+        for entry in &entries {
+            content.push_str(
+                &serde_json::to_string(entry).context("Failed to serialize journal entry")?,
+            );
+            content.push('\n');
+        }
+        write_atomic(&self.path, content.as_bytes())
+    }
+
+    pub fn read(&self) -> Result<Vec<JournalEntry>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path).context("Failed to read journal")?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("Failed to parse journal entry"))
+            .collect()
+    }
+
+    /// Replay the journal into the stack of applied mutations and the stack of
+    /// undone mutations (most recent last), each with the time it was last applied
+    /// or undone.
+    fn stacks(&self) -> Result<(Vec<TimedMutation>, Vec<TimedMutation>)> {
+        let mut done: Vec<TimedMutation> = Vec::new();
+        let mut undone: Vec<TimedMutation> = Vec::new();
+
+        for entry in self.read()? {
+            match entry {
+                JournalEntry::Mutation(mutation) => {
+                    let at = mutation.at;
+                    done.push((mutation, at));
+                    undone.clear();
+                }
+                JournalEntry::Undo { target, at } => {
+                    if done.last().is_some_and(|(m, _)| m.id == target) {
+                        let (mutation, _) = done.pop().unwrap();
+                        undone.push((mutation, at));
+                    }
+                }
+                JournalEntry::Redo { target, at } => {
+                    if undone.last().is_some_and(|(m, _)| m.id == target) {
+                        let (mutation, _) = undone.pop().unwrap();
+                        done.push((mutation, at));
+                    }
+                }
+            }
+        }
+
+        Ok((done, undone))
+    }
+
+    /// The mutation `undo` would revert, and when it was applied.
+    pub fn undo_target(&self) -> Result<Option<TimedMutation>> {
+        Ok(self.stacks()?.0.pop())
+    }
+
+    /// The mutation `redo` would re-apply, and when it was undone.
+    pub fn redo_target(&self) -> Result<Option<TimedMutation>> {
+        Ok(self.stacks()?.1.pop())
+    }
+
+    /// Whether mutation `id` is applied (or with `undone`, undone), so that
+    /// repeated undos (redos) would reach it.
+    pub fn reaches(&self, id: Uuid, undone: bool) -> Result<bool> {
+        let (done, undone_stack) = self.stacks()?;
+        let stack = if undone { undone_stack } else { done };
+        Ok(stack.iter().any(|(mutation, _)| mutation.id == id))
+    }
+}
+
+/// Put each task in `changes` back to its `before` (undo) or `after` (redo) state.
+pub fn apply_changes(tasks: &mut Vec<Task>, changes: &[TaskChange], undo: bool) {
+    let ordered: Vec<&TaskChange> = if undo {
+        changes.iter().rev().collect()
+    } else {
+        changes.iter().collect()
+    };
+
+    for change in ordered {
+        let state = if undo { &change.before } else { &change.after };
+        let pos = tasks.iter().position(|t| t.id == change.task_id);
+        match (state, pos) {
+            (Some(task), Some(pos)) => tasks[pos] = task.clone(),
+            (Some(task), None) => tasks.push(task.clone()),
+            (None, Some(pos)) => {
+                tasks.remove(pos);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn summarize(changes: &[TaskChange]) -> String {
+    // Describe a deleted subtree by its root rather than whichever task came first
+    let deleted: HashSet<Uuid> = changes
+        .iter()
+        .filter(|c| is_deletion(c))
+        .map(|c| c.task_id)
+        .collect();
+    let first = changes
+        .iter()
+        .find(|c| {
+            is_deletion(c)
+                && c.before
+                    .as_ref()
+                    .is_some_and(|t| t.parent_id.is_none_or(|p| !deleted.contains(&p)))
+        })
+        .unwrap_or(&changes[0]);
+
+    let summary = match (&first.before, &first.after) {
+        (None, Some(after)) => format!("Add '{}'", after.title),
+        (Some(before), None) if before.is_deleted() => {
+            format!("Permanently delete '{}'", before.title)
+        }
+        (Some(before), None) => format!("Delete '{}'", before.title),
+        (Some(before), Some(after)) => {
+            let verb = if !before.is_deleted() && after.is_deleted() {
+                "Delete"
+            } else if before.is_deleted() && !after.is_deleted() {
+                "Restore"
+            } else if before.status != after.status {
+                match after.status {
+                    TaskStatus::NotStarted => "Reset",
+                    TaskStatus::InProgress => "Start",
+                    TaskStatus::Completed => "Complete",
+                    TaskStatus::Cancelled => "Cancel",
+                }
+            } else if before.has_active_time_entry() && !after.has_active_time_entry() {
+                "Pause"
+            } else if !before.has_active_time_entry() && after.has_active_time_entry() {
+                "Start"
+            } else {
+                "Edit"
+            };
+            format!("{} '{}'", verb, after.title)
+        }
+        (None, None) => "Change".to_string(),
+    };
+
+    if changes.len() > 1 {
+        format!("{} (+{} more)", summary, changes.len() - 1)
+    } else {
+        summary
+    }
+}
+
+/// Whether a change moved a task to the trash or removed it outright.
+fn is_deletion(change: &TaskChange) -> bool {
+    match (&change.before, &change.after) {
+        (Some(before), Some(after)) => !before.is_deleted() && after.is_deleted(),
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{DeleteMode, JsonStore, TaskStore};
+
+    #[test]
+    fn test_undo_redo_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.json").to_string_lossy().to_string();
+
+        let mut store = JsonStore::new(path.clone());
+        store.load().unwrap();
+        let task = Task::new("Keep me".to_string());
+        let id = task.id;
+        store.add_task(task).unwrap();
+        store.delete_task(id, DeleteMode::Abort).unwrap();
+        assert!(store.get_task(id).unwrap().is_deleted());
+
+        assert_eq!(store.undo().unwrap().as_deref(), Some("Delete 'Keep me'"));
+        let mut reloaded = JsonStore::new(path.clone());
+        reloaded.load().unwrap();
+        assert!(!reloaded.get_task(id).unwrap().is_deleted());
+
+        assert_eq!(
+            reloaded.redo().unwrap().as_deref(),
+            Some("Delete 'Keep me'")
+        );
+        assert!(reloaded.get_task(id).unwrap().is_deleted());
+
+        // Undo twice goes back past the add as well
+        reloaded.undo().unwrap();
+        reloaded.undo().unwrap();
+        assert!(reloaded.get_all_tasks().is_empty());
+        assert!(reloaded.undo().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_each_backend_has_its_own_journal() {
+        let dir = Path::new("/data");
+        assert_eq!(
+            Journal::for_store(&dir.join("tasks.json")).path,
+            dir.join("tasks.journal.jsonl")
+        );
+        assert_eq!(
+            Journal::for_store(&dir.join("tasks.db")).path,
+            dir.join("tasks.db.journal.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_journal_keeps_the_last_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut journal = Journal::for_store(&dir.path().join("tasks.json"));
+        journal.max_bytes = 40_000;
+
+        let ids: Vec<Uuid> = (0..2 * MAX_UNDO).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            journal
+                .append(&JournalEntry::Mutation(Mutation {
+                    id: *id,
+                    at: Utc::now(),
+                    summary: format!("Change {}", i),
+                    changes: Vec::new(),
+                    linked: Vec::new(),
+                }))
+                .unwrap();
+        }
+        for id in ids.iter().rev().take(2) {
+            journal
+                .append(&JournalEntry::Undo {
+                    target: *id,
+                    at: Utc::now(),
+                })
+                .unwrap();
+        }
+
+        let size = fs::metadata(&journal.path).unwrap().len();
+        assert!(size <= journal.max_bytes, "{}", size);
+        let (done, undone) = journal.stacks().unwrap();
+        assert!(done.len() + undone.len() < MAX_UNDO);
+        assert_eq!(undone.len(), 2);
+        assert_eq!(done.last().unwrap().0.id, ids[ids.len() - 3]);
+        assert_eq!(
+            journal.redo_target().unwrap().unwrap().0.id,
+            ids[ids.len() - 2]
+        );
+
+        // Without a size limit, compacting keeps `MAX_UNDO` changes
+        journal.max_bytes = u64::MAX;
+        for i in 0..MAX_UNDO + 100 {
+            journal
+                .append(&JournalEntry::Mutation(Mutation {
+                    id: Uuid::new_v4(),
+                    at: Utc::now(),
+                    summary: format!("Change {}", i),
+                    changes: Vec::new(),
+                    linked: Vec::new(),
+                }))
+                .unwrap();
+        }
+        journal.compact().unwrap();
+        assert_eq!(journal.stacks().unwrap().0.len(), MAX_UNDO);
+    }
+}
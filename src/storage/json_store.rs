@@ -1,107 +1,225 @@
 use crate::models::{Config, Task};
+use crate::storage::atomic::{read_json_with_recovery, write_atomic};
+use crate::storage::journal::Journal;
+use crate::storage::lock::FileLock;
+use crate::storage::migrations::{upgrade_tasks, TaskDocument};
+use crate::storage::TaskStore;
 use anyhow::{Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::hash::{Hash, Hasher};
 use std::path::Path;
+use std::time::SystemTime;
 use uuid::Uuid;
 
-pub struct Storage {
+pub struct JsonStore {
     tasks: Vec<Task>,
     file_path: String,
+    // Serialized form of every task as last read from or written to disk,
+    // used as the common ancestor when merging concurrent changes
+    base: HashMap<Uuid, String>,
+    disk_state: Option<DiskState>,
+    journal: Journal,
 }
 
-impl Storage {
+/// Fingerprint of the tasks file as we last saw it.
+#[derive(Debug, Clone, PartialEq)]
+struct DiskState {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl DiskState {
+    fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read(path).context("Failed to read tasks file")?;
+        Ok(Some(Self::from_written(path, &content)))
+    }
+
+    fn from_written(path: &Path, content: &[u8]) -> Self {
+        let metadata = fs::metadata(path).ok();
+        Self {
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            len: content.len() as u64,
+            hash: hash_bytes(content),
+        }
+    }
+}
+
+fn hash_bytes(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl JsonStore {
     pub fn new(file_path: String) -> Self {
         Self {
             tasks: Vec::new(),
+            journal: Journal::for_store(Path::new(&file_path)),
             file_path,
+            base: HashMap::new(),
+            disk_state: None,
         }
     }
 
-    pub fn load(&mut self) -> Result<()> {
+    fn snapshot_base(&mut self) -> Result<()> {
+        self.base = serialize_tasks(&self.tasks)?;
+        Ok(())
+    }
+}
+
+impl TaskStore for JsonStore {
+    fn load(&mut self) -> Result<()> {
         let path = Path::new(&self.file_path);
+        let mut lock = FileLock::shared(path)?;
+
         if !path.exists() {
             // Initialize with empty tasks list
             self.tasks = Vec::new();
-            return Ok(());
+        } else {
+            self.tasks = read_tasks_file(path, &mut lock)?;
         }
 
-        let content = fs::read_to_string(path).context("Failed to read tasks file")?;
+        self.disk_state = DiskState::read(path)?;
+        self.snapshot_base()
+    }
 
-        if content.trim().is_empty() {
-            self.tasks = Vec::new();
-        } else {
-            self.tasks = serde_json::from_str(&content).context("Failed to parse tasks JSON")?;
+    fn save(&mut self) -> Result<()> {
+        let path = Path::new(&self.file_path);
+        let mut lock = FileLock::exclusive(path)?;
+        let entry = self.journal.prepare(&self.base, &self.tasks)?;
+
+        // Another process saved since we loaded: fold its changes in rather than
+        // overwriting them.
+        if self.is_stale()? {
+            let theirs: Vec<Task> = if path.exists() {
+                read_tasks_file(path, &mut lock)?
+            } else {
+                Vec::new()
+            };
+            self.tasks = merge_tasks(&self.base, &self.tasks, theirs)?;
         }
 
-        Ok(())
+        let json = serde_json::to_string_pretty(&TaskDocument::current(&self.tasks))
+            .context("Failed to serialize tasks")?;
+        write_atomic(path, json.as_bytes()).context("Failed to write tasks file")?;
+
+        if let Some(entry) = entry {
+            self.journal.append(&entry)?;
+        }
+
+        self.disk_state = Some(DiskState::from_written(path, json.as_bytes()));
+        self.snapshot_base()
     }
 
-    pub fn save(&self) -> Result<()> {
-        let json =
-            serde_json::to_string_pretty(&self.tasks).context("Failed to serialize tasks")?;
-        fs::write(&self.file_path, json).context("Failed to write tasks file")?;
-        Ok(())
+    fn is_stale(&self) -> Result<bool> {
+        let path = Path::new(&self.file_path);
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(self.disk_state.is_some()),
+        };
+
+        match &self.disk_state {
+            None => Ok(true),
+            Some(state)
+                if state.modified == metadata.modified().ok() && state.len == metadata.len() =>
+            {
+                Ok(false)
+            }
+            Some(state) => {
+                let content = fs::read(path).context("Failed to read tasks file")?;
+                Ok(hash_bytes(&content) != state.hash)
+            }
+        }
     }
 
-    pub fn add_task(&mut self, task: Task) -> Result<()> {
+    fn add_task(&mut self, task: Task) -> Result<()> {
         self.tasks.push(task);
         self.save()
     }
 
-    pub fn delete_task(&mut self, id: Uuid) -> Result<()> {
-        if let Some(pos) = self.tasks.iter().position(|t| t.id == id) {
-            self.tasks.remove(pos);
-            self.save()
-        } else {
-            Err(anyhow::anyhow!("Task not found"))
-        }
-    }
-
-    pub fn get_task(&self, id: Uuid) -> Option<&Task> {
-        self.tasks.iter().find(|t| t.id == id)
+    fn get_all_tasks(&self) -> &[Task] {
+        &self.tasks
     }
 
-    pub fn get_task_mut(&mut self, id: Uuid) -> Option<&mut Task> {
+    fn get_task_mut(&mut self, id: Uuid) -> Option<&mut Task> {
         self.tasks.iter_mut().find(|t| t.id == id)
     }
 
-    pub fn get_all_tasks(&self) -> &[Task] {
-        &self.tasks
+    fn tasks_mut(&mut self) -> &mut Vec<Task> {
+        &mut self.tasks
     }
 
-    pub fn find_task_by_short_id(&self, short_id: &str) -> Option<&Task> {
-        self.tasks.iter().find(|t| t.short_id() == short_id)
+    fn journal(&self) -> &Journal {
+        &self.journal
     }
 
-    pub fn get_root_tasks(&self) -> Vec<&Task> {
-        self.tasks
-            .iter()
-            .filter(|t| t.parent_id.is_none())
-            .collect()
+    fn journal_mut(&mut self) -> &mut Journal {
+        &mut self.journal
     }
+}
 
-    pub fn get_children(&self, parent_id: Uuid) -> Vec<&Task> {
-        self.tasks
-            .iter()
-            .filter(|t| t.parent_id == Some(parent_id))
-            .collect()
-    }
+/// Read a tasks file of any supported schema version.
+/// Read the task file at `path`, on which `lock` is held (see `read_json_with_recovery`).
+pub fn read_tasks_file(path: &Path, lock: &mut FileLock) -> Result<Vec<Task>> {
+    let document: serde_json::Value = read_json_with_recovery(path, "tasks", lock)?;
+    upgrade_tasks(document, &path.display().to_string())
+}
 
-    pub fn get_task_hierarchy(&self, task: &Task) -> Vec<Uuid> {
-        let mut hierarchy = vec![task.id];
-        let mut current_id = task.parent_id;
+fn serialize_tasks(tasks: &[Task]) -> Result<HashMap<Uuid, String>> {
+    tasks
+        .iter()
+        .map(|t| {
+            Ok((
+                t.id,
+                serde_json::to_string(t).context("Failed to serialize task")?,
+            ))
+        })
+        .collect()
+}
 
-        while let Some(id) = current_id {
-            hierarchy.insert(0, id);
-            if let Some(parent) = self.get_task(id) {
-                current_id = parent.parent_id;
-            } else {
-                break;
-            }
+/// Three-way merge of our in-memory tasks with the tasks another process wrote.
+///
+/// Tasks we changed (or added) since `base` win; everything we left untouched
+/// takes the on-disk version, including tasks the other process added or deleted.
+pub(crate) fn merge_tasks(
+    base: &HashMap<Uuid, String>,
+    ours: &[Task],
+    theirs: Vec<Task>,
+) -> Result<Vec<Task>> {
+    let ours_serialized = serialize_tasks(ours)?;
+    let ours_changed = |id: &Uuid| base.get(id) != ours_serialized.get(id);
+
+    let theirs_ids: HashSet<Uuid> = theirs.iter().map(|t| t.id).collect();
+    let mut merged = Vec::with_capacity(theirs.len());
+
+    for their in theirs {
+        match ours.iter().find(|t| t.id == their.id) {
+            Some(our) if ours_changed(&our.id) => merged.push(our.clone()),
+            Some(_) => merged.push(their),
+            // Deleted on our side
+            None if base.contains_key(&their.id) => {}
+            // Added on their side
+            None => merged.push(their),
         }
+    }
 
-        hierarchy
+    for our in ours {
+        if theirs_ids.contains(&our.id) {
+            continue;
+        }
+        // Keep tasks we added, and tasks they deleted but we have since modified
+        if ours_changed(&our.id) {
+            merged.push(our.clone());
+        }
     }
+
+    Ok(merged)
 }
 
 pub fn load_config(path: &Path) -> Result<Config> {
@@ -111,13 +229,90 @@ pub fn load_config(path: &Path) -> Result<Config> {
         return Ok(config);
     }
 
-    let content = fs::read_to_string(path).context("Failed to read config file")?;
-    let config = serde_json::from_str(&content).context("Failed to parse config JSON")?;
+    let mut lock = FileLock::shared(path)?;
+    let config: Config = read_json_with_recovery(path, "config", &mut lock)?;
+    config
+        .work_calendar
+        .validate()
+        .with_context(|| format!("Invalid work_calendar in {}", path.display()))?;
     Ok(config)
 }
 
 pub fn save_config(path: &Path, config: &Config) -> Result<()> {
     let json = serde_json::to_string_pretty(config).context("Failed to serialize config")?;
-    fs::write(path, json).context("Failed to write config file")?;
+    let _lock = FileLock::exclusive(path)?;
+    write_atomic(path, json.as_bytes()).context("Failed to write config file")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_both_sides_changes() {
+        let edited = Task::new("Edited by us".to_string());
+        let untouched = Task::new("Untouched".to_string());
+        let deleted_by_us = Task::new("Deleted by us".to_string());
+        let base =
+            serialize_tasks(&[edited.clone(), untouched.clone(), deleted_by_us.clone()]).unwrap();
+
+        let mut ours = vec![edited.clone(), untouched.clone()];
+        ours[0].start();
+        let added_by_us = Task::new("Added by us".to_string());
+        ours.push(added_by_us.clone());
+
+        let mut theirs = vec![edited.clone(), untouched.clone(), deleted_by_us.clone()];
+        theirs[1].complete();
+        let added_by_them = Task::new("Added by them".to_string());
+        theirs.push(added_by_them.clone());
+
+        let merged = merge_tasks(&base, &ours, theirs).unwrap();
+        let find = |id: Uuid| merged.iter().find(|t| t.id == id);
+
+        assert!(find(edited.id).unwrap().has_active_time_entry());
+        assert!(find(untouched.id).unwrap().completed_at.is_some());
+        assert!(find(deleted_by_us.id).is_none());
+        assert!(find(added_by_us.id).is_some());
+        assert!(find(added_by_them.id).is_some());
+    }
+
+    #[test]
+    fn test_invalid_work_calendar_is_rejected() {
+        use crate::models::config::CalendarOverride;
+        use chrono::Weekday;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        let load = |edit: &dyn Fn(&mut Config)| {
+            let mut config = Config::default();
+            edit(&mut config);
+            save_config(&path, &config).unwrap();
+            load_config(&path).map_err(|e| format!("{:#}", e))
+        };
+
+        assert!(load(&|_| {}).is_ok());
+        let error = load(&|c| c.work_calendar.hours_per_day = 0.0).unwrap_err();
+        assert!(
+            error.contains("hours_per_day must be more than 0"),
+            "{}",
+            error
+        );
+        let error = load(&|c| c.work_calendar.work_days.clear()).unwrap_err();
+        assert!(error.contains("at least one day"), "{}", error);
+        let error = load(&|c| c.work_calendar.work_days.push(Weekday::Mon)).unwrap_err();
+        assert!(error.contains("Mon more than once"), "{}", error);
+
+        let error = load(&|c| {
+            c.work_calendar.reportees.insert(
+                "alice".to_string(),
+                CalendarOverride {
+                    hours_per_day: Some(-4.0),
+                    work_days: None,
+                },
+            );
+        })
+        .unwrap_err();
+        assert!(error.contains("in alice's schedule"), "{}", error);
+    }
+}
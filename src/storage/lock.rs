@@ -0,0 +1,64 @@
+use crate::storage::atomic::with_suffix;
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+
+/// Advisory lock guarding a data file against concurrent twig processes.
+///
+/// The lock is taken on a sibling `<path>.lock` file rather than the data file
+/// itself, since atomic writes replace the data file on every save. The lock is
+/// released when the guard is dropped.
+pub struct FileLock {
+    file: File,
+    exclusive: bool,
+}
+
+impl FileLock {
+    /// Take a shared lock, allowing other readers but waiting for any writer.
+    pub fn shared(path: &Path) -> Result<Self> {
+        let file = Self::open(path)?;
+        file.lock_shared()
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(Self {
+            file,
+            exclusive: false,
+        })
+    }
+
+    /// Take an exclusive lock, waiting for all other readers and writers.
+    pub fn exclusive(path: &Path) -> Result<Self> {
+        let file = Self::open(path)?;
+        file.lock()
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(Self {
+            file,
+            exclusive: true,
+        })
+    }
+
+    /// Turn a shared lock on `path` into an exclusive one. The shared lock is
+    /// released first (so two upgrading readers can't deadlock), which means the
+    /// file may have changed by the time this returns.
+    pub fn upgrade(&mut self, path: &Path) -> Result<()> {
+        if !self.exclusive {
+            self.file
+                .unlock()
+                .with_context(|| format!("Failed to unlock {}", path.display()))?;
+            self.file
+                .lock()
+                .with_context(|| format!("Failed to lock {}", path.display()))?;
+            self.exclusive = true;
+        }
+        Ok(())
+    }
+
+    fn open(path: &Path) -> Result<File> {
+        let lock_path = with_suffix(path, "lock");
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open lock file {}", lock_path.display()))
+    }
+}
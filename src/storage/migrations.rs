@@ -0,0 +1,199 @@
+use crate::models::Task;
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Version of the stored task list format written by this build of twig.
+/// Bump this and append a migration to `MIGRATIONS` whenever a change to `Task`
+/// can't be read from older files as-is.
+pub const CURRENT_VERSION: u32 = 4;
+
+/// Upgrades a task document from version `n` to `n + 1`, where `n` is the
+/// migration's index in `MIGRATIONS`.
+type Migration = fn(Value) -> Result<Value>;
+
+const MIGRATIONS: &[Migration] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+];
+
+/// The versioned envelope tasks are stored in:
+/// `{ "version": 1, "tasks": [...] }`.
+#[derive(Serialize)]
+pub struct TaskDocument<'a> {
+    pub version: u32,
+    pub tasks: &'a [Task],
+}
+
+impl<'a> TaskDocument<'a> {
+    pub fn current(tasks: &'a [Task]) -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            tasks,
+        }
+    }
+}
+
+/// Parse a stored task document of any supported version, applying migrations to
+/// bring it up to `CURRENT_VERSION`. `source` names the file in error messages.
+///
+/// Version 0 is the original format: a bare JSON array of tasks.
+pub fn upgrade_tasks(document: Value, source: &str) -> Result<Vec<Task>> {
+    let (mut version, mut document) = match document {
+        Value::Null => return Ok(Vec::new()),
+        Value::Array(tasks) => (0, envelope(0, tasks)),
+        Value::Object(ref fields) => {
+            let version = fields
+                .get("version")
+                .and_then(Value::as_u64)
+                .with_context(|| format!("{} has no valid schema version", source))?;
+            (version as u32, document)
+        }
+        _ => bail!("{} is not a task list", source),
+    };
+
+    check_version(version, source)?;
+
+    while version < CURRENT_VERSION {
+        document = MIGRATIONS[version as usize](document)
+            .with_context(|| format!("Failed to migrate {} from version {}", source, version))?;
+        version += 1;
+    }
+
+    let tasks = document
+        .get_mut("tasks")
+        .map(Value::take)
+        .unwrap_or(Value::Array(Vec::new()));
+    serde_json::from_value(tasks).with_context(|| format!("Failed to parse tasks in {}", source))
+}
+
+/// Fail with a clear message when a file was written by a newer twig.
+pub fn check_version(version: u32, source: &str) -> Result<()> {
+    if version > CURRENT_VERSION {
+        bail!(
+            "{} was written by a newer version of twig (schema version {}, this version \
+             supports up to {}). Please upgrade twig.",
+            source,
+            version,
+            CURRENT_VERSION
+        );
+    }
+    Ok(())
+}
+
+pub fn envelope(version: u32, tasks: Vec<Value>) -> Value {
+    let mut fields = Map::new();
+    fields.insert("version".to_string(), Value::from(version));
+    fields.insert("tasks".to_string(), Value::Array(tasks));
+    Value::Object(fields)
+}
+
+/// Apply `f` to every task object in a document.
+fn map_tasks(mut document: Value, f: impl Fn(&mut Map<String, Value>)) -> Result<Value> {
+    let tasks = document
+        .get_mut("tasks")
+        .and_then(Value::as_array_mut)
+        .context("Document has no task list")?;
+    for task in tasks {
+        if let Value::Object(fields) = task {
+            f(fields);
+        }
+    }
+    Ok(document)
+}
+
+/// v1 introduced the versioned envelope; tasks written before `notes` existed
+/// get an empty one.
+fn migrate_v0_to_v1(document: Value) -> Result<Value> {
+    let mut document = map_tasks(document, |task| {
+        task.entry("notes").or_insert_with(|| Value::from(""));
+    })?;
+    document["version"] = Value::from(1);
+    Ok(document)
+}
+
+/// v2 added the trash (`deleted_at`). Nothing needs converting, but the bump stops
+/// older versions of twig from showing trashed tasks as if they were live.
+fn migrate_v1_to_v2(document: Value) -> Result<Value> {
+    let mut document = map_tasks(document, |task| {
+        task.entry("deleted_at").or_insert(Value::Null);
+    })?;
+    document["version"] = Value::from(2);
+    Ok(document)
+}
+
+/// v3 added delegation stubs (`delegated_to`). The bump stops older versions of
+/// twig from treating stubs as ordinary tasks and dropping the link on save.
+fn migrate_v2_to_v3(document: Value) -> Result<Value> {
+    let mut document = map_tasks(document, |task| {
+        task.entry("delegated_to").or_insert(Value::Null);
+    })?;
+    document["version"] = Value::from(3);
+    Ok(document)
+}
+
+/// v4 added notes to time entries. The bump stops older versions of twig from
+/// dropping them on save.
+fn migrate_v3_to_v4(document: Value) -> Result<Value> {
+    let mut document = map_tasks(document, |task| {
+        if let Some(Value::Array(entries)) = task.get_mut("time_entries") {
+            for entry in entries.iter_mut().filter_map(Value::as_object_mut) {
+                entry.entry("note").or_insert_with(|| Value::from(""));
+            }
+        }
+    })?;
+    document["version"] = Value::from(4);
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrades_legacy_array() {
+        let mut task = serde_json::to_value(Task::new("Old task".to_string())).unwrap();
+        task.as_object_mut().unwrap().remove("notes");
+
+        let tasks = upgrade_tasks(Value::Array(vec![task]), "tasks.json").unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "Old task");
+        assert!(tasks[0].notes.is_empty());
+    }
+
+    #[test]
+    fn test_upgrades_v2_tasks_without_delegation() {
+        let mut task = serde_json::to_value(Task::new("Task".to_string())).unwrap();
+        task.as_object_mut().unwrap().remove("delegated_to");
+
+        let tasks = upgrade_tasks(envelope(2, vec![task]), "tasks.json").unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert!(tasks[0].delegated_to.is_none());
+    }
+
+    #[test]
+    fn test_upgrades_v3_time_entries_without_notes() {
+        let mut task = Task::new("Task".to_string());
+        task.start();
+        let mut task = serde_json::to_value(task).unwrap();
+        task["time_entries"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("note");
+
+        let document = MIGRATIONS[3](envelope(3, vec![task])).unwrap();
+        assert_eq!(document["version"], 4);
+        assert_eq!(document["tasks"][0]["time_entries"][0]["note"], "");
+        let tasks = upgrade_tasks(document, "tasks.json").unwrap();
+        assert_eq!(tasks[0].time_entries.len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let document = envelope(CURRENT_VERSION + 1, Vec::new());
+        let err = upgrade_tasks(document, "tasks.json").unwrap_err();
+        assert!(err.to_string().contains("newer version of twig"));
+    }
+}
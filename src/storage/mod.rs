@@ -1,5 +1,16 @@
+pub mod archive;
+pub mod atomic;
+pub mod delegation;
+pub mod journal;
 pub mod json_store;
+pub mod lock;
+pub mod migrations;
 pub mod paths;
+pub mod sqlite_store;
+pub mod task_store;
+pub mod timers;
 
-pub use json_store::Storage;
+pub use json_store::JsonStore;
 pub use paths::DataPaths;
+pub use sqlite_store::SqliteStore;
+pub use task_store::{new_task_store, DeleteMode, TaskStore};
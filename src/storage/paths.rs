@@ -1,29 +1,122 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory given with the global `--data-dir` flag, if any.
+static DATA_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `dir` for all data and configuration, taking precedence over `TWIG_HOME`
+/// and the XDG directories. Must be called before the first `DataPaths::new`.
+pub fn set_data_dir_override(dir: PathBuf) {
+    let dir = if dir.is_relative() {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(&dir))
+            .unwrap_or(dir)
+    } else {
+        dir
+    };
+    let _ = DATA_DIR_OVERRIDE.set(dir);
+}
 
 pub struct DataPaths {
     base_dir: PathBuf,
+    config_dir: PathBuf,
 }
 
 impl DataPaths {
+    /// Resolve the data and config directories, in order of precedence:
+    /// `--data-dir`, `$TWIG_HOME`, an existing legacy `~/.twig`, then
+    /// `$XDG_DATA_HOME/twig` and `$XDG_CONFIG_HOME/twig` (falling back to
+    /// `~/.local/share/twig` and `~/.config/twig`).
     pub fn new() -> Result<Self> {
-        let home = std::env::var("HOME").context("HOME environment variable not set")?;
-        let base_dir = Path::new(&home).join(".twig");
+        let paths = match Self::explicit_dir() {
+            Some(dir) => Self::single(dir),
+            None => match legacy_dir() {
+                Some(legacy) if legacy.is_dir() => Self::single(legacy),
+                _ => Self::xdg()?,
+            },
+        };
+
+        paths.create_dirs()?;
+        Ok(paths)
+    }
+
+    fn single(dir: PathBuf) -> Self {
+        Self {
+            config_dir: dir.clone(),
+            base_dir: dir,
+        }
+    }
+
+    fn explicit_dir() -> Option<PathBuf> {
+        if let Some(dir) = DATA_DIR_OVERRIDE.get() {
+            return Some(dir.clone());
+        }
+        non_empty_env("TWIG_HOME").map(PathBuf::from)
+    }
+
+    fn xdg() -> Result<Self> {
+        let home = non_empty_env("HOME");
+        let data_home = non_empty_env("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|h| Path::new(h).join(".local/share")));
+        let config_home = non_empty_env("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|h| Path::new(h).join(".config")));
+
+        match (data_home, config_home) {
+            (Some(data_home), Some(config_home)) => Ok(Self {
+                base_dir: data_home.join("twig"),
+                config_dir: config_home.join("twig"),
+            }),
+            _ => bail!(
+                "Cannot determine where to store data: set --data-dir, TWIG_HOME, \
+                 XDG_DATA_HOME/XDG_CONFIG_HOME or HOME"
+            ),
+        }
+    }
+
+    /// Where data would live if the legacy `~/.twig` directory didn't exist.
+    fn migration_target() -> Result<Self> {
+        match Self::explicit_dir() {
+            Some(dir) => Ok(Self::single(dir)),
+            None => Self::xdg(),
+        }
+    }
 
-        // Create directories if they don't exist
-        std::fs::create_dir_all(&base_dir).context("Failed to create .twig directory")?;
-        std::fs::create_dir_all(base_dir.join("reportees"))
+    fn create_dirs(&self) -> Result<()> {
+        fs::create_dir_all(&self.base_dir).with_context(|| {
+            format!(
+                "Failed to create data directory {}",
+                self.base_dir.display()
+            )
+        })?;
+        fs::create_dir_all(&self.config_dir).with_context(|| {
+            format!(
+                "Failed to create config directory {}",
+                self.config_dir.display()
+            )
+        })?;
+        fs::create_dir_all(self.base_dir.join("reportees"))
             .context("Failed to create reportees directory")?;
+        Ok(())
+    }
 
-        Ok(Self { base_dir })
+    pub fn data_dir(&self) -> &Path {
+        &self.base_dir
     }
 
     pub fn tasks_file(&self) -> PathBuf {
         self.base_dir.join("tasks.json")
     }
 
+    pub fn tasks_db_file(&self) -> PathBuf {
+        self.base_dir.join("tasks.db")
+    }
+
     pub fn config_file(&self) -> PathBuf {
-        self.base_dir.join("config.json")
+        self.config_dir.join("config.json")
     }
 
     pub fn reportee_tasks_file(&self, name: &str) -> PathBuf {
@@ -31,6 +124,18 @@ impl DataPaths {
             .join("reportees")
             .join(format!("{}.json", name))
     }
+
+    pub fn reportee_tasks_db_file(&self, name: &str) -> PathBuf {
+        self.base_dir.join("reportees").join(format!("{}.db", name))
+    }
+
+    pub fn archive_dir(&self) -> PathBuf {
+        self.base_dir.join("archive")
+    }
+
+    pub fn reportee_archive_dir(&self, name: &str) -> PathBuf {
+        self.base_dir.join("archive").join("reportees").join(name)
+    }
 }
 
 impl Default for DataPaths {
@@ -38,3 +143,91 @@ impl Default for DataPaths {
         Self::new().expect("Failed to initialize data paths")
     }
 }
+
+fn non_empty_env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn legacy_dir() -> Option<PathBuf> {
+    non_empty_env("HOME").map(|home| Path::new(&home).join(".twig"))
+}
+
+/// Move a legacy `~/.twig` tree to the directories twig would otherwise use
+/// (`--data-dir`/`TWIG_HOME` if set, else the XDG directories). Returns the new
+/// locations, or `None` if there was nothing to migrate.
+pub fn migrate_legacy_dir() -> Result<Option<DataPaths>> {
+    let legacy = match legacy_dir() {
+        Some(legacy) if legacy.is_dir() => legacy,
+        _ => return Ok(None),
+    };
+
+    let target = DataPaths::migration_target()?;
+    if target.base_dir == legacy {
+        return Ok(None);
+    }
+
+    for existing in [
+        target.tasks_file(),
+        target.tasks_db_file(),
+        target.config_file(),
+    ] {
+        if existing.exists() {
+            bail!(
+                "{} already exists; refusing to overwrite it with {}",
+                existing.display(),
+                legacy.display()
+            );
+        }
+    }
+    fs::create_dir_all(&target.base_dir)?;
+    fs::create_dir_all(&target.config_dir)?;
+
+    for entry in fs::read_dir(&legacy).context("Failed to read legacy data directory")? {
+        let entry = entry?;
+        let name = entry.file_name();
+        // config.json and its backup/lock files go to the config directory
+        let dest_dir = if name.to_string_lossy().starts_with("config.json") {
+            &target.config_dir
+        } else {
+            &target.base_dir
+        };
+        let dest = dest_dir.join(&name);
+        if dest.is_dir() && entry.path().is_dir() {
+            // e.g. an empty reportees/ directory created by an earlier run
+            move_dir_contents(&entry.path(), &dest)?;
+            fs::remove_dir(entry.path())?;
+        } else {
+            move_path(&entry.path(), &dest)?;
+        }
+    }
+
+    fs::remove_dir(&legacy).with_context(|| format!("Failed to remove {}", legacy.display()))?;
+    Ok(Some(target))
+}
+
+fn move_dir_contents(from: &Path, to: &Path) -> Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        move_path(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Rename `from` to `to`, copying and deleting instead when they are on
+/// different filesystems.
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        move_dir_contents(from, to)?;
+        fs::remove_dir(from)?;
+    } else {
+        fs::copy(from, to)
+            .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
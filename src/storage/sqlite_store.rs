@@ -0,0 +1,363 @@
+use crate::models::Task;
+use crate::storage::journal::Journal;
+use crate::storage::json_store::merge_tasks;
+use crate::storage::migrations::{check_version, envelope, upgrade_tasks, CURRENT_VERSION};
+use crate::storage::{JsonStore, TaskStore};
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, Transaction, TransactionBehavior};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// SQLite-backed task store.
+///
+/// Each task is stored as its own row, so saving only writes the tasks that
+/// changed since the last load/save instead of rewriting the whole list.
+pub struct SqliteStore {
+    tasks: Vec<Task>,
+    file_path: PathBuf,
+    json_import_path: Option<PathBuf>,
+    conn: Option<Connection>,
+    // Serialized form of every task as it currently exists in the database
+    persisted: HashMap<Uuid, String>,
+    // `PRAGMA data_version` as of the last load; changes when another connection commits
+    data_version: i64,
+    journal: Journal,
+}
+
+impl SqliteStore {
+    pub fn new(file_path: PathBuf) -> Self {
+        Self {
+            tasks: Vec::new(),
+            journal: Journal::for_store(&file_path),
+            file_path,
+            json_import_path: None,
+            conn: None,
+            persisted: HashMap::new(),
+            data_version: 0,
+        }
+    }
+
+    /// Import tasks from an existing JSON store the first time the database is created.
+    pub fn with_json_import(mut self, json_path: PathBuf) -> Self {
+        self.json_import_path = Some(json_path);
+        self
+    }
+
+    fn connection(&self) -> Result<&Connection> {
+        self.conn.as_ref().context("Task database is not loaded")
+    }
+
+    fn open(path: &Path) -> Result<Connection> {
+        let conn = Connection::open(path).context("Failed to open tasks database")?;
+        // Wait for other twig processes holding the database lock instead of failing
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tasks (
+                id TEXT PRIMARY KEY,
+                parent_id TEXT,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_tasks_parent_id ON tasks(parent_id);",
+        )
+        .context("Failed to initialize tasks database")?;
+        Ok(conn)
+    }
+
+    /// Build the database from the JSON store in a temporary file and move it
+    /// into place only once every task is written, so an import that fails
+    /// part-way is retried on the next load instead of leaving an empty database.
+    fn import_json(&self, json_path: PathBuf) -> Result<()> {
+        let mut json_store = JsonStore::new(json_path.to_string_lossy().to_string());
+        json_store.load()?;
+
+        let temp_path = self.file_path.with_extension("db.importing");
+        if temp_path.exists() {
+            fs::remove_file(&temp_path)
+                .context("Failed to remove unfinished tasks database import")?;
+        }
+        {
+            let conn = Self::open(&temp_path)?;
+            let tx = conn.unchecked_transaction()?;
+            for task in json_store.get_all_tasks() {
+                let data = serde_json::to_string(task).context("Failed to serialize task")?;
+                tx.execute(
+                    "INSERT INTO tasks (id, parent_id, data) VALUES (?1, ?2, ?3)",
+                    params![
+                        task.id.to_string(),
+                        task.parent_id.map(|p| p.to_string()),
+                        data
+                    ],
+                )
+                .context("Failed to write task")?;
+            }
+            tx.pragma_update(None, "user_version", CURRENT_VERSION)
+                .context("Failed to update tasks database schema version")?;
+            tx.commit().context("Failed to commit tasks database")?;
+        }
+        fs::rename(&temp_path, &self.file_path).context("Failed to move imported tasks database")
+    }
+
+    /// The serialized tasks in the database, in insertion order.
+    fn read_rows(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT data FROM tasks ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<rusqlite::Result<_>>()
+            .context("Failed to read tasks database")
+    }
+
+    fn read_data_version(conn: &Connection) -> Result<i64> {
+        conn.query_row("PRAGMA data_version", [], |row| row.get(0))
+            .context("Failed to read tasks database version")
+    }
+}
+
+impl TaskStore for SqliteStore {
+    fn load(&mut self) -> Result<()> {
+        if !self.file_path.exists() {
+            if let Some(json_path) = self.json_import_path.clone() {
+                if json_path.exists() {
+                    self.import_json(json_path)?;
+                }
+            }
+        }
+
+        let conn = Self::open(&self.file_path)?;
+
+        let schema_version: u32 = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .context("Failed to read tasks database schema version")?;
+        let source = self.file_path.display().to_string();
+        check_version(schema_version, &source)?;
+
+        let rows = Self::read_rows(&conn)?;
+
+        let values = rows
+            .iter()
+            .map(|data| serde_json::from_str(data))
+            .collect::<serde_json::Result<Vec<Value>>>()
+            .context("Failed to parse task in database")?;
+        self.tasks = upgrade_tasks(envelope(schema_version, values), &source)?;
+
+        // Rows written by an older schema are all rewritten in the current format
+        let needs_rewrite = schema_version < CURRENT_VERSION && !rows.is_empty();
+        self.persisted = if needs_rewrite {
+            HashMap::new()
+        } else {
+            self.tasks
+                .iter()
+                .zip(rows)
+                .map(|(task, data)| (task.id, data))
+                .collect()
+        };
+        self.data_version = Self::read_data_version(&conn)?;
+        self.conn = Some(conn);
+
+        if needs_rewrite {
+            self.journal.skip_next();
+            self.save()?;
+        }
+        if schema_version < CURRENT_VERSION {
+            self.connection()?
+                .pragma_update(None, "user_version", CURRENT_VERSION)
+                .context("Failed to update tasks database schema version")?;
+        }
+
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<()> {
+        let entry = self.journal.prepare(&self.persisted, &self.tasks)?;
+        let mut written = HashMap::new();
+        {
+            let conn = self.conn.as_ref().context("Task database is not loaded")?;
+            // Take the write lock up front, so that nothing commits between
+            // reading other processes' changes and writing ours
+            let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+                .context("Failed to lock tasks database")?;
+
+            // Another process saved since we loaded: fold its changes in rather
+            // than overwriting them
+            if Self::read_data_version(&tx)? != self.data_version {
+                let rows = Self::read_rows(&tx)?;
+                let theirs = rows
+                    .iter()
+                    .map(|data| serde_json::from_str(data))
+                    .collect::<serde_json::Result<Vec<Task>>>()
+                    .context("Failed to parse task in database")?;
+                let ids: Vec<Uuid> = theirs.iter().map(|t| t.id).collect();
+                self.tasks = merge_tasks(&self.persisted, &self.tasks, theirs)?;
+                self.persisted = ids.into_iter().zip(rows).collect();
+            }
+
+            for task in &self.tasks {
+                let data = serde_json::to_string(task).context("Failed to serialize task")?;
+                if self.persisted.get(&task.id) != Some(&data) {
+                    tx.execute(
+                        "INSERT INTO tasks (id, parent_id, data) VALUES (?1, ?2, ?3)
+                         ON CONFLICT(id) DO UPDATE SET parent_id = excluded.parent_id, data = excluded.data",
+                        params![
+                            task.id.to_string(),
+                            task.parent_id.map(|p| p.to_string()),
+                            data
+                        ],
+                    )
+                    .context("Failed to write task")?;
+                }
+                written.insert(task.id, data);
+            }
+
+            for id in self.persisted.keys() {
+                if !written.contains_key(id) {
+                    tx.execute("DELETE FROM tasks WHERE id = ?1", params![id.to_string()])
+                        .context("Failed to delete task")?;
+                }
+            }
+
+            tx.commit().context("Failed to commit tasks database")?;
+        }
+        self.persisted = written;
+        self.data_version = Self::read_data_version(self.connection()?)?;
+
+        if let Some(entry) = entry {
+            self.journal.append(&entry)?;
+        }
+        Ok(())
+    }
+
+    fn is_stale(&self) -> Result<bool> {
+        match &self.conn {
+            Some(conn) => Ok(Self::read_data_version(conn)? != self.data_version),
+            None => Ok(false),
+        }
+    }
+
+    fn add_task(&mut self, task: Task) -> Result<()> {
+        self.tasks.push(task);
+        self.save()
+    }
+
+    fn get_all_tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    fn get_task_mut(&mut self, id: Uuid) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    fn tasks_mut(&mut self) -> &mut Vec<Task> {
+        &mut self.tasks
+    }
+
+    fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    fn journal_mut(&mut self) -> &mut Journal {
+        &mut self.journal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::DeleteMode;
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.db");
+
+        let mut store = SqliteStore::new(path.clone());
+        store.load().unwrap();
+        let parent = Task::new("Parent".to_string());
+        let parent_id = parent.id;
+        let mut child = Task::new("Child".to_string());
+        child.parent_id = Some(parent_id);
+        let child_id = child.id;
+        store.add_task(parent).unwrap();
+        store.add_task(child).unwrap();
+        store.get_task_mut(parent_id).unwrap().start();
+        store.save().unwrap();
+
+        let mut reloaded = SqliteStore::new(path.clone());
+        reloaded.load().unwrap();
+        assert_eq!(reloaded.get_all_tasks().len(), 2);
+        assert!(reloaded
+            .get_task(parent_id)
+            .unwrap()
+            .has_active_time_entry());
+        assert_eq!(reloaded.get_children(parent_id)[0].id, child_id);
+
+        assert!(reloaded.delete_task(parent_id, DeleteMode::Abort).is_err());
+        reloaded.delete_task(child_id, DeleteMode::Abort).unwrap();
+        let mut reloaded = SqliteStore::new(path.clone());
+        reloaded.load().unwrap();
+        assert!(reloaded.get_task(child_id).unwrap().is_deleted());
+    }
+
+    #[test]
+    fn test_failed_json_import_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("tasks.json");
+        let db_path = dir.path().join("tasks.db");
+
+        fs::write(&json_path, "{ not json").unwrap();
+        let mut store = SqliteStore::new(db_path.clone()).with_json_import(json_path.clone());
+        assert!(store.load().is_err());
+        assert!(!db_path.exists());
+
+        fs::remove_file(&json_path).unwrap();
+        let mut json_store = JsonStore::new(json_path.to_string_lossy().to_string());
+        json_store.load().unwrap();
+        json_store
+            .add_task(Task::new("Imported".to_string()))
+            .unwrap();
+        let mut store = SqliteStore::new(db_path.clone()).with_json_import(json_path);
+        store.load().unwrap();
+        assert_eq!(store.get_all_tasks()[0].title, "Imported");
+
+        let mut reloaded = SqliteStore::new(db_path);
+        reloaded.load().unwrap();
+        assert_eq!(reloaded.get_all_tasks().len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_saves_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.db");
+
+        let mut setup = SqliteStore::new(path.clone());
+        setup.load().unwrap();
+        let first = Task::new("First".to_string());
+        let second = Task::new("Second".to_string());
+        let (first_id, second_id) = (first.id, second.id);
+        setup.add_task(first).unwrap();
+        setup.add_task(second).unwrap();
+
+        let mut ours = SqliteStore::new(path.clone());
+        let mut theirs = SqliteStore::new(path.clone());
+        ours.load().unwrap();
+        theirs.load().unwrap();
+
+        theirs.get_task_mut(first_id).unwrap().title = "First, edited by them".to_string();
+        theirs
+            .add_task(Task::new("Added by them".to_string()))
+            .unwrap();
+
+        assert!(ours.is_stale().unwrap());
+        ours.get_task_mut(second_id).unwrap().title = "Second, edited by us".to_string();
+        ours.save().unwrap();
+        assert!(!ours.is_stale().unwrap());
+        assert_eq!(ours.get_all_tasks().len(), 3);
+
+        let mut reloaded = SqliteStore::new(path);
+        reloaded.load().unwrap();
+        let title = |id| reloaded.get_task(id).unwrap().title.clone();
+        assert_eq!(title(first_id), "First, edited by them");
+        assert_eq!(title(second_id), "Second, edited by us");
+        assert_eq!(reloaded.get_all_tasks().len(), 3);
+    }
+}
@@ -0,0 +1,486 @@
+use crate::models::config::StorageBackend;
+use crate::models::{Config, Task};
+use crate::storage::journal::{apply_changes, Journal, JournalEntry, LinkedMutation};
+use crate::storage::{DataPaths, JsonStore, SqliteStore};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// What happens to the subtasks of a deleted task.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeleteMode {
+    /// Refuse to delete a task that has subtasks
+    Abort,
+    /// Delete the task together with all of its descendants
+    Cascade,
+    /// Move the subtasks up to the deleted task's parent
+    Promote,
+}
+
+/// A persistent collection of tasks belonging to a single owner
+/// (either "me" or one reportee).
+///
+/// Backends keep the full task list in memory so callers can borrow tasks
+/// directly; `save` is responsible for persisting whatever changed.
+///
+/// `get_all_tasks` and `get_task` include tasks in the trash; the other queries
+/// (`get_tasks`, `get_root_tasks`, `get_children`, ...) only see live tasks.
+pub trait TaskStore {
+    fn load(&mut self) -> Result<()>;
+
+    fn save(&mut self) -> Result<()>;
+
+    /// Whether another process has modified the store since it was last loaded or saved.
+    fn is_stale(&self) -> Result<bool>;
+
+    fn add_task(&mut self, task: Task) -> Result<()>;
+
+    fn get_all_tasks(&self) -> &[Task];
+
+    fn get_task_mut(&mut self, id: Uuid) -> Option<&mut Task>;
+
+    /// Direct access to the in-memory task list; changes are persisted by `save`.
+    fn tasks_mut(&mut self) -> &mut Vec<Task>;
+
+    fn journal(&self) -> &Journal;
+
+    fn journal_mut(&mut self) -> &mut Journal;
+
+    /// Move a task to the trash, handling its subtasks according to `mode`, as a
+    /// single (undoable) change. Returns the number of tasks trashed.
+    fn delete_task(&mut self, id: Uuid, mode: DeleteMode) -> Result<usize> {
+        self.delete_tasks(&[id], mode)
+    }
+
+    /// Move several tasks to the trash as a single (undoable) change, handling
+    /// subtasks that aren't among them according to `mode`. Promoted subtasks
+    /// move up to their closest ancestor that stays. Returns the number of tasks
+    /// trashed.
+    fn delete_tasks(&mut self, ids: &[Uuid], mode: DeleteMode) -> Result<usize> {
+        let mut removed: HashSet<Uuid> = ids.iter().copied().collect();
+        let mut orphans = Vec::new();
+        for &id in ids {
+            let task = self.get_task(id).context("Task not found")?;
+            let children: Vec<Uuid> = self
+                .get_children(id)
+                .iter()
+                .map(|t| t.id)
+                .filter(|child_id| !ids.contains(child_id))
+                .collect();
+            if children.is_empty() {
+                continue;
+            }
+            match mode {
+                DeleteMode::Abort => {
+                    bail!("'{}' has {} subtask(s)", task.title, children.len())
+                }
+                DeleteMode::Cascade => removed.extend(self.get_descendants(id)),
+                DeleteMode::Promote => orphans.extend(children),
+            }
+        }
+
+        for child_id in orphans {
+            if removed.contains(&child_id) {
+                continue;
+            }
+            let mut parent_id = self.get_task(child_id).and_then(|t| t.parent_id);
+            while let Some(id) = parent_id.filter(|id| removed.contains(id)) {
+                parent_id = self.get_task(id).and_then(|t| t.parent_id);
+            }
+            if let Some(child) = self.get_task_mut(child_id) {
+                child.parent_id = parent_id;
+            }
+        }
+
+        let now = Utc::now();
+        for task in self.tasks_mut().iter_mut() {
+            if removed.contains(&task.id) {
+                task.pause();
+                task.deleted_at = Some(now);
+            }
+        }
+        self.save()?;
+        Ok(removed.len())
+    }
+
+    /// Take a task out of the trash, along with any subtasks that were deleted
+    /// with it. If its parent is no longer live it becomes a top-level task.
+    /// Returns the number of tasks restored.
+    fn restore_task(&mut self, id: Uuid) -> Result<usize> {
+        let task = self.get_task(id).context("Task not found")?;
+        let deleted_at = task.deleted_at.context("Task is not in the trash")?;
+        let parent_is_live = task
+            .parent_id
+            .and_then(|parent_id| self.get_task(parent_id))
+            .is_some_and(|parent| !parent.is_deleted());
+
+        let mut restored = vec![id];
+        let mut next = 0;
+        while next < restored.len() {
+            let parent_id = restored[next];
+            let children: Vec<Uuid> = self
+                .get_all_tasks()
+                .iter()
+                .filter(|t| t.parent_id == Some(parent_id) && t.deleted_at == Some(deleted_at))
+                .map(|t| t.id)
+                .filter(|child_id| !restored.contains(child_id))
+                .collect();
+            restored.extend(children);
+            next += 1;
+        }
+
+        for task in self.tasks_mut().iter_mut() {
+            if restored.contains(&task.id) {
+                task.deleted_at = None;
+                if task.id == id && !parent_is_live {
+                    task.parent_id = None;
+                }
+            }
+        }
+        self.save()?;
+        Ok(restored.len())
+    }
+
+    /// Permanently remove the trashed tasks picked by `matching`, or only those
+    /// deleted before `cutoff`. Returns the number of tasks removed.
+    fn empty_trash(
+        &mut self,
+        cutoff: Option<DateTime<Utc>>,
+        matching: &dyn Fn(&Task) -> bool,
+    ) -> Result<usize> {
+        let expired = |task: &Task| {
+            task.deleted_at
+                .is_some_and(|at| cutoff.is_none_or(|cutoff| at < cutoff))
+                && matching(task)
+        };
+
+        let before = self.get_all_tasks().len();
+        self.tasks_mut().retain(|t| !expired(t));
+        let removed = before - self.get_all_tasks().len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Revert the most recent change to this store, returning its summary.
+    fn undo(&mut self) -> Result<Option<String>> {
+        let mutation = match self.journal().undo_target()? {
+            Some((mutation, _)) => mutation,
+            None => return Ok(None),
+        };
+        apply_changes(self.tasks_mut(), &mutation.changes, true);
+        self.journal_mut().set_pending(JournalEntry::Undo {
+            target: mutation.id,
+            at: Utc::now(),
+        });
+        self.save()?;
+        Ok(Some(mutation.summary))
+    }
+
+    /// Re-apply the most recently undone change, returning its summary.
+    fn redo(&mut self) -> Result<Option<String>> {
+        let mutation = match self.journal().redo_target()? {
+            Some((mutation, _)) => mutation,
+            None => return Ok(None),
+        };
+        apply_changes(self.tasks_mut(), &mutation.changes, false);
+        self.journal_mut().set_pending(JournalEntry::Redo {
+            target: mutation.id,
+            at: Utc::now(),
+        });
+        self.save()?;
+        Ok(Some(mutation.summary))
+    }
+
+    fn get_task(&self, id: Uuid) -> Option<&Task> {
+        self.get_all_tasks().iter().find(|t| t.id == id)
+    }
+
+    /// Every task that isn't in the trash.
+    fn get_tasks(&self) -> Vec<&Task> {
+        self.get_all_tasks()
+            .iter()
+            .filter(|t| !t.is_deleted())
+            .collect()
+    }
+
+    /// Tasks in the trash, most recently deleted first.
+    fn get_trashed_tasks(&self) -> Vec<&Task> {
+        let mut trashed: Vec<&Task> = self
+            .get_all_tasks()
+            .iter()
+            .filter(|t| t.is_deleted())
+            .collect();
+        trashed.sort_by_key(|t| std::cmp::Reverse(t.deleted_at));
+        trashed
+    }
+
+    fn get_root_tasks(&self) -> Vec<&Task> {
+        self.get_tasks()
+            .into_iter()
+            .filter(|t| t.parent_id.is_none())
+            .collect()
+    }
+
+    fn get_children(&self, parent_id: Uuid) -> Vec<&Task> {
+        self.get_tasks()
+            .into_iter()
+            .filter(|t| t.parent_id == Some(parent_id))
+            .collect()
+    }
+
+    /// IDs of every task below `id` in the hierarchy (not including `id` itself).
+    fn get_descendants(&self, id: Uuid) -> Vec<Uuid> {
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::from([id]);
+        let mut pending = vec![id];
+        while let Some(parent_id) = pending.pop() {
+            for child in self.get_children(parent_id) {
+                // `seen` guards against parent cycles
+                if seen.insert(child.id) {
+                    descendants.push(child.id);
+                    pending.push(child.id);
+                }
+            }
+        }
+        descendants
+    }
+
+    /// `id` and the IDs of every task below it, including trashed ones, e.g. for
+    /// moving a whole subtree to another store.
+    fn get_subtree(&self, id: Uuid) -> Vec<Uuid> {
+        let mut subtree = vec![id];
+        let mut next = 0;
+        while next < subtree.len() {
+            let parent_id = subtree[next];
+            let children: Vec<Uuid> = self
+                .get_all_tasks()
+                .iter()
+                .filter(|t| t.parent_id == Some(parent_id) && !subtree.contains(&t.id))
+                .map(|t| t.id)
+                .collect();
+            subtree.extend(children);
+            next += 1;
+        }
+        subtree
+    }
+
+    fn get_task_hierarchy(&self, task: &Task) -> Vec<Uuid> {
+        let mut hierarchy = vec![task.id];
+        let mut current_id = task.parent_id;
+
+        while let Some(id) = current_id {
+            // Stop at a parent cycle rather than looping forever (see `twig doctor`)
+            if hierarchy.contains(&id) {
+                break;
+            }
+            hierarchy.insert(0, id);
+            if let Some(parent) = self.get_task(id) {
+                current_id = parent.parent_id;
+            } else {
+                break;
+            }
+        }
+
+        hierarchy
+    }
+}
+
+/// Journal the next save of each of `stores` (owner and store) as a single
+/// change described by `summary`: undoing or redoing it in any of them does so
+/// in all (see `undo_linked`).
+pub fn link_next_saves(stores: &mut [(&str, &mut dyn TaskStore)], summary: &str) {
+    let links: Vec<LinkedMutation> = stores
+        .iter()
+        .map(|(owner, _)| LinkedMutation {
+            owner: owner.to_string(),
+            id: Uuid::new_v4(),
+        })
+        .collect();
+    for (i, (_, store)) in stores.iter_mut().enumerate() {
+        let others = links
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, link)| link.clone())
+            .collect();
+        store
+            .journal_mut()
+            .link_next(links[i].id, summary.to_string(), others);
+    }
+}
+
+/// Undo the last change to the first of `stores`, along with the changes to the
+/// others saved together with it (see `link_next_saves`). Returns its summary.
+pub fn undo_linked(stores: Vec<(&str, &mut dyn TaskStore)>) -> Result<Option<String>> {
+    step_linked(stores, true)
+}
+
+/// Redo the last undone change to the first of `stores`, along with the changes
+/// to the others saved together with it. Returns its summary.
+pub fn redo_linked(stores: Vec<(&str, &mut dyn TaskStore)>) -> Result<Option<String>> {
+    step_linked(stores, false)
+}
+
+fn step_linked(mut stores: Vec<(&str, &mut dyn TaskStore)>, undo: bool) -> Result<Option<String>> {
+    let target = |journal: &Journal| {
+        if undo {
+            journal.undo_target()
+        } else {
+            journal.redo_target()
+        }
+    };
+    let Some(((_, first), rest)) = stores.split_first_mut() else {
+        return Ok(None);
+    };
+    let Some((mutation, _)) = target(first.journal())? else {
+        return Ok(None);
+    };
+
+    let mut linked = Vec::new();
+    for (owner, store) in rest.iter_mut() {
+        let Some(link) = mutation.linked.iter().find(|link| link.owner == *owner) else {
+            continue;
+        };
+        match target(store.journal())? {
+            Some((next, _)) if next.id == link.id => linked.push(store),
+            // Undoing it now would skip over the later changes to that store
+            _ if store.journal().reaches(link.id, !undo)? => bail!(
+                "{} changed after '{}'; {} those changes first",
+                if *owner == "me" {
+                    "Your tasks".to_string()
+                } else {
+                    format!("{}'s tasks", owner)
+                },
+                mutation.summary,
+                if undo { "undo" } else { "redo" }
+            ),
+            _ => {}
+        }
+    }
+
+    let summary = if undo { first.undo()? } else { first.redo()? };
+    for store in linked {
+        if undo {
+            store.undo()?;
+        } else {
+            store.redo()?;
+        }
+    }
+    Ok(summary)
+}
+
+/// Create the (unloaded) task store for `owner` using the backend selected in `config`.
+/// `owner` is "me" for the main task list, otherwise a reportee name.
+pub fn new_task_store(paths: &DataPaths, config: &Config, owner: &str) -> Box<dyn TaskStore> {
+    let json_path = if owner == "me" {
+        paths.tasks_file()
+    } else {
+        paths.reportee_tasks_file(owner)
+    };
+
+    match config.storage_backend {
+        StorageBackend::Json => Box::new(JsonStore::new(json_path.to_string_lossy().to_string())),
+        StorageBackend::Sqlite => {
+            let db_path = if owner == "me" {
+                paths.tasks_db_file()
+            } else {
+                paths.reportee_tasks_db_file(owner)
+            };
+            Box::new(SqliteStore::new(db_path).with_json_import(json_path))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_with_subtree() -> (JsonStore, tempfile::TempDir, [Uuid; 3]) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonStore::new(dir.path().join("tasks.json").to_string_lossy().to_string());
+        store.load().unwrap();
+
+        let root = Task::new("Root".to_string());
+        let mut middle = Task::new("Middle".to_string());
+        middle.parent_id = Some(root.id);
+        let mut leaf = Task::new("Leaf".to_string());
+        leaf.parent_id = Some(middle.id);
+        let ids = [root.id, middle.id, leaf.id];
+        store.tasks_mut().extend([root, middle, leaf]);
+        store.save().unwrap();
+        (store, dir, ids)
+    }
+
+    #[test]
+    fn test_delete_modes() {
+        let (mut store, _dir, [root, middle, leaf]) = store_with_subtree();
+        assert!(store.delete_task(middle, DeleteMode::Abort).is_err());
+
+        assert_eq!(store.delete_task(middle, DeleteMode::Promote).unwrap(), 1);
+        assert_eq!(store.get_task(leaf).unwrap().parent_id, Some(root));
+        assert_eq!(
+            store.undo().unwrap().as_deref(),
+            Some("Delete 'Middle' (+1 more)")
+        );
+        assert_eq!(store.get_task(leaf).unwrap().parent_id, Some(middle));
+
+        assert_eq!(store.delete_task(root, DeleteMode::Cascade).unwrap(), 3);
+        assert!(store.get_tasks().is_empty());
+        assert_eq!(store.get_trashed_tasks().len(), 3);
+        assert_eq!(
+            store.undo().unwrap().as_deref(),
+            Some("Delete 'Root' (+2 more)")
+        );
+        assert_eq!(store.get_tasks().len(), 3);
+
+        // Deleting several tasks at once is one change, and subtasks left behind
+        // move past every deleted ancestor
+        assert!(store
+            .delete_tasks(&[root, middle], DeleteMode::Abort)
+            .is_err());
+        assert_eq!(
+            store
+                .delete_tasks(&[root, middle], DeleteMode::Promote)
+                .unwrap(),
+            2
+        );
+        assert_eq!(store.get_task(leaf).unwrap().parent_id, None);
+        assert_eq!(
+            store.undo().unwrap().as_deref(),
+            Some("Delete 'Root' (+2 more)")
+        );
+        assert_eq!(store.get_task(leaf).unwrap().parent_id, Some(middle));
+
+        // Only subtasks left behind count towards refusing the delete
+        let mut sibling = Task::new("Sibling".to_string());
+        sibling.parent_id = Some(root);
+        store.add_task(sibling).unwrap();
+        let error = store
+            .delete_tasks(&[root, middle], DeleteMode::Abort)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "'Root' has 1 subtask(s)");
+    }
+
+    #[test]
+    fn test_trash_restore_and_empty() {
+        let (mut store, _dir, [root, middle, leaf]) = store_with_subtree();
+        store.delete_task(middle, DeleteMode::Cascade).unwrap();
+        store.delete_task(root, DeleteMode::Abort).unwrap();
+        assert_eq!(store.get_trashed_tasks()[0].id, root);
+
+        // Restoring the subtree brings back the leaf deleted with it, but the
+        // parent is still in the trash so it moves to the top level
+        assert_eq!(store.restore_task(middle).unwrap(), 2);
+        assert_eq!(store.get_task(middle).unwrap().parent_id, None);
+        assert_eq!(store.get_children(middle)[0].id, leaf);
+
+        let cutoff = store.get_task(root).unwrap().deleted_at;
+        assert_eq!(store.empty_trash(cutoff, &|_| true).unwrap(), 0);
+        assert_eq!(store.empty_trash(None, &|t| t.id != root).unwrap(), 0);
+        assert_eq!(store.empty_trash(None, &|_| true).unwrap(), 1);
+        assert!(store.get_task(root).is_none());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::models::Task;
+use crate::storage::task_store::link_next_saves;
+use crate::storage::TaskStore;
+use anyhow::Result;
+use uuid::Uuid;
+
+/// A task whose timer is running, and the owner of the store holding it.
+#[derive(Debug, Clone)]
+pub struct RunningTimer {
+    pub owner: String,
+    pub task: Task,
+}
+
+impl RunningTimer {
+    /// " (alice)" after a reportee's task; nothing after your own.
+    pub fn owner_suffix(&self) -> String {
+        if self.owner == "me" {
+            String::new()
+        } else {
+            format!(" ({})", self.owner)
+        }
+    }
+}
+
+/// Every task with a running timer in `stores`, longest running first.
+pub fn running_timers<'a>(
+    stores: impl IntoIterator<Item = (&'a str, &'a dyn TaskStore)>,
+) -> Vec<RunningTimer> {
+    let mut timers: Vec<RunningTimer> = stores
+        .into_iter()
+        .flat_map(|(owner, storage)| {
+            storage
+                .get_tasks()
+                .into_iter()
+                .filter(|t| t.has_active_time_entry())
+                .map(|task| RunningTimer {
+                    owner: owner.to_string(),
+                    task: task.clone(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    timers.sort_by_key(|t| {
+        t.task
+            .time_entries
+            .iter()
+            .find(|e| e.is_active())
+            .map(|e| e.start)
+    });
+    timers
+}
+
+/// Pause every running timer in `stores` except the one on task `keep`, so that
+/// only one task is timed at once. Only changes the tasks in memory; returns
+/// the tasks paused, so the caller knows which stores to save.
+pub fn pause_other_timers(
+    stores: &mut [(&str, &mut dyn TaskStore)],
+    keep: Uuid,
+) -> Vec<RunningTimer> {
+    let mut paused = Vec::new();
+    for (owner, storage) in stores {
+        for task in storage.tasks_mut() {
+            if task.id != keep && task.has_active_time_entry() {
+                task.pause();
+                paused.push(RunningTimer {
+                    owner: owner.to_string(),
+                    task: task.clone(),
+                });
+            }
+        }
+    }
+    paused
+}
+
+/// Pause the timers running on any task but `keep` (see `pause_other_timers`),
+/// then save the first of `stores`, which holds `keep`, and every store paused
+/// in as one change described by `summary`, so undoing it restarts them all.
+pub fn save_pausing_other_timers(
+    mut stores: Vec<(&str, &mut dyn TaskStore)>,
+    keep: Uuid,
+    summary: &str,
+) -> Result<Vec<RunningTimer>> {
+    let paused = pause_other_timers(&mut stores, keep);
+    let mut first = true;
+    stores.retain(|(owner, _)| {
+        std::mem::take(&mut first) || paused.iter().any(|t| t.owner == *owner)
+    });
+    if stores.len() > 1 {
+        link_next_saves(&mut stores, summary);
+    }
+    for (_, store) in stores {
+        store.save()?;
+    }
+    Ok(paused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::task_store::undo_linked;
+    use crate::storage::JsonStore;
+
+    #[test]
+    fn test_starting_pauses_timers_in_every_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mine = JsonStore::new(dir.path().join("tasks.json").to_string_lossy().to_string());
+        let mut theirs =
+            JsonStore::new(dir.path().join("alice.json").to_string_lossy().to_string());
+        mine.load().unwrap();
+        theirs.load().unwrap();
+
+        let mut running = Task::new("Running".to_string());
+        running.start();
+        let mut other = Task::new("Other".to_string());
+        other.start();
+        let mut next = Task::new("Next".to_string());
+        next.start();
+        next.start();
+        assert_eq!(next.time_entries.len(), 1);
+        let next_id = next.id;
+        mine.tasks_mut().extend([running, next]);
+        theirs.tasks_mut().push(other);
+
+        let mut stores: Vec<(&str, &mut dyn TaskStore)> =
+            vec![("me", &mut mine), ("alice", &mut theirs)];
+        let paused = pause_other_timers(&mut stores, next_id);
+        assert_eq!(paused.len(), 2);
+        assert!(paused.iter().any(|t| t.owner == "alice"));
+
+        let timers = running_timers([("me", &mine as &dyn TaskStore), ("alice", &theirs)]);
+        assert_eq!(timers.len(), 1);
+        assert_eq!(timers[0].task.id, next_id);
+    }
+
+    #[test]
+    fn test_undoing_a_start_restarts_the_paused_timers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut mine = JsonStore::new(dir.path().join("tasks.json").to_string_lossy().to_string());
+        let mut theirs =
+            JsonStore::new(dir.path().join("alice.json").to_string_lossy().to_string());
+        mine.load().unwrap();
+        theirs.load().unwrap();
+
+        let next = Task::new("Next".to_string());
+        let next_id = next.id;
+        mine.add_task(next).unwrap();
+        let mut other = Task::new("Other".to_string());
+        other.start();
+        theirs.add_task(other).unwrap();
+
+        mine.get_task_mut(next_id).unwrap().start();
+        let stores: Vec<(&str, &mut dyn TaskStore)> =
+            vec![("me", &mut mine), ("alice", &mut theirs)];
+        let paused = save_pausing_other_timers(stores, next_id, "Start 'Next'").unwrap();
+        assert_eq!(paused.len(), 1);
+        assert_eq!(paused[0].owner_suffix(), " (alice)");
+
+        // Undoing from either store undoes both
+        let stores: Vec<(&str, &mut dyn TaskStore)> =
+            vec![("alice", &mut theirs), ("me", &mut mine)];
+        let summary = undo_linked(stores).unwrap();
+        assert_eq!(summary.as_deref(), Some("Start 'Next'"));
+        assert!(!mine.get_task(next_id).unwrap().has_active_time_entry());
+        assert!(theirs.get_tasks()[0].has_active_time_entry());
+    }
+}
@@ -1,6 +1,14 @@
-use crate::models::{Task, TaskStatus};
-use crate::storage::{DataPaths, Storage};
+use crate::models::{Config, Task, TaskStatus, TimerPolicy, Timezone, WorkCalendar};
+use crate::storage::archive::Archive;
+use crate::storage::delegation::{move_task_tree, sync_stubs};
+use crate::storage::journal::{Journal, TimedMutation};
+use crate::storage::task_store::{redo_linked, undo_linked};
+use crate::storage::timers::save_pausing_other_timers;
+use crate::storage::{json_store, new_task_store, DataPaths, DeleteMode, TaskStore};
 use crate::tui::ui;
+use crate::utils::date::{start_of_day, DateRange};
+use crate::utils::filter::Filter;
+use crate::utils::task_ref::IdPrefixes;
 use anyhow::Result;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
@@ -9,6 +17,10 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::time::Duration;
+
+/// How often the TUI checks whether the task files were changed by another process.
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 pub enum AppMode {
     Normal,
@@ -16,6 +28,8 @@ pub enum AppMode {
     AddTask,
     EditTask,
     DeleteConfirm,
+    Delegate,
+    Filter,
 }
 
 pub struct InputState {
@@ -31,6 +45,16 @@ pub enum ViewTab {
     MyTasks,
     AllReportees,
     History,
+    Trash,
+}
+
+/// The delegate dialog: where the task goes and whether to leave a stub.
+pub struct DelegateState {
+    pub task_id: uuid::Uuid,
+    pub owner: String,
+    pub targets: Vec<String>,
+    pub selected: usize,
+    pub keep_stub: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -59,41 +83,57 @@ pub enum VisibleItemInfo<'a> {
 }
 
 pub struct App {
-    pub storage: Storage,
+    pub storage: Box<dyn TaskStore>,
     pub selected_index: usize,
     pub mode: AppMode,
     pub view_tab: ViewTab,
     pub reportees: Vec<String>,
-    pub reportee_storages: std::collections::HashMap<String, Storage>,
+    pub reportee_storages: std::collections::HashMap<String, Box<dyn TaskStore>>,
     pub show_completed: bool,
     pub show_cancelled: bool,
-    pub filter_tag: Option<String>,
+    // Filter applied to the task and history views, and the `/` prompt's text
+    pub filter: Filter,
+    pub filter_input: String,
     pub expanded_tasks: Vec<uuid::Uuid>,
     pub expanded_reportees: Vec<String>, // which reportee sections are expanded
     pub should_quit: bool,
     pub input_state: InputState,
     pub editing_task_id: Option<uuid::Uuid>,
+    pub delegate_state: Option<DelegateState>,
     pub visible_task_list: Vec<VisibleItem>,
     // History view state
     pub history_period: HistoryPeriod,
     pub history_date: chrono::NaiveDate,
+    // Archived tasks by owner, read on demand for History periods starting on or
+    // after `archive_loaded_since`
+    pub archived_tasks: std::collections::HashMap<String, Vec<Task>>,
+    archive_loaded_since: Option<chrono::NaiveDate>,
+    paths: DataPaths,
+    // Shortest unique ID prefix of each owner's tasks, refreshed with the task list
+    id_prefixes: std::collections::HashMap<String, IdPrefixes>,
+    id_prefix_length: usize,
+    timer_policy: TimerPolicy,
+    pub work_calendar: WorkCalendar,
+    // For the timezone and week start of dates and filters
+    config: Config,
+    // One-off feedback shown in the footer until the next key press
+    pub status_message: Option<String>,
 }
 
 impl App {
     pub fn new() -> Result<Self> {
         let paths = DataPaths::new()?;
-        let mut storage = Storage::new(paths.tasks_file().to_string_lossy().to_string());
+        let config = json_store::load_config(&paths.config_file())?;
+        let mut storage = new_task_store(&paths, &config, "me");
         storage.load()?;
 
         // Load reportees
-        let config = crate::storage::json_store::load_config(&paths.config_file())?;
         let reportees = config.reportees.clone();
 
         // Load reportee storages
         let mut reportee_storages = std::collections::HashMap::new();
         for reportee in &reportees {
-            let reportee_path = paths.reportee_tasks_file(reportee);
-            let mut reportee_storage = Storage::new(reportee_path.to_string_lossy().to_string());
+            let mut reportee_storage = new_task_store(&paths, &config, reportee);
             let _ = reportee_storage.load(); // Ignore errors for now
             reportee_storages.insert(reportee.clone(), reportee_storage);
         }
@@ -107,7 +147,8 @@ impl App {
             reportee_storages,
             show_completed: false, // Only show tasks completed today by default
             show_cancelled: false,
-            filter_tag: None,
+            filter: Filter::default(),
+            filter_input: String::new(),
             expanded_tasks: Vec::new(),
             expanded_reportees: Vec::new(),
             should_quit: false,
@@ -120,9 +161,21 @@ impl App {
                 current_field: 0,
             },
             editing_task_id: None,
+            delegate_state: None,
             visible_task_list: Vec::new(),
             history_period: HistoryPeriod::Day,
-            history_date: chrono::Local::now().date_naive(),
+            history_date: chrono::Utc::now()
+                .with_timezone(&config.timezone)
+                .date_naive(),
+            archived_tasks: std::collections::HashMap::new(),
+            archive_loaded_since: None,
+            paths,
+            id_prefixes: std::collections::HashMap::new(),
+            id_prefix_length: config.id_prefix_length,
+            timer_policy: config.timer_policy,
+            work_calendar: config.work_calendar.clone(),
+            config,
+            status_message: None,
         })
     }
 
@@ -136,7 +189,8 @@ impl App {
                 }
             }
             ViewTab::AllReportees => ViewTab::History,
-            ViewTab::History => ViewTab::MyTasks,
+            ViewTab::History => ViewTab::Trash,
+            ViewTab::Trash => ViewTab::MyTasks,
         };
         self.selected_index = 0;
         self.rebuild_visible_task_list();
@@ -147,6 +201,7 @@ impl App {
             1 => ViewTab::MyTasks,
             2 if !self.reportees.is_empty() => ViewTab::AllReportees,
             3 => ViewTab::History,
+            4 => ViewTab::Trash,
             _ => return,
         };
         self.selected_index = 0;
@@ -155,6 +210,8 @@ impl App {
 
     pub fn rebuild_visible_task_list(&mut self) {
         self.visible_task_list.clear();
+        self.sync_delegated_stubs();
+        self.rebuild_id_prefixes();
 
         match &self.view_tab {
             ViewTab::MyTasks => {
@@ -162,7 +219,7 @@ impl App {
                     .storage
                     .get_root_tasks()
                     .into_iter()
-                    .filter(|t| self.should_show_task(t))
+                    .filter(|t| self.should_show_task(t, "me"))
                     .map(|t| t.id)
                     .collect();
 
@@ -184,7 +241,7 @@ impl App {
                                 storage
                                     .get_root_tasks()
                                     .into_iter()
-                                    .filter(|t| self.should_show_task(t))
+                                    .filter(|t| self.should_show_task(t, reportee))
                                     .map(|t| t.id)
                                     .collect()
                             } else {
@@ -201,87 +258,187 @@ impl App {
                 // Show tasks completed/cancelled in the selected period
                 self.rebuild_history_list();
             }
+            ViewTab::Trash => {
+                self.rebuild_trash_list();
+            }
         }
     }
 
-    fn rebuild_history_list(&mut self) {
-        use chrono::{Datelike, Duration};
+    /// Whether the current tab shows live tasks that can be added to and edited.
+    pub fn is_task_view(&self) -> bool {
+        matches!(self.view_tab, ViewTab::MyTasks | ViewTab::AllReportees)
+    }
 
-        let (start_date, end_date) = match self.history_period {
-            HistoryPeriod::Day => (self.history_date, self.history_date),
-            HistoryPeriod::Week => {
-                // Start of week (Monday) to end of week (Sunday)
-                let days_from_monday = self.history_date.weekday().num_days_from_monday();
-                let start = self.history_date - Duration::days(days_from_monday as i64);
-                let end = start + Duration::days(6);
-                (start, end)
-            }
-            HistoryPeriod::Month => {
-                // Start of month to end of month
-                let start = self.history_date.with_day(1).unwrap();
-                let next_month = if self.history_date.month() == 12 {
-                    chrono::NaiveDate::from_ymd_opt(self.history_date.year() + 1, 1, 1).unwrap()
-                } else {
-                    chrono::NaiveDate::from_ymd_opt(
-                        self.history_date.year(),
-                        self.history_date.month() + 1,
-                        1,
-                    )
-                    .unwrap()
-                };
-                let end = next_month - Duration::days(1);
-                (start, end)
-            }
+    fn rebuild_trash_list(&mut self) {
+        let stores = std::iter::once(("me", &self.storage)).chain(
+            self.reportee_storages
+                .iter()
+                .map(|(name, storage)| (name.as_str(), storage)),
+        );
+
+        let mut trashed: Vec<_> = stores
+            .flat_map(|(owner, storage)| {
+                storage
+                    .get_trashed_tasks()
+                    .into_iter()
+                    .map(move |t| (t.deleted_at, t.id, owner.to_string()))
+            })
+            .collect();
+        trashed.sort_by_key(|(deleted_at, _, _)| std::cmp::Reverse(*deleted_at));
+
+        self.visible_task_list.extend(
+            trashed
+                .into_iter()
+                .map(|(_, id, owner)| VisibleItem::Task { id, owner }),
+        );
+    }
+
+    fn rebuild_history_list(&mut self) {
+        let range = match self.history_period {
+            HistoryPeriod::Day => DateRange::Day(self.history_date),
+            HistoryPeriod::Week => DateRange::week_of(self.history_date, self.config.week_start),
+            HistoryPeriod::Month => DateRange::month_of(self.history_date),
         };
+        let (start_date, end_date) = (range.first_day(), range.last_day());
+
+        self.load_archive_since(start_date);
 
-        // Collect all completed/cancelled tasks from my storage
-        let my_history: Vec<uuid::Uuid> = self
-            .storage
-            .get_all_tasks()
-            .iter()
-            .filter(|t| self.is_task_in_history_range(t, start_date, end_date))
-            .map(|t| t.id)
+        // Collect all completed/cancelled tasks from my storage, then reportees'
+        let owners: Vec<String> = std::iter::once("me".to_string())
+            .chain(self.reportees.iter().cloned())
             .collect();
+        for owner in owners {
+            let storage = self.get_storage_for_owner(&owner);
+            let calendar = self.work_calendar.for_owner(&owner);
+            let archived = self
+                .archived_tasks
+                .get(&owner)
+                .into_iter()
+                .flatten()
+                .filter(|t| storage.get_task(t.id).is_none());
+            let history: Vec<uuid::Uuid> = storage
+                .get_tasks()
+                .into_iter()
+                .chain(archived)
+                .filter(|t| self.is_task_in_history_range(t, start_date, end_date))
+                .filter(|t| self.filter.matches(t, &calendar))
+                .map(|t| t.id)
+                .collect();
 
-        for task_id in my_history {
-            self.visible_task_list.push(VisibleItem::Task {
-                id: task_id,
-                owner: "me".to_string(),
-            });
+            for task_id in history {
+                self.visible_task_list.push(VisibleItem::Task {
+                    id: task_id,
+                    owner: owner.clone(),
+                });
+            }
         }
+    }
 
-        // Also include reportee tasks
-        let reportees = self.reportees.clone();
-        for reportee in &reportees {
-            if let Some(storage) = self.reportee_storages.get(reportee) {
-                let reportee_history: Vec<uuid::Uuid> = storage
-                    .get_all_tasks()
-                    .iter()
-                    .filter(|t| self.is_task_in_history_range(t, start_date, end_date))
-                    .map(|t| t.id)
-                    .collect();
+    /// Make sure archived tasks for periods from `since` on are loaded.
+    fn load_archive_since(&mut self, since: chrono::NaiveDate) {
+        if self
+            .archive_loaded_since
+            .is_some_and(|loaded| loaded <= since)
+        {
+            return;
+        }
 
-                for task_id in reportee_history {
-                    self.visible_task_list.push(VisibleItem::Task {
-                        id: task_id,
-                        owner: reportee.clone(),
-                    });
+        let since_utc = start_of_day(&self.config.timezone, since);
+        let owners = std::iter::once("me").chain(self.reportees.iter().map(String::as_str));
+        let mut archived_tasks = std::collections::HashMap::new();
+        for owner in owners {
+            match Archive::for_owner(&self.paths, &self.config, owner).load_since(Some(since_utc)) {
+                Ok(mut tasks) => {
+                    tasks.retain(|t| !t.is_deleted());
+                    archived_tasks.insert(owner.to_string(), tasks);
+                }
+                Err(e) => {
+                    self.status_message = Some(format!("Failed to read archive: {}", e));
+                    return;
+                }
+            }
+        }
+        self.archived_tasks = archived_tasks;
+        self.archive_loaded_since = Some(since);
+    }
+
+    /// Show each delegated stub with the status of the task it stands in for.
+    fn sync_delegated_stubs(&mut self) {
+        let links: Vec<(String, uuid::Uuid)> = std::iter::once(&self.storage)
+            .chain(self.reportee_storages.values())
+            .flat_map(|storage| storage.get_tasks())
+            .filter_map(|t| t.delegated_to.as_ref())
+            .map(|link| (link.owner.clone(), link.task_id))
+            .collect();
+        if links.is_empty() {
+            return;
+        }
+
+        let linked: std::collections::HashMap<(String, uuid::Uuid), Task> = links
+            .into_iter()
+            .filter_map(|(owner, id)| {
+                let task = self.find_task(id, &owner)?.clone();
+                Some(((owner, id), task))
+            })
+            .collect();
+        let find = |owner: &str, id| linked.get(&(owner.to_string(), id)).cloned();
+
+        // Save the synced stubs right away, outside the undo history, so that
+        // they don't end up in the next change saved to their store
+        let stores = std::iter::once(&mut self.storage).chain(self.reportee_storages.values_mut());
+        for storage in stores {
+            if sync_stubs(storage.as_mut(), find) {
+                storage.journal_mut().skip_next();
+                if let Err(e) = storage.save() {
+                    self.status_message = Some(format!("Failed to save stubs: {}", e));
                 }
             }
         }
     }
 
+    fn rebuild_id_prefixes(&mut self) {
+        let mut id_prefixes = std::collections::HashMap::new();
+        id_prefixes.insert(
+            "me".to_string(),
+            IdPrefixes::new(&self.storage.get_tasks(), self.id_prefix_length),
+        );
+        for (name, storage) in &self.reportee_storages {
+            id_prefixes.insert(
+                name.clone(),
+                IdPrefixes::new(&storage.get_tasks(), self.id_prefix_length),
+            );
+        }
+        self.id_prefixes = id_prefixes;
+    }
+
+    /// The ID to show for one of `owner`'s tasks.
+    pub fn display_id(&self, task: &Task, owner: &str) -> String {
+        match self.id_prefixes.get(owner) {
+            Some(prefixes) => prefixes.get(task),
+            None => task.short_id(),
+        }
+    }
+
+    /// Look up a task in `owner`'s store, falling back to their loaded archive.
+    pub fn find_task(&self, id: uuid::Uuid, owner: &str) -> Option<&Task> {
+        self.get_storage_for_owner(owner).get_task(id).or_else(|| {
+            self.archived_tasks
+                .get(owner)
+                .and_then(|tasks| tasks.iter().find(|t| t.id == id))
+        })
+    }
+
     fn is_task_in_history_range(
         &self,
         task: &Task,
         start: chrono::NaiveDate,
         end: chrono::NaiveDate,
     ) -> bool {
-        use chrono::Local;
-
         // Check if task was completed in range
         if let Some(completed_at) = task.completed_at {
-            let completed_date = completed_at.with_timezone(&Local).date_naive();
+            let completed_date = completed_at
+                .with_timezone(&self.config.timezone)
+                .date_naive();
             if completed_date >= start && completed_date <= end {
                 return true;
             }
@@ -289,7 +446,9 @@ impl App {
 
         // Check if task was cancelled in range
         if let Some(cancelled_at) = task.cancelled_at {
-            let cancelled_date = cancelled_at.with_timezone(&Local).date_naive();
+            let cancelled_date = cancelled_at
+                .with_timezone(&self.config.timezone)
+                .date_naive();
             if cancelled_date >= start && cancelled_date <= end {
                 return true;
             }
@@ -304,13 +463,14 @@ impl App {
             owner: owner.clone(),
         });
 
-        // If task is expanded, add its children
-        if self.expanded_tasks.contains(&task_id) {
+        // If task is expanded, add its children. A filter expands everything
+        // leading to a match.
+        if self.expanded_tasks.contains(&task_id) || !self.filter.is_empty() {
             let storage = self.get_storage_for_owner(&owner);
             let child_ids: Vec<uuid::Uuid> = storage
                 .get_children(task_id)
                 .into_iter()
-                .filter(|c| self.should_show_task(c))
+                .filter(|c| self.should_show_task(c, &owner))
                 .map(|c| c.id)
                 .collect();
 
@@ -320,10 +480,41 @@ impl App {
         }
     }
 
-    fn should_show_task(&self, task: &Task) -> bool {
-        use chrono::Local;
+    fn should_show_task(&self, task: &Task, owner: &str) -> bool {
+        if self.filter.is_empty() {
+            return self.is_shown_by_status(task);
+        }
+
+        // Matching tasks, and the tasks above them so they can be reached
+        let calendar = self.work_calendar.for_owner(owner);
+        let shows = |t: &Task| {
+            self.filter.matches(t, &calendar)
+                && (self.filter.constrains_status() || self.is_shown_by_status(t))
+        };
+        let storage = self.get_storage_for_owner(owner);
+        shows(task)
+            || storage
+                .get_descendants(task.id)
+                .into_iter()
+                .filter_map(|id| storage.get_task(id))
+                .any(shows)
+    }
+
+    /// The timezone dates are shown in.
+    pub fn timezone(&self) -> &Timezone {
+        &self.config.timezone
+    }
+
+    /// Today's date in the configured timezone.
+    fn today(&self) -> chrono::NaiveDate {
+        chrono::Utc::now()
+            .with_timezone(&self.config.timezone)
+            .date_naive()
+    }
 
-        let today = Local::now().date_naive();
+    /// Whether a task passes the hiding of finished tasks (toggled with h/H).
+    fn is_shown_by_status(&self, task: &Task) -> bool {
+        let today = self.today();
 
         // For completed tasks: only show if 'h' is pressed AND completed today
         if task.status == TaskStatus::Completed {
@@ -333,7 +524,9 @@ impl App {
             }
             // When show_completed is on, only show today's completed tasks
             if let Some(completed_at) = task.completed_at {
-                let completed_date = completed_at.with_timezone(&Local).date_naive();
+                let completed_date = completed_at
+                    .with_timezone(&self.config.timezone)
+                    .date_naive();
                 if completed_date != today {
                     return false;
                 }
@@ -348,22 +541,19 @@ impl App {
             }
             // When show_cancelled is on, only show today's cancelled tasks
             if let Some(cancelled_at) = task.cancelled_at {
-                let cancelled_date = cancelled_at.with_timezone(&Local).date_naive();
+                let cancelled_date = cancelled_at
+                    .with_timezone(&self.config.timezone)
+                    .date_naive();
                 if cancelled_date != today {
                     return false;
                 }
             }
         }
 
-        if let Some(ref tag) = self.filter_tag {
-            if !task.tags.contains(tag) {
-                return false;
-            }
-        }
         true
     }
 
-    pub fn get_visible_items(&self) -> Vec<VisibleItemInfo> {
+    pub fn get_visible_items(&self) -> Vec<VisibleItemInfo<'_>> {
         let mut result = Vec::new();
         for item in &self.visible_task_list {
             match item {
@@ -374,9 +564,8 @@ impl App {
                     });
                 }
                 VisibleItem::Task { id, owner } => {
-                    let storage = self.get_storage_for_owner(owner);
-                    if let Some(task) = storage.get_task(*id) {
-                        let depth = self.get_task_depth(task, storage);
+                    if let Some(task) = self.find_task(*id, owner) {
+                        let depth = self.get_task_depth(task, owner);
                         result.push(VisibleItemInfo::Task {
                             task,
                             depth,
@@ -389,12 +578,12 @@ impl App {
         result
     }
 
-    fn get_task_depth(&self, task: &Task, storage: &Storage) -> usize {
+    fn get_task_depth(&self, task: &Task, owner: &str) -> usize {
         let mut depth = 0;
         let mut current_id = task.parent_id;
         while let Some(id) = current_id {
             depth += 1;
-            if let Some(parent) = storage.get_task(id) {
+            if let Some(parent) = self.find_task(id, owner) {
                 current_id = parent.parent_id;
             } else {
                 break;
@@ -414,26 +603,28 @@ impl App {
     pub fn get_selected_task(&self) -> Option<(&Task, &str)> {
         match self.get_selected_item()? {
             VisibleItem::Task { id, owner } => {
-                let storage = self.get_storage_for_owner(owner);
-                storage.get_task(*id).map(|t| (t, owner.as_str()))
+                self.find_task(*id, owner).map(|t| (t, owner.as_str()))
             }
             VisibleItem::ReporteeHeader(_) => None,
         }
     }
 
-    pub fn get_storage_for_owner(&self, owner: &str) -> &Storage {
+    pub fn get_storage_for_owner(&self, owner: &str) -> &dyn TaskStore {
         if owner == "me" {
-            &self.storage
+            self.storage.as_ref()
         } else {
-            self.reportee_storages.get(owner).unwrap_or(&self.storage)
+            self.reportee_storages
+                .get(owner)
+                .unwrap_or(&self.storage)
+                .as_ref()
         }
     }
 
-    fn get_storage_for_owner_mut(&mut self, owner: &str) -> &mut Storage {
+    fn get_storage_for_owner_mut(&mut self, owner: &str) -> &mut dyn TaskStore {
         if owner == "me" {
-            &mut self.storage
+            self.storage.as_mut()
         } else {
-            self.reportee_storages.get_mut(owner).unwrap()
+            self.reportee_storages.get_mut(owner).unwrap().as_mut()
         }
     }
 
@@ -495,6 +686,26 @@ impl App {
         self.rebuild_visible_task_list();
     }
 
+    pub fn start_filter(&mut self) {
+        self.filter_input = self.filter.as_str().to_string();
+        self.mode = AppMode::Filter;
+    }
+
+    /// Apply the filter typed at the `/` prompt; an empty one clears it.
+    pub fn apply_filter(&mut self) {
+        match Filter::parse(&self.filter_input, &self.config) {
+            Ok(filter) => {
+                self.filter = filter;
+                self.mode = AppMode::Normal;
+                self.selected_index = 0;
+                self.rebuild_visible_task_list();
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Invalid filter: {:#}", e));
+            }
+        }
+    }
+
     pub fn start_add_task(&mut self, as_subtask: bool) {
         self.input_state = InputState {
             title: String::new(),
@@ -516,12 +727,14 @@ impl App {
     }
 
     pub fn start_edit_task(&mut self) {
-        if let Some((task, _owner)) = self.get_selected_task() {
+        if let Some((task, owner)) = self.get_selected_task() {
             let task_id = task.id;
             let title = task.title.clone();
             let description = task.description.clone();
             let tags = task.tags.join(", ");
-            let estimate = task.get_formatted_estimate().unwrap_or_default();
+            let estimate = task
+                .get_formatted_estimate(&self.work_calendar.for_owner(owner))
+                .unwrap_or_default();
             let notes = task.notes.clone();
 
             self.editing_task_id = Some(task_id);
@@ -550,10 +763,6 @@ impl App {
                 .collect();
         }
 
-        if !self.input_state.estimate.is_empty() {
-            let _ = task.set_estimate(&self.input_state.estimate);
-        }
-
         task.notes = self.input_state.note.clone();
 
         // Set parent based on editing_task_id (which stores the parent for new tasks)
@@ -587,9 +796,15 @@ impl App {
                     }
                 }
             }
-            ViewTab::History => "me".to_string(), // History view doesn't allow adding tasks
+            // History and Trash views don't allow adding tasks
+            ViewTab::History | ViewTab::Trash => "me".to_string(),
         };
 
+        if !self.input_state.estimate.is_empty() {
+            let calendar = self.work_calendar.for_owner(&owner);
+            let _ = task.set_estimate(&self.input_state.estimate, &calendar);
+        }
+
         let storage = self.get_storage_for_owner_mut(&owner);
         storage.add_task(task)?;
         self.rebuild_visible_task_list();
@@ -622,6 +837,7 @@ impl App {
                 .unwrap_or_else(|| "me".to_string());
 
             {
+                let calendar = self.work_calendar.for_owner(&owner);
                 let storage = self.get_storage_for_owner_mut(&owner);
                 if let Some(task) = storage.get_task_mut(task_id) {
                     task.title = title;
@@ -634,7 +850,7 @@ impl App {
                     }
 
                     if !estimate.is_empty() {
-                        let _ = task.set_estimate(&estimate);
+                        let _ = task.set_estimate(&estimate, &calendar);
                     } else {
                         task.estimated_effort_hours = None;
                     }
@@ -661,7 +877,116 @@ impl App {
         }
     }
 
-    pub fn confirm_delete_task(&mut self) -> Result<()> {
+    pub fn start_delegate_task(&mut self) {
+        let Some((task, owner)) = self.get_selected_task() else {
+            return;
+        };
+        if task.is_delegated() {
+            self.status_message =
+                Some("This is a stub for a delegated task; move that task instead".to_string());
+            return;
+        }
+
+        let targets: Vec<String> = std::iter::once("me".to_string())
+            .chain(self.reportees.iter().cloned())
+            .filter(|target| target != owner)
+            .collect();
+        if self.reportees.is_empty() {
+            self.status_message =
+                Some("Add a reportee first (twig reportee add <name>)".to_string());
+            return;
+        }
+
+        self.delegate_state = Some(DelegateState {
+            task_id: task.id,
+            owner: owner.to_string(),
+            targets,
+            selected: 0,
+            keep_stub: owner == "me",
+        });
+        self.mode = AppMode::Delegate;
+    }
+
+    pub fn delegate_move_selection(&mut self, down: bool) {
+        if let Some(state) = &mut self.delegate_state {
+            state.selected = if down {
+                (state.selected + 1).min(state.targets.len() - 1)
+            } else {
+                state.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    pub fn delegate_toggle_stub(&mut self) {
+        if let Some(state) = &mut self.delegate_state {
+            state.keep_stub = !state.keep_stub;
+        }
+    }
+
+    pub fn confirm_delegate_task(&mut self) -> Result<()> {
+        let Some(state) = self.delegate_state.take() else {
+            return Ok(());
+        };
+        self.mode = AppMode::Normal;
+        let target = state.targets[state.selected].clone();
+
+        // Take the reportee stores out of the map to borrow both ends mutably
+        let mut from = self.reportee_storages.remove(&state.owner);
+        let mut to = self.reportee_storages.remove(&target);
+        let (id, keep_stub) = (state.task_id, state.keep_stub);
+        let owner = state.owner.as_str();
+        let result = match (from.as_mut(), to.as_mut()) {
+            (Some(from), Some(to)) => {
+                move_task_tree(from.as_mut(), owner, to.as_mut(), &target, id, keep_stub)
+            }
+            (Some(from), None) => move_task_tree(
+                from.as_mut(),
+                owner,
+                self.storage.as_mut(),
+                &target,
+                id,
+                keep_stub,
+            ),
+            (None, Some(to)) => move_task_tree(
+                self.storage.as_mut(),
+                owner,
+                to.as_mut(),
+                &target,
+                id,
+                keep_stub,
+            ),
+            (None, None) => Ok(0),
+        };
+        if let Some(from) = from {
+            self.reportee_storages.insert(state.owner.clone(), from);
+        }
+        if let Some(to) = to {
+            self.reportee_storages.insert(target.clone(), to);
+        }
+
+        self.status_message = Some(match result {
+            Ok(moved) if target == "me" => format!("Took back {} task(s)", moved),
+            Ok(moved) => format!("Delegated {} task(s) to {}", moved, target),
+            Err(e) => format!("Failed to delegate: {}", e),
+        });
+        self.rebuild_visible_task_list();
+        self.clamp_selection();
+        Ok(())
+    }
+
+    /// Number of tasks below the task awaiting delete confirmation.
+    pub fn delete_target_subtask_count(&self) -> usize {
+        self.editing_task_id
+            .and_then(|id| self.get_task_by_id_with_owner(id))
+            .map(|(task, owner)| {
+                self.get_storage_for_owner(owner)
+                    .get_descendants(task.id)
+                    .len()
+            })
+            .unwrap_or(0)
+    }
+
+    pub fn confirm_delete_task(&mut self, mode: DeleteMode) -> Result<()> {
         if let Some(task_id) = self.editing_task_id {
             // Find owner
             let owner = self
@@ -677,8 +1002,12 @@ impl App {
                 })
                 .unwrap_or_else(|| "me".to_string());
 
-            self.get_storage_for_owner_mut(&owner)
-                .delete_task(task_id)?;
+            let removed = self
+                .get_storage_for_owner_mut(&owner)
+                .delete_task(task_id, mode)?;
+            if removed > 1 {
+                self.status_message = Some(format!("Moved {} tasks to the trash", removed));
+            }
             self.rebuild_visible_task_list();
             // Adjust selection if needed
             if self.selected_index >= self.visible_task_list.len() && self.selected_index > 0 {
@@ -695,8 +1024,7 @@ impl App {
         for item in &self.visible_task_list {
             if let VisibleItem::Task { id: task_id, owner } = item {
                 if *task_id == id {
-                    let storage = self.get_storage_for_owner(owner);
-                    return storage.get_task(id).map(|t| (t, owner.as_str()));
+                    return self.find_task(id, owner).map(|t| (t, owner.as_str()));
                 }
             }
         }
@@ -717,14 +1045,30 @@ impl App {
 
     pub fn start_selected_task(&mut self) -> Result<()> {
         if let Some((task, owner)) = self.get_selected_task() {
+            if task.is_delegated() {
+                self.status_message = Some(
+                    "This is a stub for a delegated task; start that task instead".to_string(),
+                );
+                return Ok(());
+            }
             let task_id = task.id;
             let owner = owner.to_string();
+            let summary = format!("Start '{}'", task.title);
             {
                 let storage = self.get_storage_for_owner_mut(&owner);
                 if let Some(task_mut) = storage.get_task_mut(task_id) {
                     task_mut.start();
                 }
-                storage.save()?;
+            }
+            if self.timer_policy == TimerPolicy::Single {
+                let stores = self.stores_from(&owner);
+                let paused = save_pausing_other_timers(stores, task_id, &summary)?;
+                if !paused.is_empty() {
+                    let titles: Vec<&str> = paused.iter().map(|t| t.task.title.as_str()).collect();
+                    self.status_message = Some(format!("Paused {}", titles.join(", ")));
+                }
+            } else {
+                self.get_storage_for_owner_mut(&owner).save()?;
             }
         }
         Ok(())
@@ -777,15 +1121,128 @@ impl App {
         Ok(())
     }
 
+    pub fn restore_selected_task(&mut self) -> Result<()> {
+        if let Some((task, owner)) = self.get_selected_task() {
+            let task_id = task.id;
+            let owner = owner.to_string();
+            let restored = self
+                .get_storage_for_owner_mut(&owner)
+                .restore_task(task_id)?;
+            self.status_message = Some(format!("Restored {} task(s)", restored));
+            self.rebuild_visible_task_list();
+            self.clamp_selection();
+        }
+        Ok(())
+    }
+
     pub fn reload(&mut self) -> Result<()> {
         self.storage.load()?;
         for storage in self.reportee_storages.values_mut() {
             let _ = storage.load();
         }
+        self.archive_loaded_since = None;
         self.rebuild_visible_task_list();
+        self.clamp_selection();
         Ok(())
     }
 
+    /// Reload any store another process (e.g. `twig add` in another shell) has
+    /// written since we last loaded or saved it.
+    pub fn reload_if_stale(&mut self) -> Result<()> {
+        let mut reloaded = false;
+
+        if self.storage.is_stale()? {
+            self.storage.load()?;
+            reloaded = true;
+        }
+
+        for storage in self.reportee_storages.values_mut() {
+            if storage.is_stale().unwrap_or(false) {
+                let _ = storage.load();
+                reloaded = true;
+            }
+        }
+
+        if reloaded {
+            // e.g. `twig archive` moved tasks out of a store
+            self.archive_loaded_since = None;
+            self.rebuild_visible_task_list();
+            self.clamp_selection();
+        }
+        Ok(())
+    }
+
+    /// Undo the most recent change across my tasks and all reportees' tasks.
+    pub fn undo(&mut self) -> Result<()> {
+        let message = match self.latest_journal_owner(Journal::undo_target)? {
+            Some(owner) => match undo_linked(self.stores_from(&owner))? {
+                Some(summary) => format!("Undid: {}", summary),
+                None => "Nothing to undo".to_string(),
+            },
+            None => "Nothing to undo".to_string(),
+        };
+        self.status_message = Some(message);
+        self.rebuild_visible_task_list();
+        self.clamp_selection();
+        Ok(())
+    }
+
+    /// Redo the most recently undone change across all stores.
+    pub fn redo(&mut self) -> Result<()> {
+        let message = match self.latest_journal_owner(Journal::redo_target)? {
+            Some(owner) => match redo_linked(self.stores_from(&owner))? {
+                Some(summary) => format!("Redid: {}", summary),
+                None => "Nothing to redo".to_string(),
+            },
+            None => "Nothing to redo".to_string(),
+        };
+        self.status_message = Some(message);
+        self.rebuild_visible_task_list();
+        self.clamp_selection();
+        Ok(())
+    }
+
+    /// Every store by owner, starting with `owner`'s.
+    fn stores_from(&mut self, owner: &str) -> Vec<(&str, &mut dyn TaskStore)> {
+        let mut stores: Vec<(&str, &mut dyn TaskStore)> = vec![("me", self.storage.as_mut())];
+        for (name, storage) in &mut self.reportee_storages {
+            stores.push((name.as_str(), storage.as_mut()));
+        }
+        if let Some(first) = stores.iter().position(|(name, _)| *name == owner) {
+            stores.swap(0, first);
+        }
+        stores
+    }
+
+    /// The owner whose journal has the most recent candidate according to `target`.
+    fn latest_journal_owner(
+        &self,
+        target: impl Fn(&Journal) -> Result<Option<TimedMutation>>,
+    ) -> Result<Option<String>> {
+        let mut latest: Option<(String, chrono::DateTime<chrono::Utc>)> = None;
+
+        let stores = std::iter::once(("me", &self.storage)).chain(
+            self.reportee_storages
+                .iter()
+                .map(|(name, storage)| (name.as_str(), storage)),
+        );
+        for (owner, storage) in stores {
+            if let Some((_, at)) = target(storage.journal())? {
+                if latest.as_ref().is_none_or(|(_, latest_at)| at > *latest_at) {
+                    latest = Some((owner.to_string(), at));
+                }
+            }
+        }
+
+        Ok(latest.map(|(owner, _)| owner))
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected_index = self
+            .selected_index
+            .min(self.visible_task_list.len().saturating_sub(1));
+    }
+
     pub fn input_char(&mut self, c: char) {
         let field = match self.input_state.current_field {
             0 => &mut self.input_state.title,
@@ -880,19 +1337,16 @@ impl App {
     }
 
     pub fn history_goto_today(&mut self) {
-        self.history_date = chrono::Local::now().date_naive();
+        self.history_date = self.today();
         self.rebuild_visible_task_list();
     }
 
     pub fn get_history_period_label(&self) -> String {
-        use chrono::Datelike;
-
         match self.history_period {
             HistoryPeriod::Day => self.history_date.format("%A, %B %d, %Y").to_string(),
             HistoryPeriod::Week => {
-                let days_from_monday = self.history_date.weekday().num_days_from_monday();
-                let start = self.history_date - chrono::Duration::days(days_from_monday as i64);
-                let end = start + chrono::Duration::days(6);
+                let week = DateRange::week_of(self.history_date, self.config.week_start);
+                let (start, end) = (week.first_day(), week.last_day());
                 format!(
                     "Week of {} - {}",
                     start.format("%b %d"),
@@ -939,9 +1393,18 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
     loop {
         terminal.draw(|f| ui::draw(f, app))?;
 
+        if !event::poll(STALE_CHECK_INTERVAL)? {
+            // Idle: pick up changes made by other twig processes
+            if matches!(app.mode, AppMode::Normal) {
+                app.reload_if_stale()?;
+            }
+            continue;
+        }
+
         if let Event::Key(key) = event::read()? {
             match app.mode {
                 AppMode::Normal => {
+                    app.status_message = None;
                     match key.code {
                         KeyCode::Char('q') => {
                             app.should_quit = true;
@@ -955,52 +1418,59 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
                         KeyCode::Char('k') | KeyCode::Up => {
                             app.move_selection_up();
                         }
-                        KeyCode::Char('s') => {
+                        KeyCode::Char('s') if !matches!(app.view_tab, ViewTab::Trash) => {
                             app.start_selected_task()?;
                         }
-                        KeyCode::Char('c') => {
+                        KeyCode::Char('c') if !matches!(app.view_tab, ViewTab::Trash) => {
                             app.complete_selected_task()?;
                         }
-                        KeyCode::Char('x') => {
+                        KeyCode::Char('x') if !matches!(app.view_tab, ViewTab::Trash) => {
                             app.cancel_selected_task()?;
                         }
-                        KeyCode::Char('p') => {
+                        KeyCode::Char('p') if !matches!(app.view_tab, ViewTab::Trash) => {
                             app.pause_selected_task()?;
                         }
+                        KeyCode::Char('R') if matches!(app.view_tab, ViewTab::Trash) => {
+                            app.restore_selected_task()?;
+                        }
                         KeyCode::Char('h') => {
                             app.toggle_completed();
                         }
                         KeyCode::Char('H') => {
                             app.toggle_cancelled();
                         }
+                        KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.redo()?;
+                        }
                         KeyCode::Char('r') => {
                             app.reload()?;
                         }
+                        KeyCode::Char('u') => {
+                            app.undo()?;
+                        }
                         KeyCode::Char('?') => {
                             app.mode = AppMode::Help;
                         }
                         KeyCode::Enter | KeyCode::Char(' ') | KeyCode::Tab => {
                             app.toggle_expand();
                         }
-                        KeyCode::Char('a') => {
-                            if !matches!(app.view_tab, ViewTab::History) {
-                                app.start_add_task(true); // Add as subtask
-                            }
+                        KeyCode::Char('a') if app.is_task_view() => {
+                            app.start_add_task(true); // Add as subtask
                         }
-                        KeyCode::Char('A') => {
-                            if !matches!(app.view_tab, ViewTab::History) {
-                                app.start_add_task(false); // Add as top-level task
-                            }
+                        KeyCode::Char('A') if app.is_task_view() => {
+                            app.start_add_task(false); // Add as top-level task
                         }
-                        KeyCode::Char('e') => {
-                            if !matches!(app.view_tab, ViewTab::History) {
-                                app.start_edit_task();
-                            }
+                        KeyCode::Char('e') if app.is_task_view() => {
+                            app.start_edit_task();
                         }
-                        KeyCode::Char('d') => {
-                            if !matches!(app.view_tab, ViewTab::History) {
-                                app.start_delete_task();
-                            }
+                        KeyCode::Char('d') if app.is_task_view() => {
+                            app.start_delete_task();
+                        }
+                        KeyCode::Char('D') if app.is_task_view() => {
+                            app.start_delegate_task();
+                        }
+                        KeyCode::Char('/') if !matches!(app.view_tab, ViewTab::Trash) => {
+                            app.start_filter();
                         }
                         KeyCode::Char('m') => {
                             if matches!(app.view_tab, ViewTab::History) {
@@ -1037,7 +1507,14 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
                             }
                         }
                         KeyCode::Char('3') => {
-                            app.switch_to_tab(3);
+                            if app.reportees.is_empty() {
+                                app.switch_to_tab(4); // Trash is tab 3 without reportees
+                            } else {
+                                app.switch_to_tab(3);
+                            }
+                        }
+                        KeyCode::Char('4') if !app.reportees.is_empty() => {
+                            app.switch_to_tab(4);
                         }
                         _ => {}
                     }
@@ -1051,14 +1528,60 @@ fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut A
                     }
                 }
                 AppMode::DeleteConfirm => match key.code {
-                    KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y') => {
-                        let _ = app.confirm_delete_task();
+                    // Tasks with subtasks need an explicit choice of what happens to them
+                    KeyCode::Enter | KeyCode::Char('y') | KeyCode::Char('Y')
+                        if app.delete_target_subtask_count() == 0 =>
+                    {
+                        let _ = app.confirm_delete_task(DeleteMode::Abort);
+                    }
+                    KeyCode::Char('a') | KeyCode::Char('A') => {
+                        let _ = app.confirm_delete_task(DeleteMode::Cascade);
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('P') => {
+                        let _ = app.confirm_delete_task(DeleteMode::Promote);
                     }
                     KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
                         app.cancel_input();
                     }
                     _ => {}
                 },
+                AppMode::Delegate => match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => {
+                        app.delegate_move_selection(true);
+                    }
+                    KeyCode::Char('k') | KeyCode::Up => {
+                        app.delegate_move_selection(false);
+                    }
+                    KeyCode::Char('s') | KeyCode::Char(' ') => {
+                        app.delegate_toggle_stub();
+                    }
+                    KeyCode::Enter => {
+                        app.confirm_delegate_task()?;
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') => {
+                        app.delegate_state = None;
+                        app.mode = AppMode::Normal;
+                    }
+                    _ => {}
+                },
+                AppMode::Filter => {
+                    app.status_message = None;
+                    match key.code {
+                        KeyCode::Enter => {
+                            app.apply_filter();
+                        }
+                        KeyCode::Esc => {
+                            app.mode = AppMode::Normal;
+                        }
+                        KeyCode::Backspace => {
+                            app.filter_input.pop();
+                        }
+                        KeyCode::Char(c) => {
+                            app.filter_input.push(c);
+                        }
+                        _ => {}
+                    }
+                }
                 AppMode::AddTask | AppMode::EditTask => {
                     match key.code {
                         KeyCode::Esc => {
@@ -27,6 +27,10 @@ pub fn draw(f: &mut Frame, app: &App) {
             draw_main_view(f, app);
             draw_delete_confirm_dialog(f, app);
         }
+        AppMode::Delegate => {
+            draw_main_view(f, app);
+            draw_delegate_dialog(f, app);
+        }
         _ => {
             draw_main_view(f, app);
         }
@@ -60,7 +64,7 @@ fn draw_main_view(f: &mut Frame, app: &App) {
 }
 
 fn draw_header(f: &mut Frame, area: Rect, app: &App) {
-    let filters = vec![
+    let filters = [
         if app.show_completed {
             "✓ Completed"
         } else {
@@ -122,8 +126,30 @@ fn draw_header(f: &mut Frame, area: Rect, app: &App) {
         tab_spans.push(Span::styled("History", Style::default()));
     }
 
+    // Trash tab
+    let trash_key = if app.reportees.is_empty() {
+        "[3]"
+    } else {
+        "[4]"
+    };
+    tab_spans.push(Span::raw(format!("  {} ", trash_key)));
+    if matches!(app.view_tab, ViewTab::Trash) {
+        tab_spans.push(Span::styled(
+            "Trash",
+            Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ));
+    } else {
+        tab_spans.push(Span::styled("Trash", Style::default()));
+    }
+
     tab_spans.push(Span::styled(
-        "  (Tab or 1-3)",
+        if app.reportees.is_empty() {
+            "  (Tab or 1-3)"
+        } else {
+            "  (Tab or 1-4)"
+        },
         Style::default().fg(Color::DarkGray),
     ));
 
@@ -195,12 +221,18 @@ fn draw_task_list(f: &mut Frame, area: Rect, app: &App) {
 
                     // Time tracking status - make it very visible
                     let (time_info, time_color) = if task.has_active_time_entry() {
-                        (format!(" ⏱TRACKING"), Some(Color::Yellow))
+                        (" ⏱TRACKING".to_string(), Some(Color::Yellow))
                     } else if task.status == TaskStatus::InProgress && task.total_time_seconds > 0 {
                         // In progress but not actively tracking = paused
-                        (format!(" ⏸PAUSED"), Some(Color::DarkGray))
+                        (" ⏸PAUSED".to_string(), Some(Color::DarkGray))
                     } else if task.total_time_seconds > 0 {
-                        (format!(" [{}]", task.get_formatted_total_time()), None)
+                        (
+                            format!(
+                                " [{}]",
+                                task.get_formatted_total_time(&app.work_calendar.for_owner(owner))
+                            ),
+                            None,
+                        )
                     } else {
                         (String::new(), None)
                     };
@@ -208,13 +240,18 @@ fn draw_task_list(f: &mut Frame, area: Rect, app: &App) {
                     // Indentation for tree structure
                     let indent = "  ".repeat(*depth);
 
+                    let delegated = match &task.delegated_to {
+                        Some(link) => format!(" → {}", link.owner),
+                        None => String::new(),
+                    };
                     let base_content = format!(
-                        "{}{}{} {} [{}]",
+                        "{}{}{} {}{} [{}]",
                         indent,
                         expand_indicator,
                         status_icon,
                         task.title,
-                        task.short_id()
+                        delegated,
+                        app.display_id(task, owner)
                     );
 
                     // Build the line with styled time tracking info
@@ -266,6 +303,15 @@ fn draw_task_list(f: &mut Frame, area: Rect, app: &App) {
                 visible_items.len()
             )
         }
+        ViewTab::Trash => format!(
+            "🗑 Trash ({}/{})",
+            if visible_items.is_empty() {
+                0
+            } else {
+                app.selected_index + 1
+            },
+            visible_items.len()
+        ),
         _ => format!(
             "Task Tree ({}/{})",
             if visible_items.is_empty() {
@@ -277,6 +323,11 @@ fn draw_task_list(f: &mut Frame, area: Rect, app: &App) {
         ),
     };
 
+    let title = if app.filter.is_empty() || matches!(app.view_tab, ViewTab::Trash) {
+        title
+    } else {
+        format!("{} — filter: {}", title, app.filter.as_str())
+    };
     let list = List::new(items).block(Block::default().title(title).borders(Borders::ALL));
 
     f.render_widget(list, area);
@@ -284,6 +335,7 @@ fn draw_task_list(f: &mut Frame, area: Rect, app: &App) {
 
 fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
     if let Some((task, owner)) = app.get_selected_task() {
+        let calendar = app.work_calendar.for_owner(owner);
         let mut lines = vec![
             Line::from(vec![
                 Span::styled("Title: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -291,7 +343,7 @@ fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
             ]),
             Line::from(vec![
                 Span::styled("ID: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(task.short_id()),
+                Span::raw(app.display_id(task, owner)),
             ]),
             Line::from(vec![
                 Span::styled("Status: ", Style::default().add_modifier(Modifier::BOLD)),
@@ -346,7 +398,7 @@ fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
             }
         }
 
-        if let Some(estimate) = task.get_formatted_estimate() {
+        if let Some(estimate) = task.get_formatted_estimate(&calendar) {
             lines.push(Line::from(vec![
                 Span::styled("Estimate: ", Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw(estimate),
@@ -359,7 +411,7 @@ fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
                     "Time Spent: ",
                     Style::default().add_modifier(Modifier::BOLD),
                 ),
-                Span::raw(task.get_formatted_total_time()),
+                Span::raw(task.get_formatted_total_time(&calendar)),
             ]));
         }
 
@@ -402,27 +454,47 @@ fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
         lines.push(Line::from(""));
         lines.push(Line::from(vec![
             Span::styled("Created: ", Style::default().add_modifier(Modifier::BOLD)),
-            Span::raw(format_datetime(&task.created_at)),
+            Span::raw(format_datetime(&task.created_at, app.timezone())),
         ]));
 
         if let Some(started) = task.started_at {
             lines.push(Line::from(vec![
                 Span::styled("Started: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format_datetime(&started)),
+                Span::raw(format_datetime(&started, app.timezone())),
             ]));
         }
 
         if let Some(completed) = task.completed_at {
             lines.push(Line::from(vec![
                 Span::styled("Completed: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format_datetime(&completed)),
+                Span::raw(format_datetime(&completed, app.timezone())),
+            ]));
+        }
+
+        if let Some(deleted) = task.deleted_at {
+            lines.push(Line::from(vec![
+                Span::styled("Deleted: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format_datetime(&deleted, app.timezone()),
+                    Style::default().fg(Color::Red),
+                ),
+            ]));
+        }
+
+        if let Some(link) = &task.delegated_to {
+            lines.push(Line::from(vec![
+                Span::styled("Delegated: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(
+                    format!("to {} (status follows their task)", link.owner),
+                    Style::default().fg(Color::Cyan),
+                ),
             ]));
         }
 
         if let Some(eta) = task.eta {
             lines.push(Line::from(vec![
                 Span::styled("ETA: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(format_datetime(&eta)),
+                Span::raw(format_datetime(&eta, app.timezone())),
             ]));
         }
 
@@ -461,21 +533,58 @@ fn draw_task_details(f: &mut Frame, area: Rect, app: &App) {
 }
 
 fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
+    if let (AppMode::Normal, Some(message)) = (&app.mode, &app.status_message) {
+        let footer = Paragraph::new(message.as_str())
+            .block(Block::default().borders(Borders::ALL))
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(footer, area);
+        return;
+    }
+
+    if let AppMode::Filter = app.mode {
+        let mut spans = vec![
+            Span::styled("/", Style::default().fg(Color::Cyan)),
+            Span::raw(format!("{}█", app.filter_input)),
+        ];
+        match &app.status_message {
+            Some(error) => spans.push(Span::styled(
+                format!("  {}", error),
+                Style::default().fg(Color::Red),
+            )),
+            None => spans.push(Span::styled(
+                "  Enter:Apply (empty clears) | ESC:Cancel",
+                Style::default().fg(Color::Gray),
+            )),
+        }
+        let footer = Paragraph::new(Line::from(spans))
+            .block(Block::default().title("Filter").borders(Borders::ALL));
+        f.render_widget(footer, area);
+        return;
+    }
+
     let help_text = match app.mode {
         AppMode::Normal => {
             match app.view_tab {
                 ViewTab::History => {
-                    "j/k:↓↑ | ←/→:Prev/Next period | m:Change period (D/W/M) | t:Go to today | Tab:Switch tab | ?:Help | q:Quit"
+                    "j/k:↓↑ | ←/→:Prev/Next period | m:Change period (D/W/M) | t:Go to today | /:Filter | Tab:Switch tab | ?:Help | q:Quit"
+                }
+                ViewTab::Trash => {
+                    "j/k:↓↑ | R:Restore | u:Undo | Tab:Switch tab | ?:Help | q:Quit"
                 }
                 _ => {
-                    "j/k:↓↑ | Tab/Enter:Expand | ←/→:Tabs | 1-3:Switch tab | s:Start | c:Complete | x:Cancel | p:Pause | a:Add subtask | A:Add top-level | e:Edit | d:Delete | ?:Help | q:Quit"
+                    "j/k:↓↑ | Tab/Enter:Expand | ←/→:Tabs | 1-3:Switch tab | s:Start | c:Complete | x:Cancel | p:Pause | u:Undo | Ctrl+r:Redo | a:Add subtask | A:Add top-level | e:Edit | d:Delete | D:Delegate | /:Filter | ?:Help | q:Quit"
                 }
             }
         }
         AppMode::Help => "Press ? or ESC to close help",
         AppMode::AddTask => "↑/↓/Tab:Navigate | Enter:Activate button or new line | Ctrl+Enter:Save | ESC:Cancel",
         AppMode::EditTask => "↑/↓/Tab:Navigate | Enter:Activate button or new line | Ctrl+Enter:Save | ESC:Cancel",
+        AppMode::DeleteConfirm if app.delete_target_subtask_count() > 0 => {
+            "a:Delete with subtasks | p:Delete and promote subtasks | ESC/n:Cancel"
+        }
         AppMode::DeleteConfirm => "Enter/y:Confirm Delete | ESC/n:Cancel",
+        AppMode::Filter => "",
+        AppMode::Delegate => "j/k:Choose | s/Space:Toggle stub | Enter:Move | ESC:Cancel",
     };
 
     let footer = Paragraph::new(help_text)
@@ -503,7 +612,9 @@ fn draw_help(f: &mut Frame) {
         Line::from("  k / ↑            - Move up"),
         Line::from("  Enter/Space/Tab  - Expand/collapse task (shows/hides subtasks)"),
         Line::from("  Tab              - Cycle through tabs"),
-        Line::from("  1-3              - Jump to specific tab (My Tasks / Reportees / History)"),
+        Line::from(
+            "  1-4              - Jump to specific tab (My Tasks / Reportees / History / Trash)",
+        ),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Task Management",
@@ -512,11 +623,14 @@ fn draw_help(f: &mut Frame) {
         Line::from("  a       - Add new task (as subtask of selected)"),
         Line::from("  A       - Add new task (as top-level, not a subtask)"),
         Line::from("  e       - Edit selected task"),
-        Line::from("  d       - Delete selected task (with confirmation)"),
+        Line::from("  d       - Delete selected task (asks what to do with subtasks)"),
+        Line::from("  D       - Delegate selected task and its subtasks to a reportee (or back)"),
         Line::from("  s - Start task (begins time tracking)"),
         Line::from("  c - Complete task (stops time tracking)"),
         Line::from("  x - Cancel task"),
         Line::from("  p - Pause time tracking (keeps task in progress)"),
+        Line::from("  u       - Undo last change (including deletes)"),
+        Line::from("  Ctrl+r  - Redo last undone change"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Filters",
@@ -524,6 +638,8 @@ fn draw_help(f: &mut Frame) {
         )]),
         Line::from("  h - Toggle show/hide completed"),
         Line::from("  H - Toggle show/hide cancelled"),
+        Line::from("  / - Filter tasks, e.g. tag:backend -status:cancelled eta.before:friday"),
+        Line::from("      (Enter on an empty filter clears it)"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "History View (Tab 3)",
@@ -533,6 +649,12 @@ fn draw_help(f: &mut Frame) {
         Line::from("  m       - Cycle period type (Day → Week → Month)"),
         Line::from("  t       - Jump to today"),
         Line::from(""),
+        Line::from(vec![Span::styled(
+            "Trash View",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::from("  R       - Restore selected task (and subtasks deleted with it)"),
+        Line::from(""),
         Line::from(vec![Span::styled(
             "Other",
             Style::default().add_modifier(Modifier::BOLD),
@@ -604,24 +726,27 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 fn draw_delete_confirm_dialog(f: &mut Frame, app: &App) {
     if let Some(task_id) = app.editing_task_id {
         if let Some((task, owner)) = app.get_task_by_id_with_owner(task_id) {
-            let area = centered_rect(60, 30, f.area());
+            let area = centered_rect(60, 40, f.area());
 
             // Clear background
             f.render_widget(ratatui::widgets::Clear, area);
 
             let storage = app.get_storage_for_owner(owner);
-            let children = storage.get_children(task_id);
-            let has_subtasks = !children.is_empty();
+            let subtask_count = app.delete_target_subtask_count();
+            let has_subtasks = subtask_count > 0;
 
             let warning_text = if has_subtasks {
+                let promote_target = match task.parent_id.and_then(|id| storage.get_task(id)) {
+                    Some(parent) => format!("under \"{}\"", parent.title),
+                    None => "to the top level".to_string(),
+                };
                 format!(
-                    "Delete task \"{}\"?\n\n⚠ WARNING: This task has {} subtask(s)!\nDeleting it will NOT delete the subtasks,\nbut they will become orphaned.\n\nAre you sure?",
-                    task.title,
-                    children.len()
+                    "Delete task \"{}\"?\n\n⚠ This task has {} subtask(s).\n(a) Delete them too\n(p) Move them {}\n\nIt can be restored from the Trash tab.",
+                    task.title, subtask_count, promote_target
                 )
             } else {
                 format!(
-                    "Delete task \"{}\"?\n\nThis action cannot be undone.",
+                    "Delete task \"{}\"?\n\nIt can be restored from the Trash tab.",
                     task.title
                 )
             };
@@ -654,27 +779,105 @@ fn draw_delete_confirm_dialog(f: &mut Frame, app: &App) {
             f.render_widget(warning, chunks[0]);
 
             // Buttons
+            let labels: &[&str] = if has_subtasks {
+                &[
+                    "[ Delete all ] (a)",
+                    "[ Promote ] (p)",
+                    "[ Cancel ] (ESC/n)",
+                ]
+            } else {
+                &["[ Delete ] (Enter/y)", "[ Cancel ] (ESC/n)"]
+            };
             let button_chunks = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .constraints(vec![
+                    Constraint::Ratio(1, labels.len() as u32);
+                    labels.len()
+                ])
                 .split(chunks[1]);
 
-            let delete_button = Paragraph::new("[ Delete ] (Enter/y)")
-                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
-                .alignment(ratatui::layout::Alignment::Center)
-                .block(Block::default().borders(Borders::ALL));
-
-            let cancel_button = Paragraph::new("[ Cancel ] (ESC/n)")
-                .style(Style::default().fg(Color::Green))
-                .alignment(ratatui::layout::Alignment::Center)
-                .block(Block::default().borders(Borders::ALL));
-
-            f.render_widget(delete_button, button_chunks[0]);
-            f.render_widget(cancel_button, button_chunks[1]);
+            for (i, label) in labels.iter().enumerate() {
+                let style = if i + 1 == labels.len() {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                };
+                let button = Paragraph::new(*label)
+                    .style(style)
+                    .alignment(ratatui::layout::Alignment::Center)
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(button, button_chunks[i]);
+            }
         }
     }
 }
 
+fn draw_delegate_dialog(f: &mut Frame, app: &App) {
+    let Some(state) = &app.delegate_state else {
+        return;
+    };
+    let Some((task, _)) = app.get_task_by_id_with_owner(state.task_id) else {
+        return;
+    };
+
+    let area = centered_rect(60, 50, f.area());
+    f.render_widget(ratatui::widgets::Clear, area);
+
+    let block = Block::default()
+        .title("Delegate Task")
+        .borders(Borders::ALL)
+        .style(Style::default().bg(Color::Black));
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Length(2), // Task title
+            Constraint::Min(3),    // Targets
+            Constraint::Length(2), // Stub option
+        ])
+        .split(area);
+
+    let title = Paragraph::new(format!("Move \"{}\" and its subtasks to:", task.title))
+        .wrap(Wrap { trim: true });
+    f.render_widget(title, chunks[0]);
+
+    let items: Vec<ListItem> = state
+        .targets
+        .iter()
+        .enumerate()
+        .map(|(i, target)| {
+            let label = if target == "me" {
+                "Me (take it back)".to_string()
+            } else {
+                target.clone()
+            };
+            let style = if i == state.selected {
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            ListItem::new(format!("  {}", label)).style(style)
+        })
+        .collect();
+    f.render_widget(
+        List::new(items).block(Block::default().borders(Borders::ALL)),
+        chunks[1],
+    );
+
+    let checkbox = if state.keep_stub { "[x]" } else { "[ ]" };
+    let stub = Paragraph::new(format!(
+        "{} Leave a stub that follows its status (s)",
+        checkbox
+    ))
+    .style(Style::default().fg(Color::Cyan));
+    f.render_widget(stub, chunks[2]);
+}
+
 fn draw_add_task_dialog(f: &mut Frame, app: &App) {
     let area = centered_rect(80, 75, f.area());
 
@@ -705,7 +908,12 @@ fn draw_add_task_dialog(f: &mut Frame, app: &App) {
         ("Title*", &app.input_state.title, 0, 0),
         ("Description", &app.input_state.description, 1, 1),
         ("Tags (comma-separated)", &app.input_state.tags, 2, 2),
-        ("Estimate (1h/2d/3w/2m)", &app.input_state.estimate, 3, 3),
+        (
+            "Estimate (1h30m/2d/3w/2mo)",
+            &app.input_state.estimate,
+            3,
+            3,
+        ),
     ];
 
     for (label, value, field_idx, chunk_idx) in single_line_fields.iter() {
@@ -827,7 +1035,12 @@ fn draw_edit_task_dialog(f: &mut Frame, app: &App) {
         ("Title*", &app.input_state.title, 0, 0),
         ("Description", &app.input_state.description, 1, 1),
         ("Tags (comma-separated)", &app.input_state.tags, 2, 2),
-        ("Estimate (1h/2d/3w/2m)", &app.input_state.estimate, 3, 3),
+        (
+            "Estimate (1h30m/2d/3w/2mo)",
+            &app.input_state.estimate,
+            3,
+            3,
+        ),
     ];
 
     for (label, value, field_idx, chunk_idx) in single_line_fields.iter() {
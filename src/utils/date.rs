@@ -1,153 +1,573 @@
+use crate::models::task::EffortEstimate;
+use crate::models::{Config, Timezone, WorkCalendar};
 use anyhow::{Context, Result};
-use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use chrono::{
+    DateTime, Datelike, Days, Duration, FixedOffset, Local, MappedLocalTime, Months, NaiveDate,
+    NaiveDateTime, NaiveTime, Offset, TimeZone, Utc, Weekday,
+};
+use std::fmt;
 
-pub fn parse_date(input: &str) -> Result<DateTime<Utc>> {
+/// Parse a date, optionally with a time of day, relative to now in the
+/// configured timezone, with weeks starting on the configured day. See
+/// `parse_date_from` for the forms understood.
+pub fn parse_date(input: &str, config: &Config) -> Result<DateTime<Utc>> {
+    let now = Utc::now().with_timezone(&config.timezone);
+    parse_date_from(input, now, config.week_start)
+}
+
+/// Where a date expression lands before any time of day is applied.
+enum Resolved<Tz: TimeZone> {
+    /// A named day ("friday", "2024-03-01", "end of month"): its start
+    Day(NaiveDate),
+    /// A moment relative to now ("today", "in 2 weeks", "+3h")
+    Moment(DateTime<Tz>),
+}
+
+/// Parse a date relative to `now`, in `now`'s timezone, with weeks starting on
+/// `week_start`. Understands:
+///
+/// - `now`, and `today`, `tomorrow` and `yesterday`, which keep the current time
+/// - `YYYY-MM-DD`, `end of week|month|year` and `start of week|month|year`
+/// - weekday names (the next one, today included), `next friday` (the first
+///   after today) and `last friday` (the last before today)
+/// - offsets from now: `+3d`, `-2w`, `+1mo`, `+4h`, `in 2 weeks`, `3 days ago`
+///   (units: min, h, d, w, mo, y)
+///
+/// Named days start at midnight. Any form can be followed by a time of day
+/// (`14:00`, `9am`, `5:30pm`, `noon`, optionally after `at`), and a time on its
+/// own means today.
+pub fn parse_date_from<Tz: TimeZone>(
+    input: &str,
+    now: DateTime<Tz>,
+    week_start: Weekday,
+) -> Result<DateTime<Utc>> {
     let input = input.trim().to_lowercase();
+    let mut words: Vec<&str> = input.split_whitespace().collect();
 
-    match input.as_str() {
-        "today" => {
-            let local = Local::now();
-            Ok(local.with_timezone(&Utc))
-        }
-        "yesterday" => {
-            let local = Local::now() - Duration::days(1);
-            Ok(local.with_timezone(&Utc))
-        }
-        "tomorrow" => {
-            let local = Local::now() + Duration::days(1);
-            Ok(local.with_timezone(&Utc))
-        }
-        _ => {
-            // Try parsing as ISO date (YYYY-MM-DD)
-            let naive = NaiveDate::parse_from_str(&input, "%Y-%m-%d").context(
-                "Invalid date format. Use YYYY-MM-DD or 'today', 'yesterday', 'tomorrow'",
-            )?;
-            let dt = Local
-                .from_local_datetime(&naive.and_hms_opt(0, 0, 0).unwrap())
-                .unwrap();
-            Ok(dt.with_timezone(&Utc))
+    // A trailing time of day, possibly split like "5 pm"
+    let mut time = None;
+    if let [.., number, meridiem @ ("am" | "pm")] = words.as_slice() {
+        time = parse_time_of_day(&format!("{}{}", number, meridiem));
+        if time.is_some() {
+            words.truncate(words.len() - 2);
+        }
+    }
+    if time.is_none() {
+        if let Some(last) = words.last() {
+            time = parse_time_of_day(last);
+            if time.is_some() {
+                words.pop();
+            }
+        }
+    }
+    if time.is_some() && words.last() == Some(&"at") {
+        words.pop();
+    }
+
+    if words.is_empty() && time.is_none() {
+        anyhow::bail!("Invalid date: empty");
+    }
+    let resolved = resolve_day(&words, &now, week_start).with_context(|| {
+        format!(
+            "Invalid date: '{}'. Use e.g. YYYY-MM-DD, today, friday, next monday, +3d, \
+             in 2 weeks or end of month, optionally followed by a time like 14:00",
+            input
+        )
+    })?;
+
+    let tz = now.timezone();
+    let local = match (resolved, time) {
+        (Resolved::Moment(moment), None) => return Ok(moment.with_timezone(&Utc)),
+        (Resolved::Moment(moment), Some(time)) => moment.date_naive().and_time(time),
+        (Resolved::Day(day), time) => day.and_time(time.unwrap_or(NaiveTime::MIN)),
+    };
+    from_local(&tz, local)
+        .map(|dt| dt.with_timezone(&Utc))
+        .with_context(|| format!("'{}' doesn't exist in the local timezone", input))
+}
+
+/// The moment a local date and time happened in `tz`: the earlier one when the
+/// clocks went back, or the first moment after a gap when they went forward.
+fn from_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>> {
+    tz.from_local_datetime(&local).earliest().or_else(|| {
+        (1..=24)
+            .filter_map(|quarters| local.checked_add_signed(Duration::minutes(15 * quarters)))
+            .find_map(|later| tz.from_local_datetime(&later).earliest())
+    })
+}
+
+fn resolve_day<Tz: TimeZone>(
+    words: &[&str],
+    now: &DateTime<Tz>,
+    week_start: Weekday,
+) -> Option<Resolved<Tz>> {
+    let today = now.date_naive();
+    let day = |date: NaiveDate| Some(Resolved::Day(date));
+
+    match words {
+        [] | ["now"] | ["today"] => Some(Resolved::Moment(now.clone())),
+        ["tomorrow"] => now
+            .clone()
+            .checked_add_days(Days::new(1))
+            .map(Resolved::Moment),
+        ["yesterday"] => now
+            .clone()
+            .checked_sub_days(Days::new(1))
+            .map(Resolved::Moment),
+        [weekday] | ["this", weekday] if weekday.parse::<Weekday>().is_ok() => {
+            let weekday = weekday.parse::<Weekday>().ok()?;
+            day(today + Duration::days(days_until(today.weekday(), weekday)))
+        }
+        ["next", weekday] => {
+            let weekday = weekday.parse::<Weekday>().ok()?;
+            let ahead = match days_until(today.weekday(), weekday) {
+                0 => 7,
+                ahead => ahead,
+            };
+            day(today + Duration::days(ahead))
+        }
+        ["last", weekday] => {
+            let weekday = weekday.parse::<Weekday>().ok()?;
+            let back = match days_until(weekday, today.weekday()) {
+                0 => 7,
+                back => back,
+            };
+            day(today - Duration::days(back))
+        }
+        ["end" | "start" | "beginning", "of", period]
+        | ["end" | "start" | "beginning", "of", "the", period] => {
+            let end = words[0] == "end";
+            let (first, last) = match *period {
+                "week" => {
+                    let first = today - Duration::days(days_until(week_start, today.weekday()));
+                    (first, first + Duration::days(6))
+                }
+                "month" => {
+                    let first = today.with_day(1)?;
+                    (first, first.checked_add_months(Months::new(1))?.pred_opt()?)
+                }
+                "year" => (
+                    NaiveDate::from_ymd_opt(today.year(), 1, 1)?,
+                    NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+                ),
+                _ => return None,
+            };
+            day(if end { last } else { first })
+        }
+        ["in", amount, unit] => offset(now, amount.parse().ok()?, unit),
+        [amount, unit, "ago"] => offset(now, amount.parse::<i64>().ok()?.checked_neg()?, unit),
+        [signed] | [signed, _] if signed.starts_with(['+', '-']) => {
+            let sign = if signed.starts_with('-') { -1 } else { 1 };
+            let rest = &signed[1..];
+            let split = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let amount: i64 = rest[..split].parse().ok()?;
+            let unit = match words {
+                [_, unit] if split == rest.len() => *unit,
+                [_] => &rest[split..],
+                _ => return None,
+            };
+            offset(now, sign * amount, unit)
         }
+        [date] => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .map(Resolved::Day),
+        _ => None,
     }
 }
 
+/// Days from `from` forward to the next `to`, 0 if they're the same day.
+fn days_until(from: Weekday, to: Weekday) -> i64 {
+    ((to.num_days_from_monday() + 7 - from.num_days_from_monday()) % 7) as i64
+}
+
+/// `now` moved by `amount` of `unit`. Days and longer keep the local time of day.
+fn offset<Tz: TimeZone>(now: &DateTime<Tz>, amount: i64, unit: &str) -> Option<Resolved<Tz>> {
+    let now = now.clone();
+    let by_days = |days: i64| {
+        let days = Days::new(days.unsigned_abs());
+        if amount >= 0 {
+            now.clone().checked_add_days(days)
+        } else {
+            now.clone().checked_sub_days(days)
+        }
+    };
+    let by_months = |months: i64| {
+        let months = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        if amount >= 0 {
+            now.clone().checked_add_months(months)
+        } else {
+            now.clone().checked_sub_months(months)
+        }
+    };
+    let by = |duration: Option<Duration>| now.clone().checked_add_signed(duration?);
+
+    let moment = match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => by(Duration::try_minutes(amount)),
+        "h" | "hr" | "hrs" | "hour" | "hours" => by(Duration::try_hours(amount)),
+        "d" | "day" | "days" => by_days(amount),
+        "w" | "wk" | "wks" | "week" | "weeks" => by_days(amount.checked_mul(7)?),
+        "mo" | "month" | "months" => by_months(amount),
+        "y" | "yr" | "yrs" | "year" | "years" => by_months(amount.checked_mul(12)?),
+        _ => None,
+    }?;
+    Some(Resolved::Moment(moment))
+}
+
+/// A time of day like "14:00", "9am", "5:30pm", "noon" or "midnight".
+fn parse_time_of_day(input: &str) -> Option<NaiveTime> {
+    match input {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+
+    let (clock, pm) = match (input.strip_suffix("am"), input.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(false)),
+        (_, Some(clock)) => (clock, Some(true)),
+        _ => (input, None),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        None if pm.is_some() => (clock.parse().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// The offset of a `Timezone` at some moment, which remembers its timezone so
+/// that date arithmetic keeps following the local clock.
+#[derive(Debug, Clone, Copy)]
+pub struct TimezoneOffset {
+    timezone: Timezone,
+    offset: FixedOffset,
+}
+
+impl Offset for TimezoneOffset {
+    fn fix(&self) -> FixedOffset {
+        self.offset
+    }
+}
+
+impl fmt::Display for TimezoneOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.offset.fmt(f)
+    }
+}
+
+impl TimeZone for Timezone {
+    type Offset = TimezoneOffset;
+
+    fn from_offset(offset: &TimezoneOffset) -> Self {
+        offset.timezone
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<TimezoneOffset> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> MappedLocalTime<TimezoneOffset> {
+        let offset = match self {
+            Timezone::Local => Local.offset_from_local_datetime(local),
+            Timezone::Named(zone) => zone.offset_from_local_datetime(local).map(|o| o.fix()),
+            Timezone::Fixed(offset) => offset.offset_from_local_datetime(local),
+        };
+        offset.map(|offset| TimezoneOffset {
+            timezone: *self,
+            offset,
+        })
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> TimezoneOffset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> TimezoneOffset {
+        let offset = match self {
+            Timezone::Local => Local.offset_from_utc_datetime(utc),
+            Timezone::Named(zone) => zone.offset_from_utc_datetime(utc).fix(),
+            Timezone::Fixed(offset) => offset.offset_from_utc_datetime(utc),
+        };
+        TimezoneOffset {
+            timezone: *self,
+            offset,
+        }
+    }
+}
+
+/// The moment `date` starts in `tz`.
+pub fn start_of_day<Tz: TimeZone>(tz: &Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    from_local(tz, midnight)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|| midnight.and_utc())
+}
+
+/// A period of the calendar: a day, week, month, quarter or year given by its
+/// first day, or any span of whole days.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DateRange {
-    Day(DateTime<Utc>),
-    Week(DateTime<Utc>),  // Start of week
-    Month(DateTime<Utc>), // Start of month
+    Day(NaiveDate),
+    Week(NaiveDate),
+    Month(NaiveDate),
+    Quarter(NaiveDate),
+    Year(NaiveDate),
+    /// From the first to the last day, both included
+    Span(NaiveDate, NaiveDate),
 }
 
 impl DateRange {
-    pub fn parse_day(input: &str) -> Result<Self> {
-        let input = input.trim().to_lowercase();
-        let date = match input.as_str() {
-            "today" => Local::now().with_timezone(&Utc),
-            "yesterday" => (Local::now() - Duration::days(1)).with_timezone(&Utc),
-            _ => parse_date(&input)?,
+    /// The week, starting on `week_start`, that `date` falls in.
+    pub fn week_of(date: NaiveDate, week_start: Weekday) -> Self {
+        let back = Days::new(days_until(week_start, date.weekday()) as u64);
+        DateRange::Week(date.checked_sub_days(back).unwrap_or(NaiveDate::MIN))
+    }
+
+    /// The month that `date` falls in.
+    pub fn month_of(date: NaiveDate) -> Self {
+        DateRange::Month(date.with_day(1).unwrap())
+    }
+
+    /// The quarter (January to March, April to June, ...) that `date` falls in.
+    pub fn quarter_of(date: NaiveDate) -> Self {
+        let month = date.month0() / 3 * 3 + 1;
+        DateRange::Quarter(NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap())
+    }
+
+    /// The year that `date` falls in.
+    pub fn year_of(date: NaiveDate) -> Self {
+        DateRange::Year(NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap())
+    }
+
+    /// The day `input` (see `parse_date_from`) falls on, in `now`'s timezone.
+    pub fn parse_day<Tz: TimeZone>(
+        input: &str,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        DateRange::Day(local_day(input, now, week_start)?).checked()
+    }
+
+    /// "this week", "last week" or the week of a date.
+    pub fn parse_week<Tz: TimeZone>(
+        input: &str,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        let today = now.date_naive();
+        let date = match input.trim().to_lowercase().as_str() {
+            "this week" | "week" => today,
+            "last week" => today - Duration::days(7),
+            input => local_day(input, now, week_start)?,
         };
-        Ok(DateRange::Day(date))
+        Self::week_of(date, week_start).checked()
     }
 
-    pub fn parse_week(input: &str) -> Result<Self> {
-        let input = input.trim().to_lowercase();
-        let date = match input.as_str() {
-            "this week" | "week" => {
-                let now = Local::now();
-                let days_from_monday = now.weekday().num_days_from_monday();
-                (now - Duration::days(days_from_monday as i64)).with_timezone(&Utc)
-            }
-            "last week" => {
-                let now = Local::now();
-                let days_from_monday = now.weekday().num_days_from_monday();
-                (now - Duration::days(days_from_monday as i64 + 7)).with_timezone(&Utc)
-            }
-            _ => {
-                // Parse specific date and get start of that week
-                let date = parse_date(&input)?;
-                let days_from_monday = date.weekday().num_days_from_monday();
-                date - Duration::days(days_from_monday as i64)
-            }
+    /// "this month", "last month" or the month of a date.
+    pub fn parse_month<Tz: TimeZone>(
+        input: &str,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        let today = now.date_naive();
+        let date = match input.trim().to_lowercase().as_str() {
+            "this month" | "month" => today,
+            "last month" => today - Months::new(1),
+            input => local_day(input, now, week_start)?,
         };
-        Ok(DateRange::Week(date))
+        Self::month_of(date).checked()
     }
 
-    pub fn parse_month(input: &str) -> Result<Self> {
-        let input = input.trim().to_lowercase();
-        let date = match input.as_str() {
-            "this month" | "month" => {
-                let now = Local::now();
-                let first_of_month = now.with_day(1).unwrap();
-                first_of_month.with_timezone(&Utc)
-            }
-            "last month" => {
-                let now = Local::now();
-                let first_of_month = now.with_day(1).unwrap();
-                let last_month = first_of_month - Duration::days(1);
-                last_month.with_day(1).unwrap().with_timezone(&Utc)
-            }
-            _ => {
-                // Parse specific date and get start of that month
-                let date = parse_date(&input)?;
-                date.with_day(1).unwrap()
-            }
+    /// "this quarter", "last quarter" or the quarter of a date.
+    pub fn parse_quarter<Tz: TimeZone>(
+        input: &str,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        let today = now.date_naive();
+        let date = match input.trim().to_lowercase().as_str() {
+            "this quarter" | "quarter" => today,
+            "last quarter" => today - Months::new(3),
+            input => local_day(input, now, week_start)?,
+        };
+        Self::quarter_of(date).checked()
+    }
+
+    /// "this year", "last year", a year like "2024" or the year of a date.
+    pub fn parse_year<Tz: TimeZone>(
+        input: &str,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        let today = now.date_naive();
+        let date = match input.trim().to_lowercase().as_str() {
+            "this year" | "year" => today,
+            "last year" => today - Months::new(12),
+            input => match input.parse::<i32>() {
+                Ok(year) => NaiveDate::from_ymd_opt(year, 1, 1)
+                    .with_context(|| format!("Invalid year: {}", year))?,
+                Err(_) => local_day(input, now, week_start)?,
+            },
         };
-        Ok(DateRange::Month(date))
+        Self::year_of(date).checked()
     }
 
-    pub fn start(&self) -> DateTime<Utc> {
+    /// The days from `from` to `to` (today without one), both included.
+    pub fn parse_span<Tz: TimeZone>(
+        from: &str,
+        to: Option<&str>,
+        now: &DateTime<Tz>,
+        week_start: Weekday,
+    ) -> Result<Self> {
+        let first = local_day(from, now, week_start)?;
+        let last = match to {
+            Some(to) => local_day(to, now, week_start)?,
+            None => now.date_naive(),
+        };
+        if last < first {
+            anyhow::bail!("The range ends ({}) before it starts ({})", last, first);
+        }
+        DateRange::Span(first, last).checked()
+    }
+
+    /// The last `days` days, today included.
+    pub fn last_days<Tz: TimeZone>(days: u32, now: &DateTime<Tz>) -> Result<Self> {
+        if days == 0 {
+            anyhow::bail!("The number of days must be at least 1");
+        }
+        let today = now.date_naive();
+        let first = today
+            .checked_sub_days(Days::new(days as u64 - 1))
+            .with_context(|| format!("Too many days: {}", days))?;
+        DateRange::Span(first, today).checked()
+    }
+
+    /// How the range is described in reports: "daily", ..., or "custom" for a span.
+    pub fn name(&self) -> &'static str {
         match self {
-            DateRange::Day(d) => d.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            DateRange::Week(d) => d.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(),
-            DateRange::Month(d) => d.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            DateRange::Day(_) => "daily",
+            DateRange::Week(_) => "weekly",
+            DateRange::Month(_) => "monthly",
+            DateRange::Quarter(_) => "quarterly",
+            DateRange::Year(_) => "yearly",
+            DateRange::Span(..) => "custom",
         }
     }
 
-    pub fn end(&self) -> DateTime<Utc> {
+    pub fn first_day(&self) -> NaiveDate {
         match self {
-            DateRange::Day(d) => (d.date_naive() + Duration::days(1))
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_utc(),
-            DateRange::Week(d) => (d.date_naive() + Duration::days(7))
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_utc(),
-            DateRange::Month(d) => {
-                let next_month = if d.month() == 12 {
-                    d.with_year(d.year() + 1).unwrap().with_month(1).unwrap()
-                } else {
-                    d.with_month(d.month() + 1).unwrap()
-                };
-                next_month
-                    .date_naive()
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap()
-                    .and_utc()
-            }
+            DateRange::Day(d)
+            | DateRange::Week(d)
+            | DateRange::Month(d)
+            | DateRange::Quarter(d)
+            | DateRange::Year(d)
+            | DateRange::Span(d, _) => *d,
+        }
+    }
+
+    pub fn last_day(&self) -> NaiveDate {
+        match self {
+            DateRange::Span(_, last) => *last,
+            _ => self
+                .next_first_day()
+                .and_then(|next| next.pred_opt())
+                .unwrap_or(NaiveDate::MAX),
+        }
+    }
+
+    /// The day after the range, `None` past the last date there is.
+    fn next_first_day(&self) -> Option<NaiveDate> {
+        match self {
+            DateRange::Day(d) => d.succ_opt(),
+            DateRange::Week(d) => d.checked_add_days(Days::new(7)),
+            DateRange::Month(d) => d.checked_add_months(Months::new(1)),
+            DateRange::Quarter(d) => d.checked_add_months(Months::new(3)),
+            DateRange::Year(d) => d.checked_add_months(Months::new(12)),
+            DateRange::Span(_, last) => last.succ_opt(),
+        }
+    }
+
+    /// The range, if it ends before the last date there is.
+    fn checked(self) -> Result<Self> {
+        match self.next_first_day() {
+            Some(_) => Ok(self),
+            None => anyhow::bail!("Date out of range: {}", self.first_day()),
         }
     }
+
+    /// The moment the range starts in `tz`.
+    pub fn start<Tz: TimeZone>(&self, tz: &Tz) -> DateTime<Utc> {
+        start_of_day(tz, self.first_day())
+    }
+
+    /// The moment the range ends (exclusive) in `tz`.
+    pub fn end<Tz: TimeZone>(&self, tz: &Tz) -> DateTime<Utc> {
+        start_of_day(tz, self.next_first_day().unwrap_or(NaiveDate::MAX))
+    }
 }
 
-pub fn format_datetime(dt: &DateTime<Utc>) -> String {
-    dt.with_timezone(&Local)
-        .format("%Y-%m-%d %H:%M")
-        .to_string()
+/// The date `input` falls on in `now`'s timezone.
+fn local_day<Tz: TimeZone>(
+    input: &str,
+    now: &DateTime<Tz>,
+    week_start: Weekday,
+) -> Result<NaiveDate> {
+    let date = parse_date_from(input, now.clone(), week_start)?;
+    Ok(date.with_timezone(&now.timezone()).date_naive())
 }
 
-pub fn format_date(dt: &DateTime<Utc>) -> String {
-    dt.with_timezone(&Local).format("%Y-%m-%d").to_string()
+/// Parse an age like "12h", "30d" or "2w" into a duration.
+pub fn parse_age(input: &str) -> Result<Duration> {
+    let input = input.trim().to_lowercase();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: i64 = value
+        .parse()
+        .with_context(|| format!("Invalid age: {}. Use e.g. 12h, 30d or 2w", input))?;
+
+    let age = match unit {
+        "h" => Duration::try_hours(value),
+        "d" => Duration::try_days(value),
+        "w" => Duration::try_weeks(value),
+        _ => anyhow::bail!("Invalid age unit: {}. Use h/d/w", unit),
+    };
+    age.with_context(|| format!("Age too long: {}", input))
 }
 
-pub fn format_duration_human(seconds: i64) -> String {
+/// Parse a duration like "1h30m", "90min" or "1.5d" (see `EffortEstimate`).
+pub fn parse_duration(input: &str, calendar: &WorkCalendar) -> Result<Duration> {
+    EffortEstimate::parse(input)?
+        .to_duration(calendar)
+        .with_context(|| format!("Duration too long: {}", input.trim()))
+}
+
+/// Format a moment like "2024-03-01 14:00" in `timezone`.
+pub fn format_datetime(dt: &DateTime<Utc>, timezone: &Timezone) -> String {
+    dt.with_timezone(timezone)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+/// Format a duration like "1d 2h 30m", with days as long as in `calendar`.
+pub fn format_duration_human(seconds: i64, calendar: &WorkCalendar) -> String {
     if seconds < 60 {
         return format!("{}s", seconds);
     }
 
     let minutes = seconds / 60;
-    let hours = minutes / 60;
-    let days = hours / 8; // 8-hour work days
+    let minutes_per_day = ((calendar.hours_per_day * 60.0).round() as i64).max(1);
+    let days = minutes / minutes_per_day;
 
-    let remaining_hours = hours % 8;
-    let remaining_minutes = minutes % 60;
+    let remaining_hours = minutes % minutes_per_day / 60;
+    let remaining_minutes = minutes % minutes_per_day % 60;
 
     let mut parts = Vec::new();
 
@@ -163,3 +583,369 @@ pub fn format_duration_human(seconds: i64) -> String {
 
     parts.join(" ")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    /// Friday 2024-03-01 23:30 at UTC-5, already Saturday 04:30 in UTC.
+    fn now() -> DateTime<FixedOffset> {
+        FixedOffset::west_opt(5 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 1, 23, 30, 0)
+            .unwrap()
+    }
+
+    /// `input` parsed at `now()`, shown in its timezone.
+    fn parse(input: &str) -> String {
+        parse_date_from(input, now(), Weekday::Mon)
+            .unwrap()
+            .with_timezone(&now().timezone())
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn test_relative_days_keep_the_time() {
+        assert_eq!(parse("now"), "2024-03-01 23:30");
+        assert_eq!(parse("today"), "2024-03-01 23:30");
+        assert_eq!(parse("Tomorrow"), "2024-03-02 23:30");
+        assert_eq!(parse("yesterday"), "2024-02-29 23:30");
+        assert_eq!(parse("+3d"), "2024-03-04 23:30");
+        assert_eq!(parse("+2 weeks"), "2024-03-15 23:30");
+        assert_eq!(parse("-1w"), "2024-02-23 23:30");
+        assert_eq!(parse("in 2 weeks"), "2024-03-15 23:30");
+        assert_eq!(parse("in 1 month"), "2024-04-01 23:30");
+        assert_eq!(parse("+1y"), "2025-03-01 23:30");
+        assert_eq!(parse("3 days ago"), "2024-02-27 23:30");
+        assert_eq!(parse("+4h"), "2024-03-02 03:30");
+        assert_eq!(parse("in 45 min"), "2024-03-02 00:15");
+    }
+
+    #[test]
+    fn test_named_days_start_at_midnight() {
+        assert_eq!(parse("2024-03-10"), "2024-03-10 00:00");
+        assert_eq!(parse("friday"), "2024-03-01 00:00");
+        assert_eq!(parse("this fri"), "2024-03-01 00:00");
+        assert_eq!(parse("monday"), "2024-03-04 00:00");
+        assert_eq!(parse("next friday"), "2024-03-08 00:00");
+        assert_eq!(parse("next monday"), "2024-03-04 00:00");
+        assert_eq!(parse("last friday"), "2024-02-23 00:00");
+        assert_eq!(parse("last thursday"), "2024-02-29 00:00");
+        assert_eq!(parse("end of week"), "2024-03-03 00:00");
+        assert_eq!(parse("start of week"), "2024-02-26 00:00");
+        assert_eq!(parse("end of month"), "2024-03-31 00:00");
+        assert_eq!(parse("beginning of the month"), "2024-03-01 00:00");
+        assert_eq!(parse("end of year"), "2024-12-31 00:00");
+    }
+
+    #[test]
+    fn test_times_of_day() {
+        assert_eq!(parse("14:00"), "2024-03-01 14:00");
+        assert_eq!(parse("9am"), "2024-03-01 09:00");
+        assert_eq!(parse("12am"), "2024-03-01 00:00");
+        assert_eq!(parse("noon"), "2024-03-01 12:00");
+        assert_eq!(parse("tomorrow 5pm"), "2024-03-02 17:00");
+        assert_eq!(parse("friday at 5:30 pm"), "2024-03-01 17:30");
+        assert_eq!(parse("yesterday 14:00"), "2024-02-29 14:00");
+        assert_eq!(parse("2024-03-10 09:30"), "2024-03-10 09:30");
+        assert_eq!(parse("end of month at midnight"), "2024-03-31 00:00");
+        assert_eq!(parse("+1d 8:00"), "2024-03-02 08:00");
+    }
+
+    #[test]
+    fn test_days_follow_the_local_timezone() {
+        // 23:30 local is already the next day in UTC, but "today" is still
+        // local Friday, and a local midnight is 05:00 UTC
+        let today = parse_date_from("today 00:00", now(), Weekday::Mon).unwrap();
+        assert_eq!(today.to_rfc3339(), "2024-03-01T05:00:00+00:00");
+        let friday = parse_date_from("friday", now(), Weekday::Mon).unwrap();
+        assert_eq!(friday, today);
+        let saturday = parse_date_from("saturday", now(), Weekday::Mon).unwrap();
+        assert_eq!(saturday.to_rfc3339(), "2024-03-02T05:00:00+00:00");
+
+        // Just after local midnight the day has changed locally, not in UTC
+        let early = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 2, 0, 10, 0)
+            .unwrap();
+        let today = parse_date_from("today midnight", early, Weekday::Mon).unwrap();
+        assert_eq!(today.to_rfc3339(), "2024-03-01T15:00:00+00:00");
+        let date = parse_date_from("2024-03-02", early, Weekday::Mon).unwrap();
+        assert_eq!(date, today);
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        for input in [
+            "",
+            "someday",
+            "next",
+            "next week",
+            "in 2 fortnights",
+            "+3",
+            "2024-02-30",
+            "friday 25:00",
+            "13pm",
+            "end of decade",
+            "+99999999999h",
+            "+9999999999999999h",
+            "+2000000000000000000w",
+            "+4294967297mo",
+            "-9223372036854775807y",
+            "9223372036854775807 minutes ago",
+        ] {
+            assert!(
+                parse_date_from(input, now(), Weekday::Mon).is_err(),
+                "{}",
+                input
+            );
+        }
+    }
+
+    /// New York in 2024: UTC-5, and UTC-4 from 2am on March 10 to 2am on
+    /// November 3.
+    #[derive(Debug, Clone, Copy)]
+    struct NewYork;
+
+    impl TimeZone for NewYork {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            NewYork
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            let (edt, est) = (
+                FixedOffset::west_opt(4 * 3600).unwrap(),
+                FixedOffset::west_opt(5 * 3600).unwrap(),
+            );
+            let fits =
+                |offset: FixedOffset| self.offset_from_utc_datetime(&(*local - offset)) == offset;
+            match (fits(edt), fits(est)) {
+                (true, true) => MappedLocalTime::Ambiguous(edt, est),
+                (true, false) => MappedLocalTime::Single(edt),
+                (false, true) => MappedLocalTime::Single(est),
+                (false, false) => MappedLocalTime::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let utc = utc.and_utc().to_rfc3339();
+            let dst = utc.as_str() >= "2024-03-10T07:00:00+00:00"
+                && utc.as_str() < "2024-11-03T06:00:00+00:00";
+            FixedOffset::west_opt(if dst { 4 } else { 5 } * 3600).unwrap()
+        }
+    }
+
+    fn date(input: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(input, "%Y-%m-%d").unwrap()
+    }
+
+    fn hours(range: DateRange, tz: &impl TimeZone) -> i64 {
+        (range.end(tz) - range.start(tz)).num_hours()
+    }
+
+    #[test]
+    fn test_ranges_start_at_local_midnight() {
+        // Late on Friday evening at UTC-5 it's already Saturday in UTC, but the
+        // daily report is still Friday's
+        let range = DateRange::parse_day("today", &now(), Weekday::Mon).unwrap();
+        assert_eq!(range, DateRange::Day(date("2024-03-01")));
+        assert_eq!(
+            range.start(&now().timezone()).to_rfc3339(),
+            "2024-03-01T05:00:00+00:00"
+        );
+        assert_eq!(
+            range.end(&now().timezone()).to_rfc3339(),
+            "2024-03-02T05:00:00+00:00"
+        );
+
+        let week = DateRange::parse_week("this week", &now(), Weekday::Mon).unwrap();
+        assert_eq!(
+            (week.first_day(), week.last_day()),
+            (date("2024-02-26"), date("2024-03-03"))
+        );
+        let month = DateRange::parse_month("last month", &now(), Weekday::Mon).unwrap();
+        assert_eq!(
+            (month.first_day(), month.last_day()),
+            (date("2024-02-01"), date("2024-02-29"))
+        );
+
+        let tokyo: Timezone = "+09:00".parse().unwrap();
+        let range = DateRange::Day(date("2024-03-01"));
+        assert_eq!(
+            range.start(&tokyo).to_rfc3339(),
+            "2024-02-29T15:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_custom_periods() {
+        let days = |range: DateRange| (range.first_day().to_string(), range.last_day().to_string());
+        let span = |first: &str, last: &str| (first.to_string(), last.to_string());
+
+        let sunday_weeks = DateRange::parse_week("last week", &now(), Weekday::Sun).unwrap();
+        assert_eq!(days(sunday_weeks), span("2024-02-18", "2024-02-24"));
+        let week = DateRange::week_of(date("2024-03-03"), Weekday::Sun);
+        assert_eq!(days(week), span("2024-03-03", "2024-03-09"));
+
+        let quarter = DateRange::parse_quarter("today", &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(quarter), span("2024-01-01", "2024-03-31"));
+        let quarter = DateRange::parse_quarter("last quarter", &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(quarter), span("2023-10-01", "2023-12-31"));
+        let quarter = DateRange::parse_quarter("2024-08-15", &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(quarter), span("2024-07-01", "2024-09-30"));
+
+        let year = DateRange::parse_year("last year", &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(year), span("2023-01-01", "2023-12-31"));
+        let year = DateRange::parse_year("2022", &now(), Weekday::Mon).unwrap();
+        assert_eq!(year.name(), "yearly");
+        assert_eq!(days(year), span("2022-01-01", "2022-12-31"));
+
+        let sprint =
+            DateRange::parse_span("2024-02-19", Some("last friday"), &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(sprint), span("2024-02-19", "2024-02-23"));
+        assert_eq!(sprint.name(), "custom");
+        let to_today = DateRange::parse_span("monday", None, &now(), Weekday::Mon);
+        assert!(to_today.is_err(), "next Monday is after today");
+        let to_today = DateRange::parse_span("last monday", None, &now(), Weekday::Mon).unwrap();
+        assert_eq!(days(to_today), span("2024-02-26", "2024-03-01"));
+        let tz = now().timezone();
+        assert_eq!(to_today.end(&tz).to_rfc3339(), "2024-03-02T05:00:00+00:00");
+
+        let fortnight = DateRange::last_days(14, &now()).unwrap();
+        assert_eq!(days(fortnight), span("2024-02-17", "2024-03-01"));
+        assert_eq!(
+            days(DateRange::last_days(1, &now()).unwrap()),
+            span("2024-03-01", "2024-03-01")
+        );
+        assert!(DateRange::last_days(0, &now()).is_err());
+    }
+
+    #[test]
+    fn test_ages() {
+        assert_eq!(parse_age("12h").unwrap(), Duration::hours(12));
+        assert_eq!(parse_age("2W").unwrap(), Duration::days(14));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("999999999999d").is_err());
+        assert!(parse_age("99999999999999h").is_err());
+        // Long enough to be a duration but not to subtract from now
+        let age = parse_age("99999999w").unwrap();
+        assert!(Utc::now().checked_sub_signed(age).is_none());
+    }
+
+    #[test]
+    fn test_ranges_past_the_calendar_are_errors() {
+        assert!(DateRange::last_days(4_000_000_000, &now()).is_err());
+        assert!(DateRange::parse_year("262142", &now(), Weekday::Mon).is_err());
+        assert!(DateRange::parse_span("+262000y", None, &now(), Weekday::Mon).is_err());
+        let last = DateRange::Year(NaiveDate::from_ymd_opt(262142, 1, 1).unwrap());
+        assert_eq!(last.last_day(), NaiveDate::MAX);
+    }
+
+    #[test]
+    fn test_weeks_follow_the_week_start() {
+        let parse = |input: &str| {
+            parse_date_from(input, now(), Weekday::Sun)
+                .unwrap()
+                .with_timezone(&now().timezone())
+                .date_naive()
+        };
+        assert_eq!(parse("start of week"), date("2024-02-25"));
+        assert_eq!(parse("end of week"), date("2024-03-02"));
+        let range = DateRange::parse_day("end of week", &now(), Weekday::Sat).unwrap();
+        assert_eq!(range, DateRange::Day(date("2024-03-01")));
+    }
+
+    #[test]
+    fn test_ranges_across_dst_transitions() {
+        // The day the clocks go forward is 23 hours long, the day they go back 25
+        let spring = DateRange::Day(date("2024-03-10"));
+        assert_eq!(
+            spring.start(&NewYork).to_rfc3339(),
+            "2024-03-10T05:00:00+00:00"
+        );
+        assert_eq!(
+            spring.end(&NewYork).to_rfc3339(),
+            "2024-03-11T04:00:00+00:00"
+        );
+        assert_eq!(hours(spring, &NewYork), 23);
+        assert_eq!(hours(DateRange::Day(date("2024-11-03")), &NewYork), 25);
+        assert_eq!(
+            hours(
+                DateRange::week_of(date("2024-03-10"), Weekday::Mon),
+                &NewYork
+            ),
+            7 * 24 - 1
+        );
+        assert_eq!(
+            hours(DateRange::month_of(date("2024-11-20")), &NewYork),
+            30 * 24 + 1
+        );
+
+        // Times that the change skips move past it; repeated ones take the first
+        let at = |input: &str| {
+            parse_date_from(
+                input,
+                NewYork.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+                Weekday::Mon,
+            )
+            .unwrap()
+            .to_rfc3339()
+        };
+        assert_eq!(at("2024-03-10 02:30"), "2024-03-10T07:00:00+00:00");
+        assert_eq!(at("2024-11-03 01:30"), "2024-11-03T05:30:00+00:00");
+        // Days later keeps the time of day across the change
+        assert_eq!(at("+10d"), "2024-03-11T16:00:00+00:00");
+    }
+
+    #[test]
+    fn test_timezone_config() {
+        assert_eq!("local".parse::<Timezone>(), Ok(Timezone::Local));
+        let fixed = |seconds| Ok(Timezone::Fixed(FixedOffset::east_opt(seconds).unwrap()));
+        assert_eq!("UTC".parse::<Timezone>(), fixed(0));
+        assert_eq!("+05:30".parse::<Timezone>(), fixed(5 * 3600 + 1800));
+        assert_eq!("-0800".parse::<Timezone>(), fixed(-8 * 3600));
+        assert_eq!("+9".parse::<Timezone>(), fixed(9 * 3600));
+        for invalid in ["", "Europe/Atlantis", "5", "+05:75", "+25:00"] {
+            assert!(invalid.parse::<Timezone>().is_err(), "{}", invalid);
+        }
+
+        // Named zones follow daylight saving time
+        let new_york: Timezone = "America/New_York".parse().unwrap();
+        assert_eq!(new_york, Timezone::Named(chrono_tz::America::New_York));
+        assert_eq!(hours(DateRange::Day(date("2024-03-10")), &new_york), 23);
+        assert_eq!(
+            DateRange::Day(date("2024-07-01"))
+                .start(&new_york)
+                .to_rfc3339(),
+            "2024-07-01T04:00:00+00:00"
+        );
+        assert_eq!(
+            serde_json::to_string(&new_york).unwrap(),
+            "\"America/New_York\""
+        );
+
+        let json =
+            serde_json::to_string(&Timezone::Fixed(FixedOffset::west_opt(3 * 3600).unwrap()))
+                .unwrap();
+        assert_eq!(json, "\"-03:00\"");
+        assert_eq!(
+            serde_json::from_str::<Timezone>(&json).unwrap(),
+            "-3".parse().unwrap()
+        );
+    }
+}
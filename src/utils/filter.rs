@@ -0,0 +1,507 @@
+use crate::models::task::EffortEstimate;
+use crate::models::{Config, Task, TaskStatus, Timezone, WorkCalendar};
+use crate::utils::parse_date;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// A task filter such as `status:in-progress tag:backend -tag:blocked
+/// eta.before:friday estimate.gt:4h title~"migration" parent:ab12cd34`, as taken
+/// by `list`, `tree`, `report`, `stats` and the TUI's `/` prompt.
+///
+/// A task must match every term. A leading `-` negates a term and comma-separated
+/// values match any of them (`status:not-started,in-progress`). A bare word
+/// matches titles containing it.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    query: String,
+    terms: Vec<Term>,
+    /// Where date terms' days start
+    timezone: Timezone,
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    negated: bool,
+    /// Matches if any of these do
+    any_of: Vec<Condition>,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Status(TaskStatus),
+    Tag(String),
+    TitleIs(String),
+    TitleContains(String),
+    DescriptionContains(String),
+    IdPrefix(String),
+    /// A parent ID prefix, or `None` for top-level tasks
+    Parent(Option<String>),
+    Date(DateField, DateOp, NaiveDate),
+    Estimate(NumberOp, EffortEstimate),
+    Time(NumberOp, EffortEstimate),
+    Is(State),
+    Has(Field),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DateField {
+    Eta,
+    Created,
+    Started,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DateOp {
+    Before,
+    On,
+    After,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum NumberOp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Open,
+    Done,
+    Tracking,
+    Delegated,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Eta,
+    Estimate,
+    Tags,
+    Description,
+    Notes,
+    Parent,
+}
+
+impl Filter {
+    /// Parse a query, with dates read as `config` says.
+    pub fn parse(query: &str, config: &Config) -> Result<Self> {
+        let terms = tokenize(query)?
+            .iter()
+            .map(|token| parse_term(token, config))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            query: query.trim().to_string(),
+            terms,
+            timezone: config.timezone,
+        })
+    }
+
+    /// Parse a filter given as command-line arguments, each one or more terms.
+    /// An argument whose quotes the shell already removed, like `title~two words`,
+    /// stays a single term, unless more terms follow in it
+    /// (`"tag:backend -tag:blocked"`).
+    pub fn from_args(args: &[String], config: &Config) -> Result<Self> {
+        let quoted: Vec<String> = args
+            .iter()
+            .map(|arg| {
+                if !arg.contains(char::is_whitespace) || arg.contains('"') {
+                    return arg.clone();
+                }
+                match arg.find([':', '~']) {
+                    Some(i) if has_terms(&arg[i + 1..]) => arg.clone(),
+                    Some(i) => format!("{}\"{}\"", &arg[..=i], &arg[i + 1..]),
+                    None => format!("\"{}\"", arg),
+                }
+            })
+            .collect();
+        Self::parse(&quoted.join(" "), config)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The query this filter was parsed from.
+    pub fn as_str(&self) -> &str {
+        &self.query
+    }
+
+    /// Whether a term picks tasks by status (or open/done), which should then
+    /// override views that hide finished tasks.
+    pub fn constrains_status(&self) -> bool {
+        self.terms.iter().flat_map(|t| &t.any_of).any(|c| {
+            matches!(
+                c,
+                Condition::Status(_)
+                    | Condition::Is(State::Open | State::Done)
+                    | Condition::Date(DateField::Completed | DateField::Cancelled, _, _)
+            )
+        })
+    }
+
+    /// Whether `task` matches, with efforts counted in its owner's `calendar`.
+    pub fn matches(&self, task: &Task, calendar: &WorkCalendar) -> bool {
+        self.terms.iter().all(|term| {
+            let matches = |c: &Condition| c.matches(task, calendar, &self.timezone);
+            term.any_of.iter().any(matches) != term.negated
+        })
+    }
+}
+
+impl Condition {
+    fn matches(&self, task: &Task, calendar: &WorkCalendar, timezone: &Timezone) -> bool {
+        match self {
+            Condition::Status(status) => task.status == *status,
+            Condition::Tag(tag) => task.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+            Condition::TitleIs(title) => task.title.to_lowercase() == *title,
+            Condition::TitleContains(text) => task.title.to_lowercase().contains(text),
+            Condition::DescriptionContains(text) => task.description.to_lowercase().contains(text),
+            Condition::IdPrefix(prefix) => task.id.to_string().starts_with(prefix),
+            Condition::Parent(None) => task.parent_id.is_none(),
+            Condition::Parent(Some(prefix)) => task
+                .parent_id
+                .is_some_and(|id| id.to_string().starts_with(prefix)),
+            Condition::Date(field, op, date) => {
+                let Some(value) = field.value(task) else {
+                    return false;
+                };
+                let day = value.with_timezone(timezone).date_naive();
+                match op {
+                    DateOp::Before => day < *date,
+                    DateOp::On => day == *date,
+                    DateOp::After => day > *date,
+                }
+            }
+            Condition::Estimate(op, effort) => task
+                .estimated_effort_hours
+                .is_some_and(|estimate| op.compare(estimate, effort.to_hours(calendar))),
+            Condition::Time(op, effort) => op.compare(
+                task.total_time_seconds as f64,
+                (effort.to_hours(calendar) * 3600.0).round(),
+            ),
+            Condition::Is(State::Open) => {
+                matches!(task.status, TaskStatus::NotStarted | TaskStatus::InProgress)
+            }
+            Condition::Is(State::Done) => {
+                matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled)
+            }
+            Condition::Is(State::Tracking) => task.has_active_time_entry(),
+            Condition::Is(State::Delegated) => task.is_delegated(),
+            Condition::Has(Field::Eta) => task.eta.is_some(),
+            Condition::Has(Field::Estimate) => task.estimated_effort_hours.is_some(),
+            Condition::Has(Field::Tags) => !task.tags.is_empty(),
+            Condition::Has(Field::Description) => !task.description.is_empty(),
+            Condition::Has(Field::Notes) => !task.notes.is_empty(),
+            Condition::Has(Field::Parent) => task.parent_id.is_some(),
+        }
+    }
+}
+
+impl DateField {
+    fn value(self, task: &Task) -> Option<DateTime<Utc>> {
+        match self {
+            DateField::Eta => task.eta,
+            DateField::Created => Some(task.created_at),
+            DateField::Started => task.started_at,
+            DateField::Completed => task.completed_at,
+            DateField::Cancelled => task.cancelled_at,
+        }
+    }
+}
+
+impl NumberOp {
+    fn compare(self, value: f64, limit: f64) -> bool {
+        match self {
+            NumberOp::Lt => value < limit,
+            NumberOp::Le => value <= limit,
+            NumberOp::Eq => (value - limit).abs() < 1e-9,
+            NumberOp::Ge => value >= limit,
+            NumberOp::Gt => value > limit,
+        }
+    }
+}
+
+/// Whether any word of `text` is a `key:value` or `key~value` term.
+fn has_terms(text: &str) -> bool {
+    text.split_whitespace().any(|word| {
+        let word = word.trim_start_matches('-');
+        word.find([':', '~'])
+            .is_some_and(|i| i > 0 && i + 1 < word.len())
+    })
+}
+
+/// Split a query on whitespace outside double quotes, dropping the quotes.
+fn tokenize(query: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() || quoted {
+                    tokens.push(std::mem::take(&mut current));
+                }
+                quoted = false;
+            }
+            c => current.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("Unclosed quote in filter: {}", query);
+    }
+    if !current.is_empty() || quoted {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(token: &str, config: &Config) -> Result<Term> {
+    let (negated, body) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+
+    // `key~value`, `key:value` or `key.op:value`; anything else is title text
+    let Some(split) = body.find([':', '~']) else {
+        return Ok(Term {
+            negated,
+            any_of: vec![Condition::TitleContains(body.to_lowercase())],
+        });
+    };
+    let (key, value) = (&body[..split], &body[split + 1..]);
+    let contains = body.as_bytes()[split] == b'~';
+    let (field, op) = match key.split_once('.') {
+        Some((field, op)) => (field.to_lowercase(), Some(op.to_lowercase())),
+        None => (key.to_lowercase(), None),
+    };
+    if value.is_empty() {
+        bail!("Missing value in filter term '{}'", token);
+    }
+
+    let any_of = value
+        .split(',')
+        .map(|value| parse_condition(&field, op.as_deref(), contains, value.trim(), config))
+        .collect::<Result<_>>()
+        .with_context(|| format!("Invalid filter term '{}'", token))?;
+    Ok(Term { negated, any_of })
+}
+
+fn parse_condition(
+    field: &str,
+    op: Option<&str>,
+    contains: bool,
+    value: &str,
+    config: &Config,
+) -> Result<Condition> {
+    if contains && !matches!(field, "title" | "description" | "desc") {
+        bail!("'~' only works with title and description");
+    }
+    if op.is_some() && !is_date_field(field) && !matches!(field, "estimate" | "time") {
+        bail!("'{}' takes no comparison", field);
+    }
+
+    let condition = match field {
+        "status" => Condition::Status(parse_status(value)?),
+        "tag" => Condition::Tag(value.trim_start_matches('#').to_string()),
+        "title" if contains => Condition::TitleContains(value.to_lowercase()),
+        "title" => Condition::TitleIs(value.to_lowercase()),
+        "description" | "desc" => Condition::DescriptionContains(value.to_lowercase()),
+        "id" => Condition::IdPrefix(value.to_lowercase()),
+        "parent" if matches!(value, "none" | "root") => Condition::Parent(None),
+        "parent" => Condition::Parent(Some(value.to_lowercase())),
+        "estimate" => Condition::Estimate(parse_number_op(op)?, parse_effort(value)?),
+        "time" => Condition::Time(parse_number_op(op)?, parse_effort(value)?),
+        "is" => Condition::Is(match value {
+            "open" => State::Open,
+            "done" | "finished" => State::Done,
+            "tracking" | "running" => State::Tracking,
+            "delegated" | "stub" => State::Delegated,
+            _ => bail!(
+                "Unknown state '{}'. Use open, done, tracking or delegated",
+                value
+            ),
+        }),
+        "has" => Condition::Has(match value {
+            "eta" => Field::Eta,
+            "estimate" => Field::Estimate,
+            "tags" | "tag" => Field::Tags,
+            "description" | "desc" => Field::Description,
+            "notes" => Field::Notes,
+            "parent" => Field::Parent,
+            _ => bail!(
+                "Unknown field '{}'. Use eta, estimate, tags, description, notes or parent",
+                value
+            ),
+        }),
+        field if is_date_field(field) => {
+            let date_field = match field {
+                "eta" | "due" => DateField::Eta,
+                "created" => DateField::Created,
+                "started" => DateField::Started,
+                "completed" | "done" => DateField::Completed,
+                _ => DateField::Cancelled,
+            };
+            let date_op = match op {
+                None | Some("on") => DateOp::On,
+                Some("before") => DateOp::Before,
+                Some("after") => DateOp::After,
+                Some(other) => bail!(
+                    "Unknown date comparison '{}'. Use before, on or after",
+                    other
+                ),
+            };
+            let date = parse_date(value, config)?
+                .with_timezone(&config.timezone)
+                .date_naive();
+            Condition::Date(date_field, date_op, date)
+        }
+        _ => bail!(
+            "Unknown filter field '{}'. Use status, tag, title, description, id, parent, \
+             eta, created, started, completed, cancelled, estimate, time, is or has",
+            field
+        ),
+    };
+    Ok(condition)
+}
+
+fn is_date_field(field: &str) -> bool {
+    matches!(
+        field,
+        "eta" | "due" | "created" | "started" | "completed" | "done" | "cancelled"
+    )
+}
+
+fn parse_status(value: &str) -> Result<TaskStatus> {
+    match value.to_lowercase().replace('_', "-").as_str() {
+        "not-started" | "todo" => Ok(TaskStatus::NotStarted),
+        "in-progress" | "started" => Ok(TaskStatus::InProgress),
+        "completed" | "done" => Ok(TaskStatus::Completed),
+        "cancelled" | "canceled" => Ok(TaskStatus::Cancelled),
+        _ => bail!(
+            "Unknown status '{}'. Use not-started, in-progress, completed or cancelled",
+            value
+        ),
+    }
+}
+
+fn parse_number_op(op: Option<&str>) -> Result<NumberOp> {
+    match op {
+        Some("lt") => Ok(NumberOp::Lt),
+        Some("le") | Some("lte") => Ok(NumberOp::Le),
+        None | Some("eq") => Ok(NumberOp::Eq),
+        Some("ge") | Some("gte") => Ok(NumberOp::Ge),
+        Some("gt") => Ok(NumberOp::Gt),
+        Some(other) => bail!("Unknown comparison '{}'. Use lt, le, eq, ge or gt", other),
+    }
+}
+
+/// An effort like "4h" or "2d", converted to hours when matching since days
+/// and weeks differ between owners.
+fn parse_effort(value: &str) -> Result<EffortEstimate> {
+    if value.is_empty() {
+        bail!("Missing duration");
+    }
+    EffortEstimate::parse(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(title: &str) -> Task {
+        Task::new(title.to_string())
+    }
+
+    fn parse(query: &str) -> Result<Filter> {
+        Filter::parse(query, &Config::default())
+    }
+
+    #[test]
+    fn test_parses_and_matches_terms() {
+        let calendar = WorkCalendar::default();
+        let mut migration = task("Database migration");
+        migration.start();
+        migration.tags = vec!["backend".to_string()];
+        migration.estimated_effort_hours = Some(8.0);
+        let mut blocked = migration.clone();
+        blocked.id = uuid::Uuid::new_v4();
+        blocked.tags.push("blocked".to_string());
+        let mut small = task("Fix typo");
+        small.parent_id = Some(migration.id);
+        small.estimated_effort_hours = Some(1.0);
+
+        let filter = parse(
+            r#"status:in-progress tag:backend -tag:blocked estimate.gt:4h title~"migration""#,
+        )
+        .unwrap();
+        assert!(filter.matches(&migration, &calendar));
+        assert!(!filter.matches(&blocked, &calendar));
+        assert!(!filter.matches(&small, &calendar));
+        assert!(filter.constrains_status());
+
+        let parent = format!("parent:{}", &migration.id.to_string()[..8]);
+        assert!(parse(&parent).unwrap().matches(&small, &calendar));
+        assert!(parse("parent:none").unwrap().matches(&migration, &calendar));
+        assert!(parse("status:not-started,in-progress typo")
+            .unwrap()
+            .matches(&small, &calendar));
+        assert!(parse("estimate.le:1h -has:eta")
+            .unwrap()
+            .matches(&small, &calendar));
+        assert!(parse("created:today created.after:2000-01-01")
+            .unwrap()
+            .matches(&small, &calendar));
+        assert!(!parse("created.before:today")
+            .unwrap()
+            .matches(&small, &calendar));
+        assert!(parse("").unwrap().is_empty());
+
+        let args = vec![
+            "tag:backend".to_string(),
+            "title~database migration".to_string(),
+        ];
+        assert!(Filter::from_args(&args, &Config::default())
+            .unwrap()
+            .matches(&migration, &calendar));
+
+        // A whole query passed as one argument still splits into its terms
+        for query in [
+            "tag:backend -tag:blocked",
+            "status:in-progress tag:backend",
+            "title~migration estimate.gt:4h",
+        ] {
+            let filter = Filter::from_args(&[query.to_string()], &Config::default()).unwrap();
+            assert!(filter.matches(&migration, &calendar), "{}", query);
+            assert!(!filter.matches(&small, &calendar), "{}", query);
+        }
+        let args = vec!["tag:backend -tag:blocked".to_string()];
+        assert!(!Filter::from_args(&args, &Config::default())
+            .unwrap()
+            .matches(&blocked, &calendar));
+    }
+
+    #[test]
+    fn test_rejects_bad_terms() {
+        for query in [
+            "colour:red",
+            "status:sleeping",
+            "tag:",
+            "estimate.gt:lots",
+            "eta.around:today",
+            "tag~back",
+            "title:\"open",
+        ] {
+            assert!(parse(query).is_err(), "{} should not parse", query);
+        }
+    }
+}
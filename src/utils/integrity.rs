@@ -0,0 +1,393 @@
+use crate::models::{Task, TaskStatus};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use uuid::Uuid;
+
+/// Something wrong with the tasks in a store, as found by `twig doctor`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// Another task in the same store has the same ID
+    DuplicateId,
+    /// The first 8 characters of the ID are shared with another task
+    ShortIdCollision { other: Uuid },
+    /// `parent_id` refers to a task that doesn't exist
+    Orphan { parent_id: Uuid },
+    /// A live task's parent is in the trash, which hides the task
+    ParentInTrash { parent_id: Uuid },
+    /// Following `parent_id` leads back to the task itself
+    ParentCycle,
+    /// A status timestamp disagrees with `status`
+    StatusMismatch { detail: &'static str },
+    /// More than one time entry is running
+    MultipleActiveEntries { count: usize },
+    /// A time entry's `duration_seconds` doesn't match its start and end
+    EntryDurationMismatch { index: usize },
+    /// `total_time_seconds` isn't the sum of the finished time entries
+    TotalTimeMismatch { recorded: i64, actual: i64 },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::DuplicateId => write!(f, "duplicate task ID"),
+            Problem::ShortIdCollision { other } => {
+                write!(f, "short ID is shared with task {}", other)
+            }
+            Problem::Orphan { parent_id } => write!(f, "parent {} does not exist", parent_id),
+            Problem::ParentInTrash { parent_id } => {
+                write!(f, "parent {} is in the trash", parent_id)
+            }
+            Problem::ParentCycle => write!(f, "parent chain loops back to itself"),
+            Problem::StatusMismatch { detail } => write!(f, "{}", detail),
+            Problem::MultipleActiveEntries { count } => {
+                write!(f, "{} time entries running at once", count)
+            }
+            Problem::EntryDurationMismatch { index } => write!(
+                f,
+                "time entry #{} duration doesn't match its start and end",
+                index + 1
+            ),
+            Problem::TotalTimeMismatch { recorded, actual } => write!(
+                f,
+                "total time is {}s but time entries add up to {}s",
+                recorded, actual
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub task_id: Uuid,
+    pub title: String,
+    pub problem: Problem,
+}
+
+impl Issue {
+    fn new(task: &Task, problem: Problem) -> Self {
+        Self {
+            task_id: task.id,
+            title: task.title.clone(),
+            problem,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}]: {}",
+            self.title,
+            &self.task_id.to_string()[..8],
+            self.problem
+        )
+    }
+}
+
+/// Find every problem in `tasks` without changing them.
+pub fn check(tasks: &[Task]) -> Vec<Issue> {
+    repair(&mut tasks.to_vec())
+}
+
+/// Fix every problem in `tasks`, returning what was wrong.
+///
+/// Checks run in order and each sees the repairs made by the ones before it, so
+/// e.g. cycles are only looked for once orphans have been detached.
+pub fn repair(tasks: &mut Vec<Task>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    fix_duplicate_ids(tasks, &mut issues);
+    fix_short_id_collisions(tasks, &mut issues);
+    fix_orphans(tasks, &mut issues);
+    fix_cycles(tasks, &mut issues);
+    for task in tasks.iter_mut() {
+        fix_status_timestamps(task, &mut issues);
+        fix_active_entries(task, &mut issues);
+        fix_durations(task, &mut issues);
+    }
+    issues
+}
+
+/// Exact copies are dropped; differing tasks that share an ID get a new one.
+fn fix_duplicate_ids(tasks: &mut Vec<Task>, issues: &mut Vec<Issue>) {
+    let mut seen: HashMap<Uuid, Option<String>> = HashMap::new();
+    let mut i = 0;
+    while i < tasks.len() {
+        let serialized = serde_json::to_string(&tasks[i]).ok();
+        match seen.get(&tasks[i].id) {
+            None => {
+                seen.insert(tasks[i].id, serialized);
+                i += 1;
+            }
+            Some(first) => {
+                issues.push(Issue::new(&tasks[i], Problem::DuplicateId));
+                if *first == serialized {
+                    tasks.remove(i);
+                } else {
+                    tasks[i].id = Uuid::new_v4();
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+/// The later of two colliding tasks gets a new ID, and its children follow it.
+fn fix_short_id_collisions(tasks: &mut [Task], issues: &mut Vec<Issue>) {
+    let mut owners: HashMap<String, Uuid> = HashMap::new();
+    for i in 0..tasks.len() {
+        let short_id = tasks[i].short_id();
+        let other = match owners.get(&short_id) {
+            Some(&other) => other,
+            None => {
+                owners.insert(short_id, tasks[i].id);
+                continue;
+            }
+        };
+
+        issues.push(Issue::new(&tasks[i], Problem::ShortIdCollision { other }));
+        let old_id = tasks[i].id;
+        let new_id = loop {
+            let id = Uuid::new_v4();
+            if !owners.contains_key(&id.to_string()[..8]) {
+                break id;
+            }
+        };
+        tasks[i].id = new_id;
+        owners.insert(tasks[i].short_id(), new_id);
+        for task in tasks.iter_mut() {
+            if task.parent_id == Some(old_id) {
+                task.parent_id = Some(new_id);
+            }
+        }
+    }
+}
+
+/// Orphans, and live tasks whose parent is in the trash, become root tasks.
+fn fix_orphans(tasks: &mut [Task], issues: &mut Vec<Issue>) {
+    let ids: HashSet<Uuid> = tasks.iter().map(|t| t.id).collect();
+    let live: HashSet<Uuid> = tasks
+        .iter()
+        .filter(|t| !t.is_deleted())
+        .map(|t| t.id)
+        .collect();
+    for task in tasks.iter_mut() {
+        let Some(parent_id) = task.parent_id else {
+            continue;
+        };
+        let problem = if !ids.contains(&parent_id) {
+            Problem::Orphan { parent_id }
+        } else if !task.is_deleted() && !live.contains(&parent_id) {
+            Problem::ParentInTrash { parent_id }
+        } else {
+            continue;
+        };
+        issues.push(Issue::new(task, problem));
+        task.parent_id = None;
+    }
+}
+
+/// Each cycle is broken by making the first task found on it a root task.
+fn fix_cycles(tasks: &mut [Task], issues: &mut Vec<Issue>) {
+    let mut parents: HashMap<Uuid, Option<Uuid>> =
+        tasks.iter().map(|t| (t.id, t.parent_id)).collect();
+
+    for i in 0..tasks.len() {
+        let mut visited = HashSet::new();
+        let mut current = Some(tasks[i].id);
+        while let Some(id) = current {
+            if !visited.insert(id) {
+                if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
+                    issues.push(Issue::new(task, Problem::ParentCycle));
+                    task.parent_id = None;
+                }
+                parents.insert(id, None);
+                break;
+            }
+            current = parents.get(&id).copied().flatten();
+        }
+    }
+}
+
+fn fix_status_timestamps(task: &mut Task, issues: &mut Vec<Issue>) {
+    let mut mismatch = |task: &Task, detail| {
+        issues.push(Issue::new(task, Problem::StatusMismatch { detail }));
+    };
+
+    if task.status == TaskStatus::NotStarted && !task.time_entries.is_empty() {
+        mismatch(task, "has tracked time but is not started");
+        task.status = TaskStatus::InProgress;
+    }
+    if task.status != TaskStatus::NotStarted
+        && task.started_at.is_none()
+        && !task.time_entries.is_empty()
+    {
+        mismatch(task, "has tracked time but no start time");
+        task.started_at = task.time_entries.iter().map(|e| e.start).min();
+    }
+
+    if task.status == TaskStatus::Completed && task.completed_at.is_none() {
+        mismatch(task, "is completed but has no completion time");
+        task.completed_at = Some(latest_activity(task));
+    }
+    if task.status != TaskStatus::Completed && task.completed_at.is_some() {
+        mismatch(task, "has a completion time but is not completed");
+        task.completed_at = None;
+    }
+    if task.status == TaskStatus::Cancelled && task.cancelled_at.is_none() {
+        mismatch(task, "is cancelled but has no cancellation time");
+        task.cancelled_at = Some(latest_activity(task));
+    }
+    if task.status != TaskStatus::Cancelled && task.cancelled_at.is_some() {
+        mismatch(task, "has a cancellation time but is not cancelled");
+        task.cancelled_at = None;
+    }
+
+    let closed_at = match task.status {
+        TaskStatus::Completed => task.completed_at,
+        TaskStatus::Cancelled => task.cancelled_at,
+        _ => None,
+    };
+    if let Some(closed_at) = closed_at {
+        if task.has_active_time_entry() {
+            mismatch(task, "is closed but still has a running timer");
+            for entry in task.time_entries.iter_mut().filter(|e| e.is_active()) {
+                entry.end_entry(closed_at.max(entry.start));
+                task.total_time_seconds += entry.duration_seconds.unwrap_or(0);
+            }
+        }
+    }
+}
+
+/// All but the most recently started running entry are ended when it started.
+fn fix_active_entries(task: &mut Task, issues: &mut Vec<Issue>) {
+    let active: Vec<usize> = (0..task.time_entries.len())
+        .filter(|&i| task.time_entries[i].is_active())
+        .collect();
+    if active.len() < 2 {
+        return;
+    }
+
+    issues.push(Issue::new(
+        task,
+        Problem::MultipleActiveEntries {
+            count: active.len(),
+        },
+    ));
+    let keep = active
+        .iter()
+        .copied()
+        .max_by_key(|&i| task.time_entries[i].start)
+        .unwrap();
+    let end = task.time_entries[keep].start;
+    for i in active.into_iter().filter(|&i| i != keep) {
+        let entry = &mut task.time_entries[i];
+        entry.end_entry(end.max(entry.start));
+        task.total_time_seconds += entry.duration_seconds.unwrap_or(0);
+    }
+}
+
+fn fix_durations(task: &mut Task, issues: &mut Vec<Issue>) {
+    for index in 0..task.time_entries.len() {
+        let entry = &task.time_entries[index];
+        let expected = entry
+            .end
+            .map(|end| (end - entry.start).num_seconds().max(0));
+        let ends_before_start = entry.end.is_some_and(|end| end < entry.start);
+        if entry.duration_seconds != expected || ends_before_start {
+            issues.push(Issue::new(task, Problem::EntryDurationMismatch { index }));
+            let entry = &mut task.time_entries[index];
+            if ends_before_start {
+                entry.end = Some(entry.start);
+            }
+            entry.duration_seconds = expected;
+        }
+    }
+
+    let actual: i64 = task
+        .time_entries
+        .iter()
+        .filter_map(|e| e.duration_seconds)
+        .sum();
+    if task.total_time_seconds != actual {
+        issues.push(Issue::new(
+            task,
+            Problem::TotalTimeMismatch {
+                recorded: task.total_time_seconds,
+                actual,
+            },
+        ));
+        task.total_time_seconds = actual;
+    }
+}
+
+/// The last moment anything is known to have happened to `task`.
+fn latest_activity(task: &Task) -> DateTime<Utc> {
+    task.time_entries
+        .iter()
+        .map(|e| e.end.unwrap_or(e.start))
+        .chain(task.started_at)
+        .chain(Some(task.created_at))
+        .max()
+        .unwrap_or(task.created_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::task::TimeEntry;
+    use chrono::Duration;
+
+    #[test]
+    fn test_repairs_orphans_and_cycles() {
+        let mut a = Task::new("A".to_string());
+        let mut b = Task::new("B".to_string());
+        let mut orphan = Task::new("Orphan".to_string());
+        a.parent_id = Some(b.id);
+        b.parent_id = Some(a.id);
+        orphan.parent_id = Some(Uuid::new_v4());
+        let mut tasks = vec![a, b, orphan];
+
+        let issues = repair(&mut tasks);
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|i| i.problem == Problem::ParentCycle)
+                .count(),
+            1
+        );
+        assert!(matches!(issues[0].problem, Problem::Orphan { .. }));
+        assert!(tasks[2].parent_id.is_none());
+        assert_eq!(tasks.iter().filter(|t| t.parent_id.is_none()).count(), 2);
+        assert!(check(&tasks).is_empty());
+    }
+
+    #[test]
+    fn test_repairs_time_tracking() {
+        let mut task = Task::new("Timed".to_string());
+        task.start();
+        let start = task.time_entries[0].start;
+        task.time_entries
+            .push(TimeEntry::new(start + Duration::seconds(60)));
+        task.total_time_seconds = 999;
+        let mut tasks = vec![task.clone(), task];
+
+        let issues = repair(&mut tasks);
+        assert!(issues
+            .iter()
+            .any(|i| i.problem == Problem::MultipleActiveEntries { count: 2 }));
+        assert!(issues.iter().any(|i| i.problem == Problem::DuplicateId));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].total_time_seconds, 60);
+        assert_eq!(
+            tasks[0]
+                .time_entries
+                .iter()
+                .filter(|e| e.is_active())
+                .count(),
+            1
+        );
+        assert!(check(&tasks).is_empty());
+    }
+}
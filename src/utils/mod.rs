@@ -1,4 +1,7 @@
 pub mod date;
+pub mod filter;
+pub mod integrity;
+pub mod task_ref;
 pub mod tree;
 
 pub use date::{format_datetime, parse_date};
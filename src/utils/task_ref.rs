@@ -0,0 +1,174 @@
+use crate::models::Task;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Find the task `reference` points to among `tasks`.
+///
+/// A reference is tried, in order, as a full UUID, a unique ID prefix of at least
+/// `min_prefix` characters, an exact title (ignoring case) and finally a unique
+/// part of a title. The minimum keeps short words like "add" or "cafe" matching
+/// titles rather than IDs.
+pub fn resolve_task<'a>(
+    tasks: &[&'a Task],
+    reference: &str,
+    min_prefix: usize,
+) -> Result<&'a Task> {
+    let reference = reference.trim();
+    if reference.is_empty() {
+        bail!("Empty task reference");
+    }
+
+    if let Ok(id) = Uuid::parse_str(reference) {
+        return match tasks.iter().find(|t| t.id == id) {
+            Some(task) => Ok(task),
+            None => bail!("No task with ID {}", id),
+        };
+    }
+
+    let prefix = reference.to_lowercase();
+    let looks_like_id = prefix.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+    if looks_like_id && prefix.len() >= min_prefix.max(1) {
+        let matches = filter(tasks, |t| t.id.to_string().starts_with(&prefix));
+        if !matches.is_empty() {
+            return unique(matches, reference);
+        }
+    }
+
+    let title = reference.to_lowercase();
+    let exact = filter(tasks, |t| t.title.to_lowercase() == title);
+    if !exact.is_empty() {
+        return unique(exact, reference);
+    }
+
+    let partial = filter(tasks, |t| t.title.to_lowercase().contains(&title));
+    if partial.is_empty() {
+        if looks_like_id && prefix.len() < min_prefix {
+            bail!(
+                "No task matches '{}' (ID prefixes need at least {} characters)",
+                reference,
+                min_prefix
+            );
+        }
+        bail!("No task matches '{}'", reference);
+    }
+    unique(partial, reference)
+}
+
+fn filter<'a>(tasks: &[&'a Task], predicate: impl Fn(&Task) -> bool) -> Vec<&'a Task> {
+    tasks.iter().copied().filter(|t| predicate(t)).collect()
+}
+
+fn unique<'a>(matches: Vec<&'a Task>, reference: &str) -> Result<&'a Task> {
+    if let [task] = matches[..] {
+        return Ok(task);
+    }
+
+    let candidates: Vec<String> = matches
+        .iter()
+        .map(|t| format!("  {}  {}", t.short_id(), t.title))
+        .collect();
+    bail!(
+        "'{}' matches {} tasks:\n{}\nUse a longer ID prefix or the full ID",
+        reference,
+        matches.len(),
+        candidates.join("\n")
+    )
+}
+
+/// The shortest prefix of each task's ID that tells it apart from every other
+/// task given, as displayed by `list`, `tree` and the TUI (git-style).
+pub struct IdPrefixes {
+    prefixes: HashMap<Uuid, String>,
+}
+
+impl IdPrefixes {
+    pub fn new(tasks: &[&Task], min_len: usize) -> Self {
+        let mut ids: Vec<String> = tasks.iter().map(|t| t.id.to_string()).collect();
+        ids.sort();
+        ids.dedup();
+
+        let common =
+            |a: &str, b: &str| a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+        let mut prefixes = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            let before = i.checked_sub(1).map_or(0, |j| common(id, &ids[j]));
+            let after = ids.get(i + 1).map_or(0, |next| common(id, next));
+            let mut len = (before.max(after) + 1).max(min_len).min(id.len());
+            // Don't end on one of the UUID's hyphens
+            if id.as_bytes()[len - 1] == b'-' {
+                len += 1;
+            }
+            if let Ok(uuid) = Uuid::parse_str(id) {
+                prefixes.insert(uuid, id[..len].to_string());
+            }
+        }
+        Self { prefixes }
+    }
+
+    /// The prefix to show for `task`, or its 8-character short ID if it wasn't
+    /// among the tasks this was built from.
+    pub fn get(&self, task: &Task) -> String {
+        self.prefixes
+            .get(&task.id)
+            .cloned()
+            .unwrap_or_else(|| task.short_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_ids_and_titles() {
+        let write = Task::new("Write report".to_string());
+        let review = Task::new("Review report".to_string());
+        let tasks = vec![&write, &review];
+
+        let id = write.id.to_string();
+        assert_eq!(resolve_task(&tasks, &id, 4).unwrap().id, write.id);
+        assert_eq!(
+            resolve_task(&tasks, &write.short_id(), 4).unwrap().id,
+            write.id
+        );
+        assert_eq!(resolve_task(&tasks, &id[..6], 4).unwrap().id, write.id);
+        assert_eq!(
+            resolve_task(&tasks, "review REPORT", 4).unwrap().id,
+            review.id
+        );
+        assert_eq!(resolve_task(&tasks, "write", 4).unwrap().id, write.id);
+
+        let err = resolve_task(&tasks, "report", 4).unwrap_err().to_string();
+        assert!(err.contains("matches 2 tasks"));
+        assert!(resolve_task(&tasks, "missing", 4).is_err());
+        assert!(resolve_task(&tasks, &Uuid::new_v4().to_string(), 4).is_err());
+    }
+
+    #[test]
+    fn test_prefixes_are_shortest_unique() {
+        let mut tasks: Vec<Task> = ["a", "b", "c"]
+            .iter()
+            .map(|title| Task::new(title.to_string()))
+            .collect();
+        tasks[0].id = Uuid::parse_str("12345678-0000-4000-8000-000000000000").unwrap();
+        tasks[1].id = Uuid::parse_str("12345679-0000-4000-8000-000000000000").unwrap();
+        tasks[2].id = Uuid::parse_str("abcdef00-0000-4000-8000-000000000000").unwrap();
+        let refs: Vec<&Task> = tasks.iter().collect();
+
+        let prefixes = IdPrefixes::new(&refs, 4);
+        assert_eq!(prefixes.get(&tasks[0]), "12345678");
+        assert_eq!(prefixes.get(&tasks[1]), "12345679");
+        assert_eq!(prefixes.get(&tasks[2]), "abcd");
+        assert_eq!(IdPrefixes::new(&refs, 1).get(&tasks[2]), "a");
+
+        // Each displayed prefix resolves back to its task; a shared one doesn't
+        for task in &tasks {
+            let prefix = prefixes.get(task);
+            assert_eq!(resolve_task(&refs, &prefix, 4).unwrap().id, task.id);
+        }
+        let err = resolve_task(&refs, "1234567", 4).unwrap_err().to_string();
+        assert!(err.contains("matches 2 tasks"));
+        assert!(resolve_task(&refs, "abc", 4).is_err());
+    }
+}
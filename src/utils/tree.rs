@@ -1,5 +1,6 @@
-use crate::models::{Task, TaskStatus};
-use crate::storage::Storage;
+use crate::models::{Task, TaskStatus, WorkCalendar};
+use crate::storage::TaskStore;
+use crate::utils::task_ref::IdPrefixes;
 
 pub struct TreeNode {
     pub task: Task,
@@ -7,7 +8,7 @@ pub struct TreeNode {
 }
 
 impl TreeNode {
-    pub fn build_forest(storage: &Storage) -> Vec<TreeNode> {
+    pub fn build_forest(storage: &dyn TaskStore) -> Vec<TreeNode> {
         let root_tasks = storage.get_root_tasks();
         root_tasks
             .into_iter()
@@ -15,7 +16,7 @@ impl TreeNode {
             .collect()
     }
 
-    fn build_tree(task: &Task, storage: &Storage) -> TreeNode {
+    fn build_tree(task: &Task, storage: &dyn TaskStore) -> TreeNode {
         let children_tasks = storage.get_children(task.id);
         let children = children_tasks
             .into_iter()
@@ -27,18 +28,37 @@ impl TreeNode {
             children,
         }
     }
+
+    /// Prune `forest` to the tasks `keep` accepts, plus their ancestors so they
+    /// still show where they belong.
+    pub fn retain(forest: Vec<TreeNode>, keep: &impl Fn(&Task) -> bool) -> Vec<TreeNode> {
+        forest
+            .into_iter()
+            .filter_map(|mut node| {
+                node.children = Self::retain(std::mem::take(&mut node.children), keep);
+                (keep(&node.task) || !node.children.is_empty()).then_some(node)
+            })
+            .collect()
+    }
 }
 
-pub fn format_tree(forest: &[TreeNode]) -> Vec<String> {
+pub fn format_tree(forest: &[TreeNode], ids: &IdPrefixes, calendar: &WorkCalendar) -> Vec<String> {
     let mut lines = Vec::new();
     for (i, node) in forest.iter().enumerate() {
         let is_last = i == forest.len() - 1;
-        format_tree_node(node, "", is_last, &mut lines);
+        format_tree_node(node, ids, calendar, "", is_last, &mut lines);
     }
     lines
 }
 
-fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool, lines: &mut Vec<String>) {
+fn format_tree_node(
+    node: &TreeNode,
+    ids: &IdPrefixes,
+    calendar: &WorkCalendar,
+    prefix: &str,
+    is_last: bool,
+    lines: &mut Vec<String>,
+) {
     let connector = if is_last { "└─" } else { "├─" };
     let status_icon = match node.task.status {
         TaskStatus::NotStarted => "○",
@@ -48,12 +68,12 @@ fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool, lines: &mut Ve
     };
 
     let time_info = if node.task.total_time_seconds > 0 {
-        format!(" [{}]", node.task.get_formatted_total_time())
+        format!(" [{}]", node.task.get_formatted_total_time(calendar))
     } else {
         String::new()
     };
 
-    let estimate_info = if let Some(est) = node.task.get_formatted_estimate() {
+    let estimate_info = if let Some(est) = node.task.get_formatted_estimate(calendar) {
         format!(" (~{})", est)
     } else {
         String::new()
@@ -73,13 +93,19 @@ fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool, lines: &mut Ve
         String::new()
     };
 
+    let delegated_info = match &node.task.delegated_to {
+        Some(link) => format!(" → {}", link.owner),
+        None => String::new(),
+    };
+
     lines.push(format!(
-        "{}{} {} {} [{}]{}{}{}",
+        "{}{} {} {}{} [{}]{}{}{}",
         prefix,
         connector,
         status_icon,
         node.task.title,
-        node.task.short_id(),
+        delegated_info,
+        ids.get(&node.task),
         time_info,
         estimate_info,
         tags_info
@@ -88,6 +114,6 @@ fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool, lines: &mut Ve
     let child_prefix = format!("{}{}", prefix, if is_last { "  " } else { "│ " });
     for (i, child) in node.children.iter().enumerate() {
         let child_is_last = i == node.children.len() - 1;
-        format_tree_node(child, &child_prefix, child_is_last, lines);
+        format_tree_node(child, ids, calendar, &child_prefix, child_is_last, lines);
     }
 }